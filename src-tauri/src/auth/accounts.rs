@@ -0,0 +1,401 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::auth::auth_storage::{write_private_json, AuthDotJson};
+use crate::auth::credential_store::{
+    encrypted_store_file, open_json, seal_json, unlocked_backend, CredentialStore, CredentialStoreError, EncryptedStore,
+    PlaintextStore, SecretBackend,
+};
+
+/// Serializes vault updates; switching rewrites both the vault and the live
+/// credentials.
+static VAULT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, thiserror::Error)]
+pub enum AccountError {
+    #[error("Account '{0}' not found")]
+    NotFound(String),
+    #[error("Account name must not be empty")]
+    InvalidName,
+    #[error("No credentials to save; log in first")]
+    NoCredentials,
+    #[error("{0}")]
+    Storage(String),
+    #[error(transparent)]
+    Store(#[from] CredentialStoreError),
+}
+
+impl From<Box<dyn std::error::Error>> for AccountError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        AccountError::Storage(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredAccount {
+    #[serde(flatten)]
+    auth: AuthDotJson,
+    added_at: DateTime<Utc>,
+}
+
+/// `accounts.json`, or `accounts.enc` once credentials are encrypted: named
+/// credential sets, one of which is the live login.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AccountVault {
+    active: Option<String>,
+    #[serde(default)]
+    accounts: BTreeMap<String, StoredAccount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    ApiKey,
+    Chatgpt,
+}
+
+/// What the UI sees of an account; never includes the secret itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSummary {
+    pub name: String,
+    pub kind: AccountKind,
+    pub email: Option<String>,
+    pub plan: Option<String>,
+    pub added_at: DateTime<Utc>,
+    pub active: bool,
+}
+
+fn vault_file(codex_home: &Path) -> PathBuf {
+    codex_home.join("accounts.json")
+}
+
+fn sealed_vault_file(codex_home: &Path) -> PathBuf {
+    codex_home.join("accounts.enc")
+}
+
+/// Where the vault and the live credentials are kept: auth.json and
+/// accounts.json, or their sealed counterparts once the encrypted
+/// credential store exists.
+enum Storage {
+    Plaintext(PathBuf),
+    Sealed {
+        codex_home: PathBuf,
+        backend: Arc<dyn SecretBackend>,
+    },
+}
+
+impl Storage {
+    fn open(codex_home: &Path, backend: Option<Arc<dyn SecretBackend>>) -> Result<Self, AccountError> {
+        if encrypted_store_file(codex_home).exists() || sealed_vault_file(codex_home).exists() {
+            Ok(Storage::Sealed {
+                codex_home: codex_home.to_path_buf(),
+                backend: unlocked_backend(backend)?,
+            })
+        } else {
+            Ok(Storage::Plaintext(codex_home.to_path_buf()))
+        }
+    }
+
+    fn live(&self) -> Box<dyn CredentialStore> {
+        match self {
+            Storage::Plaintext(codex_home) => Box::new(PlaintextStore::new(codex_home)),
+            Storage::Sealed { codex_home, backend } => Box::new(EncryptedStore::new(codex_home, backend.clone())),
+        }
+    }
+
+    fn load_vault(&self) -> Result<AccountVault, AccountError> {
+        let codex_home = match self {
+            Storage::Sealed { codex_home, backend } => {
+                if let Some(vault) = open_json(&sealed_vault_file(codex_home), backend)? {
+                    return Ok(vault);
+                }
+                // Vaults saved before encryption was enabled are sealed on the next save
+                codex_home
+            }
+            Storage::Plaintext(codex_home) => codex_home,
+        };
+        let path = vault_file(codex_home);
+        if !path.exists() {
+            return Ok(AccountVault::default());
+        }
+        let contents = std::fs::read_to_string(&path).map_err(|e| AccountError::Storage(e.to_string()))?;
+        serde_json::from_str(&contents).map_err(|e| AccountError::Storage(format!("Invalid accounts.json: {}", e)))
+    }
+
+    fn save_vault(&self, vault: &AccountVault) -> Result<(), AccountError> {
+        match self {
+            Storage::Plaintext(codex_home) => Ok(write_private_json(&vault_file(codex_home), vault)?),
+            Storage::Sealed { codex_home, backend } => {
+                seal_json(&sealed_vault_file(codex_home), backend, vault)?;
+                let plaintext = vault_file(codex_home);
+                if plaintext.exists() {
+                    std::fs::remove_file(plaintext).map_err(|e| AccountError::Storage(e.to_string()))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Copies the live credentials back into the active account when they
+/// still hold the same ChatGPT identity, so tokens the CLI or a refresh
+/// rotated since the last switch aren't lost. A different login is left alone.
+fn sync_active(storage: &Storage, vault: &mut AccountVault) -> Result<(), AccountError> {
+    let Some(active) = vault.active.clone() else {
+        return Ok(());
+    };
+    let (Some(account), Some(current)) = (vault.accounts.get_mut(&active), storage.live().load()?) else {
+        return Ok(());
+    };
+    let same_identity = match (&account.auth.tokens, &current.tokens) {
+        (Some(stored), Some(live)) => {
+            stored.account_id == live.account_id && stored.id_token.email == live.id_token.email
+        }
+        _ => false,
+    };
+    if same_identity && account.auth.openai_api_key == current.openai_api_key {
+        account.auth = current;
+    }
+    Ok(())
+}
+
+pub fn list_accounts(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+) -> Result<Vec<AccountSummary>, AccountError> {
+    let vault = Storage::open(codex_home, backend)?.load_vault()?;
+    Ok(vault
+        .accounts
+        .iter()
+        .map(|(name, account)| {
+            let id_token = account.auth.tokens.as_ref().map(|t| &t.id_token);
+            AccountSummary {
+                name: name.clone(),
+                kind: if account.auth.openai_api_key.as_deref().is_some_and(|k| !k.is_empty()) {
+                    AccountKind::ApiKey
+                } else {
+                    AccountKind::Chatgpt
+                },
+                email: id_token.and_then(|t| t.email.clone()),
+                plan: id_token.and_then(|t| t.get_chatgpt_plan_type()),
+                added_at: account.added_at,
+                active: vault.active.as_deref() == Some(name.as_str()),
+            }
+        })
+        .collect())
+}
+
+/// Stores the credentials currently logged in under `name` and marks that
+/// account active. Saving over an existing name replaces it.
+pub fn save_current_account(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    name: &str,
+) -> Result<(), AccountError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AccountError::InvalidName);
+    }
+    let _guard = VAULT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let storage = Storage::open(codex_home, backend)?;
+
+    let current = storage
+        .live()
+        .load()?
+        .filter(|auth| auth.tokens.is_some() || auth.openai_api_key.as_deref().is_some_and(|k| !k.is_empty()))
+        .ok_or(AccountError::NoCredentials)?;
+
+    let mut vault = storage.load_vault()?;
+    if vault.active.as_deref() != Some(name) {
+        sync_active(&storage, &mut vault)?;
+    }
+    vault.accounts.insert(
+        name.to_string(),
+        StoredAccount {
+            auth: current,
+            added_at: Utc::now(),
+        },
+    );
+    vault.active = Some(name.to_string());
+    storage.save_vault(&vault)
+}
+
+/// Makes `name` the active account by writing its credentials to auth.json,
+/// or to the encrypted store when credentials are encrypted.
+pub fn switch_account(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    name: &str,
+) -> Result<(), AccountError> {
+    let _guard = VAULT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let storage = Storage::open(codex_home, backend)?;
+
+    let mut vault = storage.load_vault()?;
+    if !vault.accounts.contains_key(name) {
+        return Err(AccountError::NotFound(name.to_string()));
+    }
+    sync_active(&storage, &mut vault)?;
+
+    storage.live().save(&vault.accounts[name].auth)?;
+    vault.active = Some(name.to_string());
+    storage.save_vault(&vault)
+}
+
+/// The API key saved under `name`, without making it active. Lets a single
+/// session use another account's key.
+pub fn stored_api_key(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    name: &str,
+) -> Result<Option<String>, AccountError> {
+    let vault = Storage::open(codex_home, backend)?.load_vault()?;
+    let account = vault
+        .accounts
+        .get(name)
+        .ok_or_else(|| AccountError::NotFound(name.to_string()))?;
+    Ok(account.auth.openai_api_key.clone().filter(|k| !k.is_empty()))
+}
+
+/// Deletes a stored account. The live credentials are left alone, so
+/// removing the active account doesn't log the user out.
+pub fn remove_account(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    name: &str,
+) -> Result<(), AccountError> {
+    let _guard = VAULT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let storage = Storage::open(codex_home, backend)?;
+
+    let mut vault = storage.load_vault()?;
+    if vault.accounts.remove(name).is_none() {
+        return Err(AccountError::NotFound(name.to_string()));
+    }
+    if vault.active.as_deref() == Some(name) {
+        vault.active = None;
+    }
+    storage.save_vault(&vault)
+}
+
+/// Re-saves a plaintext accounts.json into accounts.enc after the
+/// credential store has been encrypted. Returns false when there was none.
+pub fn seal_vault(codex_home: &Path, backend: Arc<dyn SecretBackend>) -> Result<bool, AccountError> {
+    let _guard = VAULT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if !vault_file(codex_home).exists() {
+        return Ok(false);
+    }
+    let storage = Storage::Sealed {
+        codex_home: codex_home.to_path_buf(),
+        backend,
+    };
+    let vault = storage.load_vault()?;
+    storage.save_vault(&vault)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::auth_storage::{get_auth_file, login_with_api_key, read_auth_file, save_tokens};
+    use crate::auth::credential_store::{migrate_auth_json, PassphraseBackend};
+    use crate::auth::token_data::TokenData;
+    use tempfile::tempdir;
+
+    fn api_key_in_auth_file(codex_home: &Path) -> Option<String> {
+        read_auth_file(codex_home).unwrap().and_then(|auth| auth.openai_api_key)
+    }
+
+    fn login_chatgpt(codex_home: &Path, email: &str, refresh_token: &str) {
+        use base64::Engine;
+        let payload = serde_json::json!({ "email": email }).to_string();
+        let id_token = format!(
+            "e30.{}.sig",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(payload)
+        );
+        let tokens: TokenData = serde_json::from_value(serde_json::json!({
+            "id_token": id_token,
+            "access_token": "access",
+            "refresh_token": refresh_token,
+        }))
+        .unwrap();
+        save_tokens(codex_home, None, &tokens).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_switch_accounts() {
+        let dir = tempdir().unwrap();
+        login_chatgpt(dir.path(), "me@example.com", "refresh-1");
+        save_current_account(dir.path(), None, "personal").unwrap();
+        std::fs::remove_file(get_auth_file(dir.path())).unwrap();
+        login_with_api_key(dir.path(), None, "sk-work").unwrap();
+        save_current_account(dir.path(), None, "work").unwrap();
+
+        let accounts = list_accounts(dir.path(), None).unwrap();
+        let names: Vec<(&str, bool)> = accounts.iter().map(|a| (a.name.as_str(), a.active)).collect();
+        assert_eq!(names, vec![("personal", false), ("work", true)]);
+        assert_eq!(accounts[0].kind, AccountKind::Chatgpt);
+        assert_eq!(accounts[0].email.as_deref(), Some("me@example.com"));
+        assert_eq!(accounts[1].kind, AccountKind::ApiKey);
+
+        switch_account(dir.path(), None, "personal").unwrap();
+        assert_eq!(api_key_in_auth_file(dir.path()), None);
+
+        // Tokens rotated while the account is active are kept on switching away.
+        login_chatgpt(dir.path(), "me@example.com", "refresh-2");
+        switch_account(dir.path(), None, "work").unwrap();
+        assert_eq!(api_key_in_auth_file(dir.path()).as_deref(), Some("sk-work"));
+        switch_account(dir.path(), None, "personal").unwrap();
+        let tokens = read_auth_file(dir.path()).unwrap().unwrap().tokens.unwrap();
+        assert_eq!(tokens.refresh_token, "refresh-2");
+    }
+
+    #[test]
+    fn test_remove_and_errors() {
+        let dir = tempdir().unwrap();
+        assert!(matches!(save_current_account(dir.path(), None, "none"), Err(AccountError::NoCredentials)));
+
+        login_with_api_key(dir.path(), None, "sk-test").unwrap();
+        assert!(matches!(save_current_account(dir.path(), None, "  "), Err(AccountError::InvalidName)));
+        save_current_account(dir.path(), None, "test").unwrap();
+
+        assert!(matches!(switch_account(dir.path(), None, "missing"), Err(AccountError::NotFound(_))));
+        remove_account(dir.path(), None, "test").unwrap();
+        assert!(list_accounts(dir.path(), None).unwrap().is_empty());
+        assert_eq!(api_key_in_auth_file(dir.path()).as_deref(), Some("sk-test"));
+        assert!(matches!(remove_account(dir.path(), None, "test"), Err(AccountError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_encrypted_vault() {
+        let dir = tempdir().unwrap();
+        login_with_api_key(dir.path(), None, "sk-personal").unwrap();
+        save_current_account(dir.path(), None, "personal").unwrap();
+        login_with_api_key(dir.path(), None, "sk-work").unwrap();
+        save_current_account(dir.path(), None, "work").unwrap();
+
+        let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new("pw"));
+        migrate_auth_json(dir.path(), &EncryptedStore::new(dir.path(), backend.clone())).unwrap();
+        assert!(seal_vault(dir.path(), backend.clone()).unwrap());
+        assert!(!vault_file(dir.path()).exists());
+        let sealed = std::fs::read_to_string(sealed_vault_file(dir.path())).unwrap();
+        assert!(!sealed.contains("sk-personal"));
+
+        assert!(matches!(
+            list_accounts(dir.path(), None),
+            Err(AccountError::Store(CredentialStoreError::Locked))
+        ));
+        assert_eq!(list_accounts(dir.path(), Some(backend.clone())).unwrap().len(), 2);
+
+        // Switching writes the encrypted store, not auth.json
+        switch_account(dir.path(), Some(backend.clone()), "personal").unwrap();
+        assert!(!get_auth_file(dir.path()).exists());
+        let live = EncryptedStore::new(dir.path(), backend.clone()).load().unwrap().unwrap();
+        assert_eq!(live.openai_api_key.as_deref(), Some("sk-personal"));
+        assert_eq!(
+            stored_api_key(dir.path(), Some(backend), "work").unwrap().as_deref(),
+            Some("sk-work")
+        );
+    }
+}
@@ -1,12 +1,37 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions, remove_file};
+use std::fs::{File, remove_file};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use crate::auth::token_data::{TokenData, AuthMode};
+use crate::auth::credential_store::{
+    encrypted_store_file, live_store, load_live_auth, unlocked_backend, CredentialStore, CredentialStoreError,
+    EncryptedStore, SecretBackend,
+};
+use crate::auth::jwt::{validate_claims, ClaimError, ClaimValidation, CLOCK_SKEW};
+use crate::auth::oauth_server::refresh_access_token;
+use crate::auth::token_data::{parse_jwt_expiry, TokenData, AuthMode};
 use crate::auth::OPENAI_API_KEY_ENV_VAR;
 
+/// Refresh this long before the access token expires.
+const REFRESH_MARGIN_SECS: i64 = 5 * 60;
+/// Refresh opaque tokens (no readable `exp`) after this many days.
+const MAX_TOKEN_AGE_DAYS: i64 = 28;
+
+/// Serializes refreshes so concurrent callers don't spend the same refresh token twice.
+static REFRESH_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshOutcome {
+    /// The stored credentials have no ChatGPT tokens.
+    NoTokens,
+    /// The encrypted store is locked, so the tokens couldn't be checked.
+    Locked,
+    NotNeeded,
+    Refreshed,
+}
+
 #[derive(Debug, Clone)]
 pub struct CodexAuth {
     pub mode: AuthMode,
@@ -19,12 +44,77 @@ impl PartialEq for CodexAuth {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-struct AuthDotJson {
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuthDotJson {
     #[serde(rename = "OPENAI_API_KEY")]
-    openai_api_key: Option<String>,
-    tokens: Option<TokenData>,
-    last_refresh: Option<DateTime<Utc>>,
+    pub openai_api_key: Option<String>,
+    pub tokens: Option<TokenData>,
+    pub last_refresh: Option<DateTime<Utc>>,
+}
+
+/// Where the active credentials were found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthSource {
+    /// `OPENAI_API_KEY` in the environment, which overrides auth.json.
+    EnvVar,
+    File,
+    /// `credentials.enc`, the encrypted credential store.
+    Encrypted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialState {
+    Valid,
+    /// The access token is past its expiry and refreshing didn't help.
+    Expired,
+    /// The tokens are unreadable or were issued for someone else.
+    Invalid,
+    /// The encrypted store hasn't been unlocked, so nothing is known yet.
+    Locked,
+}
+
+/// Snapshot of the current login for the UI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthStatus {
+    /// None while the encrypted store is locked.
+    pub mode: Option<AuthMode>,
+    pub source: AuthSource,
+    pub state: CredentialState,
+    pub email: Option<String>,
+    pub plan: Option<String>,
+    pub account_id: Option<String>,
+    pub token_expires_at: Option<DateTime<Utc>>,
+    pub last_refresh: Option<DateTime<Utc>>,
+}
+
+impl AuthStatus {
+    fn api_key(source: AuthSource) -> Self {
+        Self {
+            mode: Some(AuthMode::ApiKey),
+            source,
+            state: CredentialState::Valid,
+            email: None,
+            plan: None,
+            account_id: None,
+            token_expires_at: None,
+            last_refresh: None,
+        }
+    }
+
+    fn locked() -> Self {
+        Self {
+            mode: None,
+            source: AuthSource::Encrypted,
+            state: CredentialState::Locked,
+            email: None,
+            plan: None,
+            account_id: None,
+            token_expires_at: None,
+            last_refresh: None,
+        }
+    }
 }
 
 impl CodexAuth {
@@ -91,48 +181,183 @@ pub async fn load_auth(codex_home: &Path, _require_valid: bool) -> Result<Option
     Ok(None)
 }
 
-pub fn login_with_api_key(codex_home: &Path, api_key: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let auth_file = get_auth_file(codex_home);
-    
+/// Describes the login the CLI would use, following the same precedence as
+/// `load_auth`, with the encrypted store in place of auth.json once it
+/// exists. ChatGPT ID tokens are checked against `issuer` and `client_id`;
+/// signatures are not verified here. Expiry is judged by the access token,
+/// which refreshes renew, not by the short-lived ID token.
+pub fn load_auth_status(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    issuer: &str,
+    client_id: &str,
+) -> Result<Option<AuthStatus>, Box<dyn std::error::Error>> {
+    if std::env::var(OPENAI_API_KEY_ENV_VAR).is_ok_and(|key| !key.is_empty()) {
+        return Ok(Some(AuthStatus::api_key(AuthSource::EnvVar)));
+    }
+
+    let source = if encrypted_store_file(codex_home).exists() {
+        AuthSource::Encrypted
+    } else {
+        AuthSource::File
+    };
+    let auth_json = match load_live_auth(codex_home, backend) {
+        Ok(Some(auth_json)) => auth_json,
+        Ok(None) => return Ok(None),
+        Err(CredentialStoreError::Locked) => return Ok(Some(AuthStatus::locked())),
+        Err(e) => return Err(e.into()),
+    };
+    let has_api_key = auth_json.openai_api_key.as_deref().is_some_and(|key| !key.is_empty());
+    let Some(tokens) = auth_json.tokens else {
+        return Ok(has_api_key.then(|| AuthStatus::api_key(source)));
+    };
+    if has_api_key && tokens.is_plan_that_should_use_api_key() {
+        return Ok(Some(AuthStatus::api_key(source)));
+    }
+
+    let now = Utc::now();
+    let expected = ClaimValidation {
+        issuer,
+        audience: client_id,
+        now,
+    };
+    // An ID token that merely aged out is checked as of its expiry instead
+    let identity = match validate_claims(&tokens.id_token.raw_jwt, &expected) {
+        Err(ClaimError::Expired(expired_at)) => validate_claims(
+            &tokens.id_token.raw_jwt,
+            &ClaimValidation {
+                now: expired_at,
+                ..expected
+            },
+        ),
+        result => result,
+    };
+    let access_expired = parse_jwt_expiry(&tokens.access_token).is_some_and(|expires_at| expires_at + CLOCK_SKEW < now);
+    let state = match identity {
+        Err(e) => {
+            log::warn!("Stored ID token rejected: {}", e);
+            CredentialState::Invalid
+        }
+        Ok(()) if access_expired => CredentialState::Expired,
+        Ok(()) => CredentialState::Valid,
+    };
+
+    Ok(Some(AuthStatus {
+        mode: Some(AuthMode::ChatGPT),
+        source,
+        state,
+        email: tokens.id_token.email.clone(),
+        plan: tokens.id_token.get_chatgpt_plan_type(),
+        account_id: tokens.account_id.clone(),
+        token_expires_at: parse_jwt_expiry(&tokens.access_token),
+        last_refresh: auth_json.last_refresh,
+    }))
+}
+
+/// Stores `api_key` as the login, in the encrypted store once it exists.
+pub fn login_with_api_key(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    api_key: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
     let auth_json = AuthDotJson {
         openai_api_key: Some(api_key.to_string()),
         tokens: None,
         last_refresh: None,
     };
     
-    save_auth_to_file(&auth_file, &auth_json)?;
+    live_store(codex_home, backend)?.save(&auth_json)?;
     Ok(())
 }
 
-pub fn save_tokens(codex_home: &Path, tokens: &TokenData) -> Result<(), Box<dyn std::error::Error>> {
-    let auth_file = get_auth_file(codex_home);
+/// Stores ChatGPT tokens next to any API key, in the encrypted store once
+/// it exists.
+pub fn save_tokens(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    tokens: &TokenData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = live_store(codex_home, backend)?;
     
-    // Load existing auth data or create new
-    let mut auth_json = if auth_file.exists() {
-        let mut file = File::open(&auth_file)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        serde_json::from_str(&contents).unwrap_or_else(|_| AuthDotJson {
-            openai_api_key: None,
-            tokens: None,
-            last_refresh: None,
-        })
-    } else {
-        AuthDotJson {
-            openai_api_key: None,
-            tokens: None,
-            last_refresh: None,
+    // An unreadable auth.json is replaced; an undecryptable store is not
+    let mut auth_json = match store.load() {
+        Ok(auth_json) => auth_json.unwrap_or_default(),
+        Err(CredentialStoreError::Storage(e)) => {
+            log::warn!("Replacing unreadable auth.json: {}", e);
+            AuthDotJson::default()
         }
+        Err(e) => return Err(e.into()),
     };
     
     auth_json.tokens = Some(tokens.clone());
     auth_json.last_refresh = Some(Utc::now());
     
-    save_auth_to_file(&auth_file, &auth_json)?;
+    store.save(&auth_json)?;
     Ok(())
 }
 
-pub fn logout(codex_home: &Path) -> Result<bool, std::io::Error> {
+/// Refreshes the stored ChatGPT tokens when the access token is close to
+/// expiry. The new tokens and `last_refresh` are written back in a single
+/// atomic replace of whichever store holds them.
+pub fn refresh_tokens_if_needed(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    issuer: &str,
+    client_id: &str,
+) -> Result<RefreshOutcome, Box<dyn std::error::Error>> {
+    let _guard = REFRESH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let store = match live_store(codex_home, backend) {
+        Ok(store) => store,
+        Err(CredentialStoreError::Locked) => return Ok(RefreshOutcome::Locked),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(mut auth_json) = store.load()? else {
+        return Ok(RefreshOutcome::NoTokens);
+    };
+
+    let Some(tokens) = auth_json.tokens.clone() else {
+        return Ok(RefreshOutcome::NoTokens);
+    };
+    if !needs_refresh(&tokens, auth_json.last_refresh, Utc::now()) {
+        return Ok(RefreshOutcome::NotNeeded);
+    }
+
+    let refreshed = refresh_access_token(issuer, client_id, &tokens.refresh_token)?;
+    auth_json.tokens = Some(TokenData {
+        id_token: refreshed.id_token.unwrap_or(tokens.id_token),
+        access_token: refreshed.access_token,
+        refresh_token: refreshed.refresh_token.unwrap_or(tokens.refresh_token),
+        account_id: tokens.account_id,
+    });
+    auth_json.last_refresh = Some(Utc::now());
+
+    store.save(&auth_json)?;
+    Ok(RefreshOutcome::Refreshed)
+}
+
+fn needs_refresh(tokens: &TokenData, last_refresh: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    match parse_jwt_expiry(&tokens.access_token) {
+        Some(expires_at) => expires_at - now < chrono::Duration::seconds(REFRESH_MARGIN_SECS),
+        None => last_refresh
+            .map(|at| now - at > chrono::Duration::days(MAX_TOKEN_AGE_DAYS))
+            .unwrap_or(true),
+    }
+}
+
+/// Forgets the stored login. Returns false when there was none. The
+/// encrypted store is resealed empty rather than deleted, so the next login
+/// stays encrypted.
+pub fn logout(codex_home: &Path, backend: Option<Arc<dyn SecretBackend>>) -> Result<bool, CredentialStoreError> {
+    if encrypted_store_file(codex_home).exists() {
+        let store = EncryptedStore::new(codex_home, unlocked_backend(backend)?);
+        let logged_in = store.load()?.is_some_and(|auth| auth != AuthDotJson::default());
+        if logged_in {
+            store.save(&AuthDotJson::default())?;
+        }
+        return Ok(logged_in);
+    }
+
     let auth_file = get_auth_file(codex_home);
     
     if auth_file.exists() {
@@ -143,33 +368,41 @@ pub fn logout(codex_home: &Path) -> Result<bool, std::io::Error> {
     }
 }
 
-fn save_auth_to_file(auth_file: &Path, auth_json: &AuthDotJson) -> Result<(), Box<dyn std::error::Error>> {
-    // Create directory if it doesn't exist
-    if let Some(parent) = auth_file.parent() {
-        std::fs::create_dir_all(parent)?;
+/// Reads auth.json as-is, without the env var override applied by `load_auth`.
+pub(crate) fn read_auth_file(codex_home: &Path) -> Result<Option<AuthDotJson>, Box<dyn std::error::Error>> {
+    let auth_file = get_auth_file(codex_home);
+    if !auth_file.exists() {
+        return Ok(None);
     }
-    
-    // Set proper permissions (readable only by owner)
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(auth_file)?;
-    
+    let contents = std::fs::read_to_string(&auth_file)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+pub(crate) fn save_auth_to_file(auth_file: &Path, auth_json: &AuthDotJson) -> Result<(), Box<dyn std::error::Error>> {
+    write_private_json(auth_file, auth_json)
+}
+
+/// Atomically replaces `path` with `value` as pretty JSON, readable by the
+/// owner only.
+pub(crate) fn write_private_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+
+    // Write to a temp file in the same directory and rename over it, so readers
+    // never observe a partially written file. NamedTempFile is created 0600.
+    let mut temp = tempfile::NamedTempFile::new_in(parent)?;
+    let json_string = serde_json::to_string_pretty(value)?;
+    temp.write_all(json_string.as_bytes())?;
+    temp.flush()?;
+    temp.as_file().sync_all()?;
+    temp.persist(path)?;
+
     #[cfg(unix)]
     {
-        use std::os::unix::fs::{PermissionsExt, OpenOptionsExt};
-        let mut opts = OpenOptions::new();
-        opts.mode(0o600); // Read/write for owner only
-        if auth_file.exists() {
-            std::fs::set_permissions(auth_file, std::fs::Permissions::from_mode(0o600))?;
-        }
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
     }
-    
-    let json_string = serde_json::to_string_pretty(auth_json)?;
-    file.write_all(json_string.as_bytes())?;
-    file.flush()?;
-    
+
     Ok(())
 }
 
@@ -181,7 +414,7 @@ mod tests {
     #[test]
     fn test_api_key_login() {
         let dir = tempdir().unwrap();
-        let result = login_with_api_key(dir.path(), "sk-test-key");
+        let result = login_with_api_key(dir.path(), None, "sk-test-key");
         assert!(result.is_ok());
         
         let auth_file = get_auth_file(dir.path());
@@ -198,12 +431,223 @@ mod tests {
     #[test]
     fn test_logout() {
         let dir = tempdir().unwrap();
-        login_with_api_key(dir.path(), "sk-test-key").unwrap();
+        login_with_api_key(dir.path(), None, "sk-test-key").unwrap();
         
-        let removed = logout(dir.path()).unwrap();
+        let removed = logout(dir.path(), None).unwrap();
         assert!(removed);
         
         let auth_file = get_auth_file(dir.path());
         assert!(!auth_file.exists());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_encrypted_logout_and_login() {
+        use crate::auth::credential_store::{migrate_auth_json, PassphraseBackend};
+        let dir = tempdir().unwrap();
+        let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new("pass"));
+        login_with_api_key(dir.path(), None, "sk-old").unwrap();
+        migrate_auth_json(dir.path(), &EncryptedStore::new(dir.path(), backend.clone())).unwrap();
+
+        assert!(matches!(logout(dir.path(), None), Err(CredentialStoreError::Locked)));
+        assert!(logout(dir.path(), Some(backend.clone())).unwrap());
+        assert!(!logout(dir.path(), Some(backend.clone())).unwrap());
+        assert_eq!(load_auth_status(dir.path(), Some(backend.clone()), "https://issuer", "client").unwrap(), None);
+
+        // Logging back in keeps the credentials encrypted
+        login_with_api_key(dir.path(), Some(backend.clone()), "sk-new").unwrap();
+        assert!(!get_auth_file(dir.path()).exists());
+        let status = load_auth_status(dir.path(), Some(backend.clone()), "https://issuer", "client").unwrap().unwrap();
+        assert_eq!(status.source, AuthSource::Encrypted);
+        let auth = load_live_auth(dir.path(), Some(backend.clone())).unwrap().unwrap();
+        assert_eq!(auth.openai_api_key.as_deref(), Some("sk-new"));
+    }
+
+    fn jwt(claims: serde_json::Value) -> String {
+        use base64::Engine;
+        let encode = |v: &serde_json::Value| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(v.to_string())
+        };
+        format!("{}.{}.sig", encode(&serde_json::json!({"alg": "none"})), encode(&claims))
+    }
+
+    fn write_tokens(codex_home: &Path, access_exp: i64) {
+        let id_token = jwt(serde_json::json!({"email": "user@example.com"}));
+        let tokens: TokenData = serde_json::from_value(serde_json::json!({
+            "id_token": id_token,
+            "access_token": jwt(serde_json::json!({"exp": access_exp})),
+            "refresh_token": "refresh-1",
+            "account_id": "acct"
+        }))
+        .unwrap();
+        save_tokens(codex_home, None, &tokens).unwrap();
+    }
+
+    /// Mock issuer that answers refresh grants with a fresh access token.
+    fn mock_issuer(new_exp: i64) -> (String, std::sync::mpsc::Receiver<String>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                tx.send(format!("{} {}", request.url(), body)).unwrap();
+                let response = serde_json::json!({
+                    "access_token": jwt(serde_json::json!({"exp": new_exp})),
+                    "refresh_token": "refresh-2"
+                });
+                let _ = request.respond(tiny_http::Response::from_string(response.to_string()));
+            }
+        });
+        (issuer, rx)
+    }
+
+    #[test]
+    fn test_refresh_near_expiry() {
+        let dir = tempdir().unwrap();
+        let now = Utc::now().timestamp();
+        write_tokens(dir.path(), now + 60);
+        let (issuer, requests) = mock_issuer(now + 3600);
+
+        let outcome = refresh_tokens_if_needed(dir.path(), None, &issuer, "client").unwrap();
+        assert_eq!(outcome, RefreshOutcome::Refreshed);
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("/token "));
+        assert!(request.contains("grant_type=refresh_token"));
+        assert!(request.contains("refresh_token=refresh-1"));
+
+        let contents = std::fs::read_to_string(get_auth_file(dir.path())).unwrap();
+        let auth_json: AuthDotJson = serde_json::from_str(&contents).unwrap();
+        let tokens = auth_json.tokens.unwrap();
+        assert_eq!(tokens.refresh_token, "refresh-2");
+        assert_eq!(tokens.id_token.email.as_deref(), Some("user@example.com"));
+        assert_eq!(parse_jwt_expiry(&tokens.access_token).unwrap().timestamp(), now + 3600);
+        assert!(auth_json.last_refresh.is_some());
+
+        // The fresh token is well outside the margin.
+        let outcome = refresh_tokens_if_needed(dir.path(), None, &issuer, "client").unwrap();
+        assert_eq!(outcome, RefreshOutcome::NotNeeded);
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_refresh_encrypted_store() {
+        use crate::auth::credential_store::{migrate_auth_json, PassphraseBackend};
+        let dir = tempdir().unwrap();
+        let now = Utc::now().timestamp();
+        let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new("pass"));
+        write_tokens(dir.path(), now + 60);
+        migrate_auth_json(dir.path(), &EncryptedStore::new(dir.path(), backend.clone())).unwrap();
+        let (issuer, _requests) = mock_issuer(now + 3600);
+
+        assert_eq!(refresh_tokens_if_needed(dir.path(), None, &issuer, "client").unwrap(), RefreshOutcome::Locked);
+        let outcome = refresh_tokens_if_needed(dir.path(), Some(backend.clone()), &issuer, "client").unwrap();
+        assert_eq!(outcome, RefreshOutcome::Refreshed);
+        assert!(!get_auth_file(dir.path()).exists());
+        let tokens = load_live_auth(dir.path(), Some(backend)).unwrap().unwrap().tokens.unwrap();
+        assert_eq!(tokens.refresh_token, "refresh-2");
+    }
+
+    #[test]
+    fn test_refresh_failure_keeps_tokens() {
+        let dir = tempdir().unwrap();
+        write_tokens(dir.path(), Utc::now().timestamp() - 10);
+        let before = std::fs::read_to_string(get_auth_file(dir.path())).unwrap();
+
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let body = r#"{"error":"invalid_grant"}"#;
+                let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(400));
+            }
+        });
+
+        let err = refresh_tokens_if_needed(dir.path(), None, &issuer, "client").unwrap_err();
+        assert!(err.to_string().contains("invalid_grant"));
+        assert_eq!(std::fs::read_to_string(get_auth_file(dir.path())).unwrap(), before);
+
+        login_with_api_key(dir.path(), None, "sk-test-key").unwrap();
+        assert_eq!(
+            refresh_tokens_if_needed(dir.path(), None, &issuer, "client").unwrap(),
+            RefreshOutcome::NoTokens
+        );
+    }
+
+    #[test]
+    fn test_load_auth_status() {
+        let dir = tempdir().unwrap();
+        let now = Utc::now().timestamp();
+        assert_eq!(load_auth_status(dir.path(), None, "https://issuer", "client").unwrap(), None);
+
+        let id_token = jwt(serde_json::json!({
+            "iss": "https://issuer",
+            "aud": "client",
+            "exp": now + 3600,
+            "email": "user@example.com",
+            "https://api.openai.com/auth": { "chatgpt_plan_type": "plus" }
+        }));
+        let tokens: TokenData = serde_json::from_value(serde_json::json!({
+            "id_token": id_token,
+            "access_token": jwt(serde_json::json!({"exp": now + 600})),
+            "refresh_token": "refresh",
+            "account_id": "acct-1"
+        }))
+        .unwrap();
+        save_tokens(dir.path(), None, &tokens).unwrap();
+
+        let status = load_auth_status(dir.path(), None, "https://issuer", "client").unwrap().unwrap();
+        assert_eq!(status.mode, Some(AuthMode::ChatGPT));
+        assert_eq!(status.source, AuthSource::File);
+        assert_eq!(status.state, CredentialState::Valid);
+        assert_eq!(status.email.as_deref(), Some("user@example.com"));
+        assert_eq!(status.plan.as_deref(), Some("Plus"));
+        assert_eq!(status.account_id.as_deref(), Some("acct-1"));
+        assert_eq!(status.token_expires_at.unwrap().timestamp(), now + 600);
+        assert!(status.last_refresh.is_some());
+
+        let status = load_auth_status(dir.path(), None, "https://other-issuer", "client").unwrap().unwrap();
+        assert_eq!(status.state, CredentialState::Invalid);
+
+        // Only the access token decides expiry; the ID token lives shorter
+        let mut expired_id = tokens.clone();
+        expired_id.id_token = crate::auth::token_data::parse_id_token(&jwt(serde_json::json!({
+            "iss": "https://issuer",
+            "aud": "client",
+            "exp": now - 7200,
+        })))
+        .unwrap();
+        save_tokens(dir.path(), None, &expired_id).unwrap();
+        let status = load_auth_status(dir.path(), None, "https://issuer", "client").unwrap().unwrap();
+        assert_eq!(status.state, CredentialState::Valid);
+        expired_id.access_token = jwt(serde_json::json!({"exp": now - 600}));
+        save_tokens(dir.path(), None, &expired_id).unwrap();
+        let status = load_auth_status(dir.path(), None, "https://issuer", "client").unwrap().unwrap();
+        assert_eq!(status.state, CredentialState::Expired);
+        save_tokens(dir.path(), None, &tokens).unwrap();
+        let status = load_auth_status(dir.path(), None, "https://issuer", "client").unwrap().unwrap();
+        assert_eq!(status.state, CredentialState::Valid);
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["mode"], "chatgpt");
+        assert_eq!(json["source"], "file");
+    }
+
+    #[test]
+    fn test_encrypted_auth_status() {
+        use crate::auth::credential_store::{migrate_auth_json, EncryptedStore, PassphraseBackend};
+
+        let dir = tempdir().unwrap();
+        login_with_api_key(dir.path(), None, "sk-test-key").unwrap();
+        let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new("pw"));
+        migrate_auth_json(dir.path(), &EncryptedStore::new(dir.path(), backend.clone())).unwrap();
+
+        let locked = load_auth_status(dir.path(), None, "https://issuer", "client").unwrap().unwrap();
+        assert_eq!((locked.mode, locked.source, locked.state), (None, AuthSource::Encrypted, CredentialState::Locked));
+
+        let status = load_auth_status(dir.path(), Some(backend), "https://issuer", "client").unwrap().unwrap();
+        assert_eq!(status.mode, Some(AuthMode::ApiKey));
+        assert_eq!(status.source, AuthSource::Encrypted);
+        assert_eq!(status.state, CredentialState::Valid);
+    }
+}
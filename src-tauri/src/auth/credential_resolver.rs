@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::auth::credential_store::SecretBackend;
+use crate::config::ModelProvider;
+
+/// How long a credential helper command may run.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where a session's API key comes from. Sessions without one keep using
+/// whatever the CLI finds in its own environment and auth.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// `CodexConfig.api_key`, entered in the UI.
+    Inline,
+    /// An API key stored in the account vault.
+    Account { name: String },
+    /// Another environment variable of this process.
+    Env { var: String },
+    /// Stdout of a helper such as `op read op://vault/item/key`.
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// First line of a file; `~/` is expanded.
+    File { path: String },
+}
+
+/// A secret that never shows up in `Debug` output or logs.
+#[derive(Clone, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedCredential {
+    pub secret: Secret,
+    /// Human-readable origin such as `env:WORK_OPENAI_KEY`; safe to log.
+    pub source: String,
+}
+
+/// The environment variable the CLI reads the key from for `provider_id`:
+/// the provider's `env_key` from config.toml, else a built-in default.
+/// A configured provider without an `env_key` has nowhere to put a key.
+pub fn provider_env_var(provider_id: &str, providers: Option<&HashMap<String, ModelProvider>>) -> Result<String, String> {
+    let configured = providers.and_then(|providers| {
+        providers
+            .get(provider_id)
+            .or_else(|| providers.get(&provider_id.to_lowercase()))
+    });
+    if let Some(provider) = configured {
+        if provider.env_key.trim().is_empty() {
+            return Err(format!("Provider {} has no env_key in config.toml", provider_id));
+        }
+        return Ok(provider.env_key.clone());
+    }
+    let default = match provider_id.to_lowercase().as_str() {
+        "gemini" => "GEMINI_API_KEY",
+        "openrouter" => "OPENROUTER_API_KEY",
+        "ollama" => "OLLAMA_API_KEY",
+        _ => "OPENAI_API_KEY",
+    };
+    Ok(default.to_string())
+}
+
+/// Resolves `source` to a key. `backend` opens the account vault when
+/// credentials are encrypted. Errors describe the source, never the value.
+pub async fn resolve_credential(
+    source: &CredentialSource,
+    inline: Option<&str>,
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+) -> Result<ResolvedCredential, String> {
+    let (value, label) = match source {
+        CredentialSource::Inline => (
+            inline.map(|s| s.to_string()).unwrap_or_default(),
+            "config".to_string(),
+        ),
+        CredentialSource::Account { name } => (from_account(codex_home, backend, name)?, format!("account:{}", name)),
+        CredentialSource::Env { var } => (
+            std::env::var(var).map_err(|_| format!("Environment variable {} is not set", var))?,
+            format!("env:{}", var),
+        ),
+        CredentialSource::Command { command, args } => (from_command(command, args).await?, format!("command:{}", command)),
+        CredentialSource::File { path } => (from_file(path)?, format!("file:{}", path)),
+    };
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("Credential from {} is empty", label));
+    }
+    Ok(ResolvedCredential {
+        secret: Secret(value.to_string()),
+        source: label,
+    })
+}
+
+fn from_account(codex_home: &Path, backend: Option<Arc<dyn SecretBackend>>, name: &str) -> Result<String, String> {
+    let api_key = crate::auth::accounts::stored_api_key(codex_home, backend, name).map_err(|e| e.to_string())?;
+    api_key.ok_or_else(|| format!("Account '{}' has no API key", name))
+}
+
+async fn from_command(command: &str, args: &[String]) -> Result<String, String> {
+    let mut cmd = Command::new(command);
+    cmd.args(args).stdin(std::process::Stdio::null()).kill_on_drop(true);
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| format!("Credential command '{}' timed out", command))?
+        .map_err(|e| format!("Failed to run credential command '{}': {}", command, e))?;
+
+    if !output.status.success() {
+        // stderr only: stdout may hold a partial secret
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Credential command '{}' failed ({}): {}",
+            command,
+            output.status,
+            stderr.trim().chars().take(200).collect::<String>()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("Credential command '{}' printed non-UTF-8 output", command))
+}
+
+fn from_file(path: &str) -> Result<String, String> {
+    let expanded = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().ok_or("Could not find home directory")?.join(rest),
+        None => Path::new(path).to_path_buf(),
+    };
+    let content = std::fs::read_to_string(&expanded).map_err(|e| format!("Failed to read credential file {}: {}", path, e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = std::fs::metadata(&expanded) {
+            if metadata.permissions().mode() & 0o077 != 0 {
+                log::warn!("Credential file {} is readable by other users", path);
+            }
+        }
+    }
+    Ok(content.lines().next().unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::accounts::save_current_account;
+    use crate::auth::auth_storage::login_with_api_key;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_inline_and_env_sources() {
+        let dir = tempdir().unwrap();
+        let resolved = resolve_credential(&CredentialSource::Inline, Some(" sk-inline\n"), dir.path(), None)
+            .await
+            .unwrap();
+        assert_eq!(resolved.secret.expose(), "sk-inline");
+        assert_eq!(resolved.source, "config");
+        assert!(resolve_credential(&CredentialSource::Inline, None, dir.path(), None).await.is_err());
+
+        std::env::set_var("CODEXIA_TEST_RESOLVER_KEY", "sk-env");
+        let source = CredentialSource::Env {
+            var: "CODEXIA_TEST_RESOLVER_KEY".to_string(),
+        };
+        let resolved = resolve_credential(&source, None, dir.path(), None).await.unwrap();
+        assert_eq!(resolved.secret.expose(), "sk-env");
+        assert_eq!(resolved.source, "env:CODEXIA_TEST_RESOLVER_KEY");
+        assert!(!format!("{:?}", resolved).contains("sk-env"));
+    }
+
+    #[tokio::test]
+    async fn test_command_source() {
+        let dir = tempdir().unwrap();
+        let source = CredentialSource::Command {
+            command: "echo".to_string(),
+            args: vec!["sk-command".to_string()],
+        };
+        let resolved = resolve_credential(&source, None, dir.path(), None).await.unwrap();
+        assert_eq!(resolved.secret.expose(), "sk-command");
+        assert_eq!(resolved.source, "command:echo");
+
+        let failing = CredentialSource::Command {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "echo sk-partial; echo denied >&2; exit 3".to_string()],
+        };
+        let err = resolve_credential(&failing, None, dir.path(), None).await.unwrap_err();
+        assert!(err.contains("denied"));
+        assert!(!err.contains("sk-partial"));
+    }
+
+    #[tokio::test]
+    async fn test_file_source() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("key.txt");
+        std::fs::write(&path, "sk-file\nsecond line\n").unwrap();
+        let source = CredentialSource::File {
+            path: path.to_string_lossy().to_string(),
+        };
+        let resolved = resolve_credential(&source, None, dir.path(), None).await.unwrap();
+        assert_eq!(resolved.secret.expose(), "sk-file");
+
+        let missing = CredentialSource::File {
+            path: dir.path().join("missing").to_string_lossy().to_string(),
+        };
+        assert!(resolve_credential(&missing, None, dir.path(), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_account_source() {
+        let dir = tempdir().unwrap();
+        login_with_api_key(dir.path(), None, "sk-work").unwrap();
+        save_current_account(dir.path(), None, "work").unwrap();
+
+        let source = CredentialSource::Account { name: "work".to_string() };
+        let resolved = resolve_credential(&source, None, dir.path(), None).await.unwrap();
+        assert_eq!(resolved.secret.expose(), "sk-work");
+        assert_eq!(resolved.source, "account:work");
+
+        let missing = CredentialSource::Account { name: "nope".to_string() };
+        assert!(resolve_credential(&missing, None, dir.path(), None).await.is_err());
+    }
+
+    #[test]
+    fn test_provider_env_var() {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "azure".to_string(),
+            ModelProvider {
+                name: "Azure".to_string(),
+                base_url: "https://example.openai.azure.com".to_string(),
+                env_key: "AZURE_OPENAI_API_KEY".to_string(),
+            },
+        );
+        providers.insert(
+            "local".to_string(),
+            ModelProvider {
+                name: "Local".to_string(),
+                base_url: "http://localhost:8080".to_string(),
+                env_key: String::new(),
+            },
+        );
+        assert_eq!(provider_env_var("Azure", Some(&providers)).unwrap(), "AZURE_OPENAI_API_KEY");
+        assert_eq!(provider_env_var("gemini", Some(&providers)).unwrap(), "GEMINI_API_KEY");
+        assert_eq!(provider_env_var("custom", None).unwrap(), "OPENAI_API_KEY");
+        assert!(provider_env_var("local", Some(&providers)).is_err());
+    }
+}
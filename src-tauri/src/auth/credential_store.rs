@@ -0,0 +1,475 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::auth::auth_storage::{get_auth_file, read_auth_file, save_auth_to_file, write_private_json, AuthDotJson};
+
+const ENVELOPE_VERSION: u32 = 1;
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// Unoptimized test builds would spend seconds per derivation.
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+const SALT_LEN: usize = 16;
+/// Base64-encoded 32-byte key for unattended setups (CI, remote machines).
+pub const CREDENTIAL_KEY_ENV_VAR: &str = "CODEXIA_CREDENTIAL_KEY";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialStoreError {
+    #[error("Credential store is locked")]
+    Locked,
+    #[error("Wrong passphrase or corrupted credential store")]
+    Decrypt,
+    #[error("Invalid credential key: {0}")]
+    InvalidKey(String),
+    #[error("Unsupported credential store: {0}")]
+    Unsupported(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid credential store contents: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Storage(String),
+}
+
+/// Supplies the 256-bit key that protects `credentials.enc`. The salt is
+/// stored in the file so passphrase backends can derive the same key again.
+pub trait SecretBackend: Send + Sync {
+    /// Recorded in the file so unlocking can tell which backend sealed it.
+    fn kind(&self) -> &'static str;
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; 32], CredentialStoreError>;
+}
+
+impl SecretBackend for Arc<dyn SecretBackend> {
+    fn kind(&self) -> &'static str {
+        self.as_ref().kind()
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; 32], CredentialStoreError> {
+        self.as_ref().derive_key(salt)
+    }
+}
+
+/// Derives the key from a user passphrase with PBKDF2-HMAC-SHA256.
+pub struct PassphraseBackend {
+    passphrase: String,
+}
+
+impl PassphraseBackend {
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: passphrase.into(),
+        }
+    }
+}
+
+impl SecretBackend for PassphraseBackend {
+    fn kind(&self) -> &'static str {
+        "passphrase"
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; 32], CredentialStoreError> {
+        if self.passphrase.is_empty() {
+            return Err(CredentialStoreError::InvalidKey("passphrase is empty".to_string()));
+        }
+        let mut key = [0u8; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations"),
+            salt,
+            self.passphrase.as_bytes(),
+            &mut key,
+        );
+        Ok(key)
+    }
+}
+
+/// Reads a raw key from `CODEXIA_CREDENTIAL_KEY`, e.g. injected by a secret
+/// manager.
+pub struct EnvKeyBackend;
+
+impl SecretBackend for EnvKeyBackend {
+    fn kind(&self) -> &'static str {
+        "env"
+    }
+
+    fn derive_key(&self, _salt: &[u8]) -> Result<[u8; 32], CredentialStoreError> {
+        let value = std::env::var(CREDENTIAL_KEY_ENV_VAR).map_err(|_| CredentialStoreError::Locked)?;
+        let bytes = STANDARD
+            .decode(value.trim())
+            .map_err(|e| CredentialStoreError::InvalidKey(e.to_string()))?;
+        bytes
+            .try_into()
+            .map_err(|_| CredentialStoreError::InvalidKey(format!("{} must decode to 32 bytes", CREDENTIAL_KEY_ENV_VAR)))
+    }
+}
+
+/// Where the credentials the CLI needs are kept.
+pub trait CredentialStore: Send + Sync {
+    fn load(&self) -> Result<Option<AuthDotJson>, CredentialStoreError>;
+    fn save(&self, auth: &AuthDotJson) -> Result<(), CredentialStoreError>;
+}
+
+/// The CLI-compatible plaintext `auth.json`.
+pub struct PlaintextStore {
+    codex_home: PathBuf,
+}
+
+impl PlaintextStore {
+    pub fn new(codex_home: &Path) -> Self {
+        Self {
+            codex_home: codex_home.to_path_buf(),
+        }
+    }
+}
+
+impl CredentialStore for PlaintextStore {
+    fn load(&self) -> Result<Option<AuthDotJson>, CredentialStoreError> {
+        read_auth_file(&self.codex_home).map_err(|e| CredentialStoreError::Storage(e.to_string()))
+    }
+
+    fn save(&self, auth: &AuthDotJson) -> Result<(), CredentialStoreError> {
+        save_auth_to_file(&get_auth_file(&self.codex_home), auth).map_err(|e| CredentialStoreError::Storage(e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    backend: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn cipher<B: SecretBackend>(backend: &B, salt: &[u8]) -> Result<LessSafeKey, CredentialStoreError> {
+    let key = backend.derive_key(salt)?;
+    let unbound = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| CredentialStoreError::InvalidKey("bad key length".to_string()))?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+/// Reads a JSON value sealed by `seal_json`; `None` when `path` is absent.
+pub fn open_json<T: DeserializeOwned, B: SecretBackend>(path: &Path, backend: &B) -> Result<Option<T>, CredentialStoreError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let envelope: Envelope = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(CredentialStoreError::Unsupported(format!("version {}", envelope.version)));
+    }
+    if envelope.backend != backend.kind() {
+        return Err(CredentialStoreError::Unsupported(format!(
+            "sealed with the {} backend",
+            envelope.backend
+        )));
+    }
+
+    let decode = |value: &str| STANDARD.decode(value).map_err(|_| CredentialStoreError::Decrypt);
+    let salt = decode(&envelope.salt)?;
+    let nonce = Nonce::try_assume_unique_for_key(&decode(&envelope.nonce)?).map_err(|_| CredentialStoreError::Decrypt)?;
+    let mut in_out = decode(&envelope.ciphertext)?;
+
+    let plaintext = cipher(backend, &salt)?
+        .open_in_place(nonce, Aad::from(backend.kind().as_bytes()), &mut in_out)
+        .map_err(|_| CredentialStoreError::Decrypt)?;
+    Ok(Some(serde_json::from_slice(plaintext)?))
+}
+
+/// Writes `value` to `path` as an AES-256-GCM envelope.
+pub fn seal_json<T: Serialize, B: SecretBackend>(path: &Path, backend: &B, value: &T) -> Result<(), CredentialStoreError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut in_out = serde_json::to_vec(value)?;
+    cipher(backend, &salt)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(backend.kind().as_bytes()),
+            &mut in_out,
+        )
+        .map_err(|_| CredentialStoreError::Storage("Encryption failed".to_string()))?;
+
+    let envelope = Envelope {
+        version: ENVELOPE_VERSION,
+        backend: backend.kind().to_string(),
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(in_out),
+    };
+    write_private_json(path, &envelope).map_err(|e| CredentialStoreError::Storage(e.to_string()))
+}
+
+/// `credentials.enc`: auth.json contents sealed with AES-256-GCM.
+pub struct EncryptedStore<B: SecretBackend> {
+    path: PathBuf,
+    backend: B,
+}
+
+pub fn encrypted_store_file(codex_home: &Path) -> PathBuf {
+    codex_home.join("credentials.enc")
+}
+
+impl<B: SecretBackend> EncryptedStore<B> {
+    pub fn new(codex_home: &Path, backend: B) -> Self {
+        Self {
+            path: encrypted_store_file(codex_home),
+            backend,
+        }
+    }
+}
+
+impl<B: SecretBackend> CredentialStore for EncryptedStore<B> {
+    fn load(&self) -> Result<Option<AuthDotJson>, CredentialStoreError> {
+        open_json(&self.path, &self.backend)
+    }
+
+    fn save(&self, auth: &AuthDotJson) -> Result<(), CredentialStoreError> {
+        seal_json(&self.path, &self.backend, auth)
+    }
+}
+
+/// The unlocked backend, falling back to `CODEXIA_CREDENTIAL_KEY` when the
+/// store hasn't been unlocked interactively.
+pub fn unlocked_backend(backend: Option<Arc<dyn SecretBackend>>) -> Result<Arc<dyn SecretBackend>, CredentialStoreError> {
+    match backend {
+        Some(backend) => Ok(backend),
+        None if std::env::var(CREDENTIAL_KEY_ENV_VAR).is_ok() => Ok(Arc::new(EnvKeyBackend)),
+        None => Err(CredentialStoreError::Locked),
+    }
+}
+
+/// Where the live credentials are kept: the encrypted store once it
+/// exists, else auth.json.
+pub fn live_store(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+) -> Result<Box<dyn CredentialStore>, CredentialStoreError> {
+    if encrypted_store_file(codex_home).exists() {
+        Ok(Box::new(EncryptedStore::new(codex_home, unlocked_backend(backend)?)))
+    } else {
+        Ok(Box::new(PlaintextStore::new(codex_home)))
+    }
+}
+
+/// The live credentials, read from `live_store`.
+pub fn load_live_auth(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+) -> Result<Option<AuthDotJson>, CredentialStoreError> {
+    live_store(codex_home, backend)?.load()
+}
+
+/// Moves auth.json into the encrypted store and deletes the plaintext file.
+/// Returns false when there was nothing to migrate.
+pub fn migrate_auth_json(codex_home: &Path, store: &dyn CredentialStore) -> Result<bool, CredentialStoreError> {
+    let Some(auth) = PlaintextStore::new(codex_home).load()? else {
+        return Ok(false);
+    };
+    store.save(&auth)?;
+    // Verify the round trip before the only plaintext copy goes away.
+    if store.load()?.as_ref() != Some(&auth) {
+        return Err(CredentialStoreError::Storage("Encrypted copy does not match auth.json".to_string()));
+    }
+    std::fs::remove_file(get_auth_file(codex_home))?;
+    Ok(true)
+}
+
+/// Spawned sessions whose CLI hasn't read the temporary auth.json yet, per
+/// codex home. The file is removed once this drops to zero.
+static PENDING_AUTH_FILES: Mutex<BTreeMap<PathBuf, usize>> = Mutex::new(BTreeMap::new());
+
+/// Keeps unsealed ChatGPT tokens in auth.json only while a CLI process
+/// starts up. Call `hide` once the CLI has read them; dropping the lease
+/// hides it too and also catches tokens the CLI refreshed later.
+/// Dropping may derive a key, so drop it off the async runtime.
+pub struct AuthFileLease {
+    codex_home: PathBuf,
+    backend: Arc<dyn SecretBackend>,
+    /// What was unsealed, to tell CLI refreshes from other changes.
+    unsealed: AuthDotJson,
+    hidden: bool,
+}
+
+impl AuthFileLease {
+    fn acquire(codex_home: &Path, backend: Arc<dyn SecretBackend>, auth: AuthDotJson) -> Result<Self, CredentialStoreError> {
+        let mut pending = PENDING_AUTH_FILES.lock().unwrap_or_else(|e| e.into_inner());
+        if !get_auth_file(codex_home).exists() {
+            PlaintextStore::new(codex_home).save(&auth)?;
+        }
+        *pending.entry(codex_home.to_path_buf()).or_default() += 1;
+        Ok(Self {
+            codex_home: codex_home.to_path_buf(),
+            backend,
+            unsealed: auth,
+            hidden: false,
+        })
+    }
+
+    /// Removes auth.json unless another starting session still needs it.
+    /// Tokens the CLI rotated in the meantime are sealed first.
+    pub fn hide(&mut self) -> Result<(), CredentialStoreError> {
+        let mut counts = PENDING_AUTH_FILES.lock().unwrap_or_else(|e| e.into_inner());
+        if !self.hidden {
+            self.hidden = true;
+            if let Some(count) = counts.get_mut(&self.codex_home) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        if counts.get(&self.codex_home).copied().unwrap_or_default() > 0 {
+            return Ok(());
+        }
+        counts.remove(&self.codex_home);
+
+        let Some(current) = PlaintextStore::new(&self.codex_home).load()? else {
+            return Ok(());
+        };
+        let store = EncryptedStore::new(&self.codex_home, self.backend.clone());
+        // Only keep a refresh of what was unsealed; a switch or login since
+        // then already wrote the store.
+        if current != self.unsealed && store.load()?.as_ref() == Some(&self.unsealed) {
+            store.save(&current)?;
+        }
+        std::fs::remove_file(get_auth_file(&self.codex_home))?;
+        Ok(())
+    }
+}
+
+impl Drop for AuthFileLease {
+    fn drop(&mut self) {
+        if let Err(e) = self.hide() {
+            log::warn!("Failed to remove unsealed credentials from {}: {}", self.codex_home.display(), e);
+        }
+    }
+}
+
+/// Credentials decrypted for a CLI process about to be spawned.
+#[derive(Default)]
+pub struct UnsealedCredentials {
+    /// Injected into the child's environment; never written to disk.
+    pub api_key: Option<String>,
+    /// ChatGPT tokens, which the CLI only reads from auth.json.
+    pub auth_file: Option<AuthFileLease>,
+}
+
+/// Decrypts the stored credentials for a CLI process about to be spawned.
+pub fn unseal_for_spawn(
+    codex_home: &Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+) -> Result<UnsealedCredentials, CredentialStoreError> {
+    if !encrypted_store_file(codex_home).exists() {
+        return Ok(UnsealedCredentials::default());
+    }
+    let backend = unlocked_backend(backend)?;
+    let Some(auth) = EncryptedStore::new(codex_home, backend.clone()).load()? else {
+        return Ok(UnsealedCredentials::default());
+    };
+
+    if let Some(api_key) = auth.openai_api_key.clone().filter(|k| !k.is_empty()) {
+        return Ok(UnsealedCredentials {
+            api_key: Some(api_key),
+            auth_file: None,
+        });
+    }
+    if auth.tokens.is_none() {
+        return Ok(UnsealedCredentials::default());
+    }
+    Ok(UnsealedCredentials {
+        api_key: None,
+        auth_file: Some(AuthFileLease::acquire(codex_home, backend, auth)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::auth_storage::login_with_api_key;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_migrate_and_unseal() {
+        let dir = tempdir().unwrap();
+        login_with_api_key(dir.path(), None, "sk-secret").unwrap();
+
+        let store = EncryptedStore::new(dir.path(), PassphraseBackend::new("correct horse"));
+        assert!(migrate_auth_json(dir.path(), &store).unwrap());
+        assert!(!get_auth_file(dir.path()).exists());
+
+        let sealed = std::fs::read_to_string(encrypted_store_file(dir.path())).unwrap();
+        assert!(!sealed.contains("sk-secret"));
+
+        let unsealed = unseal_for_spawn(dir.path(), Some(Arc::new(PassphraseBackend::new("correct horse")))).unwrap();
+        assert_eq!(unsealed.api_key.as_deref(), Some("sk-secret"));
+        assert!(unsealed.auth_file.is_none());
+        assert!(!get_auth_file(dir.path()).exists());
+
+        let wrong = unseal_for_spawn(dir.path(), Some(Arc::new(PassphraseBackend::new("wrong"))));
+        assert!(matches!(wrong, Err(CredentialStoreError::Decrypt)));
+        assert!(matches!(unseal_for_spawn(dir.path(), None), Err(CredentialStoreError::Locked)));
+    }
+
+    #[test]
+    fn test_chatgpt_tokens_only_touch_disk_while_starting() {
+        use crate::auth::token_data::TokenData;
+
+        let dir = tempdir().unwrap();
+        let tokens: TokenData = serde_json::from_value(serde_json::json!({
+            "id_token": "e30.e30.sig",
+            "access_token": "access-1",
+            "refresh_token": "refresh-1",
+        }))
+        .unwrap();
+        let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new("pw"));
+        let store = EncryptedStore::new(dir.path(), backend.clone());
+        store
+            .save(&AuthDotJson {
+                tokens: Some(tokens),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let mut first = unseal_for_spawn(dir.path(), Some(backend.clone())).unwrap().auth_file.unwrap();
+        let mut second = unseal_for_spawn(dir.path(), Some(backend.clone())).unwrap().auth_file.unwrap();
+        assert!(get_auth_file(dir.path()).exists());
+        // The second session hasn't started yet
+        first.hide().unwrap();
+        assert!(get_auth_file(dir.path()).exists());
+
+        // A refresh by the CLI is sealed before the file goes away
+        let mut refreshed = read_auth_file(dir.path()).unwrap().unwrap();
+        refreshed.tokens.as_mut().unwrap().access_token = "access-2".to_string();
+        save_auth_to_file(&get_auth_file(dir.path()), &refreshed).unwrap();
+        second.hide().unwrap();
+        assert!(!get_auth_file(dir.path()).exists());
+        assert_eq!(store.load().unwrap(), Some(refreshed));
+        drop(second);
+        drop(first);
+        assert!(!get_auth_file(dir.path()).exists());
+    }
+
+    #[test]
+    fn test_tampered_file_is_rejected() {
+        let dir = tempdir().unwrap();
+        let store = EncryptedStore::new(dir.path(), PassphraseBackend::new("pw"));
+        let auth = AuthDotJson {
+            openai_api_key: Some("sk-secret".to_string()),
+            ..Default::default()
+        };
+        store.save(&auth).unwrap();
+        assert_eq!(store.load().unwrap(), Some(auth));
+
+        let path = encrypted_store_file(dir.path());
+        let mut envelope: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let mut ciphertext = STANDARD.decode(envelope["ciphertext"].as_str().unwrap()).unwrap();
+        ciphertext[0] ^= 1;
+        envelope["ciphertext"] = STANDARD.encode(ciphertext).into();
+        std::fs::write(&path, envelope.to_string()).unwrap();
+        assert!(matches!(store.load(), Err(CredentialStoreError::Decrypt)));
+    }
+}
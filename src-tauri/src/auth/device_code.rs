@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::auth::auth_storage::save_tokens;
+use crate::auth::oauth_server::{parse_token_response, ServerOptions};
+
+/// RFC 8628 default when the issuer doesn't send `interval`.
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+/// Added to the interval each time the issuer answers `slow_down`, unless
+/// `ServerOptions::slow_down_step` says otherwise.
+pub(crate) const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceCodeError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Issuer returned {error}: {description}")]
+    Issuer { error: String, description: String },
+    #[error("Invalid response from issuer: {0}")]
+    InvalidResponse(String),
+    #[error("Login was denied")]
+    Denied,
+    #[error("Device code expired before login completed")]
+    Expired,
+    #[error("Login cancelled")]
+    Cancelled,
+    #[error("Failed to save tokens: {0}")]
+    Save(String),
+}
+
+/// What the user needs to finish logging in on another device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodePrompt {
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Verification URL with the code pre-filled, when the issuer offers one.
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+}
+
+/// A pending device authorization. `device_code` is a secret shared with the
+/// issuer only and never leaves the backend.
+#[derive(Debug, Clone)]
+pub struct DeviceAuthorization {
+    pub prompt: DeviceCodePrompt,
+    device_code: String,
+    interval: Duration,
+    expires_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    #[serde(alias = "verification_url")]
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    interval: Option<u64>,
+}
+
+enum PollResult {
+    Pending,
+    SlowDown,
+    Done(serde_json::Value),
+}
+
+pub fn request_device_code(opts: &ServerOptions) -> Result<DeviceAuthorization, DeviceCodeError> {
+    let client = http_client()?;
+    let response = client
+        .post(format!("{}/device/code", opts.issuer))
+        .form(&[("client_id", opts.client_id.as_str()), ("scope", "openid profile email offline_access")])
+        .send()?;
+
+    if !response.status().is_success() {
+        return Err(issuer_error(response));
+    }
+    let body: DeviceCodeResponse = response
+        .json()
+        .map_err(|e| DeviceCodeError::InvalidResponse(e.to_string()))?;
+
+    Ok(DeviceAuthorization {
+        prompt: DeviceCodePrompt {
+            user_code: body.user_code,
+            verification_uri: body.verification_uri,
+            verification_uri_complete: body.verification_uri_complete,
+            expires_in: body.expires_in,
+        },
+        device_code: body.device_code,
+        interval: Duration::from_secs(body.interval.unwrap_or(DEFAULT_POLL_INTERVAL_SECS)),
+        expires_at: Instant::now() + Duration::from_secs(body.expires_in),
+    })
+}
+
+/// Polls the token endpoint until the user approves, denies or the code
+/// expires, then saves the tokens to auth.json.
+pub fn complete_device_login(
+    opts: &ServerOptions,
+    authorization: &DeviceAuthorization,
+    cancel: &AtomicBool,
+) -> Result<(), DeviceCodeError> {
+    let client = http_client()?;
+    let mut interval = authorization.interval;
+
+    loop {
+        wait(interval, authorization.expires_at, cancel)?;
+        match poll_token(&client, opts, &authorization.device_code)? {
+            PollResult::Pending => {}
+            PollResult::SlowDown => interval += opts.slow_down_step,
+            PollResult::Done(body) => {
+                let tokens = parse_token_response(&body)
+                    .map_err(|e| DeviceCodeError::InvalidResponse(e.to_string()))?;
+                return save_tokens(Path::new(&opts.codex_home), opts.backend.clone(), &tokens)
+                    .map_err(|e| DeviceCodeError::Save(e.to_string()));
+            }
+        }
+    }
+}
+
+fn poll_token(
+    client: &reqwest::blocking::Client,
+    opts: &ServerOptions,
+    device_code: &str,
+) -> Result<PollResult, DeviceCodeError> {
+    let response = client
+        .post(format!("{}/token", opts.issuer))
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ("client_id", opts.client_id.as_str()),
+            ("device_code", device_code),
+        ])
+        .send()?;
+
+    if response.status().is_success() {
+        let body = response
+            .json()
+            .map_err(|e| DeviceCodeError::InvalidResponse(e.to_string()))?;
+        return Ok(PollResult::Done(body));
+    }
+
+    match issuer_error(response) {
+        DeviceCodeError::Issuer { error, .. } if error == "authorization_pending" => Ok(PollResult::Pending),
+        DeviceCodeError::Issuer { error, .. } if error == "slow_down" => Ok(PollResult::SlowDown),
+        DeviceCodeError::Issuer { error, .. } if error == "access_denied" => Err(DeviceCodeError::Denied),
+        DeviceCodeError::Issuer { error, .. } if error == "expired_token" => Err(DeviceCodeError::Expired),
+        other => Err(other),
+    }
+}
+
+/// Sleeps for `interval`, waking early for cancellation or expiry.
+fn wait(interval: Duration, expires_at: Instant, cancel: &AtomicBool) -> Result<(), DeviceCodeError> {
+    let until = Instant::now() + interval;
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(DeviceCodeError::Cancelled);
+        }
+        let now = Instant::now();
+        if now >= expires_at {
+            return Err(DeviceCodeError::Expired);
+        }
+        if now >= until {
+            return Ok(());
+        }
+        thread::sleep(CANCEL_CHECK_INTERVAL.min(until - now));
+    }
+}
+
+fn http_client() -> Result<reqwest::blocking::Client, DeviceCodeError> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?)
+}
+
+fn issuer_error(response: reqwest::blocking::Response) -> DeviceCodeError {
+    let status = response.status();
+    let body: serde_json::Value = response.json().unwrap_or_default();
+    let field = |name: &str| body.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+    DeviceCodeError::Issuer {
+        error: field("error").unwrap_or_else(|| status.to_string()),
+        description: field("error_description").unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn jwt(claims: serde_json::Value) -> String {
+        use base64::Engine;
+        let encode = |v: &serde_json::Value| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(v.to_string());
+        format!("{}.{}.sig", encode(&serde_json::json!({"alg": "none"})), encode(&claims))
+    }
+
+    /// Issuer that hands out a device code, answers `authorization_pending`
+    /// and `slow_down` once each, then either approves or denies.
+    fn mock_issuer(approve: bool) -> (String, Arc<AtomicUsize>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let polls = Arc::new(AtomicUsize::new(0));
+        let polls_clone = polls.clone();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let (status, response) = match request.url() {
+                    "/device/code" => (
+                        200,
+                        serde_json::json!({
+                            "device_code": "dev-123",
+                            "user_code": "ABCD-EFGH",
+                            "verification_uri": "https://example.com/device",
+                            "expires_in": 60,
+                            "interval": 0
+                        }),
+                    ),
+                    "/token" => {
+                        assert!(body.contains("device_code=dev-123"));
+                        match polls_clone.fetch_add(1, Ordering::SeqCst) {
+                            0 => (400, serde_json::json!({"error": "authorization_pending"})),
+                            1 => (400, serde_json::json!({"error": "slow_down"})),
+                            _ if !approve => (400, serde_json::json!({"error": "access_denied"})),
+                            _ => (
+                                200,
+                                serde_json::json!({
+                                    "id_token": jwt(serde_json::json!({"email": "user@example.com"})),
+                                    "access_token": "access",
+                                    "refresh_token": "refresh"
+                                }),
+                            ),
+                        }
+                    }
+                    _ => (404, serde_json::json!({})),
+                };
+                let _ = request.respond(tiny_http::Response::from_string(response.to_string()).with_status_code(status));
+            }
+        });
+        (issuer, polls)
+    }
+
+    fn options(codex_home: &Path, issuer: String) -> ServerOptions {
+        ServerOptions {
+            issuer,
+            slow_down_step: Duration::from_millis(50),
+            ..ServerOptions::new(codex_home.to_path_buf(), "client".to_string())
+        }
+    }
+
+    #[test]
+    fn test_device_login_saves_tokens() {
+        let dir = tempdir().unwrap();
+        let (issuer, polls) = mock_issuer(true);
+        let opts = options(dir.path(), issuer);
+
+        let authorization = request_device_code(&opts).unwrap();
+        assert_eq!(authorization.prompt.user_code, "ABCD-EFGH");
+        assert_eq!(authorization.prompt.verification_uri, "https://example.com/device");
+
+        let started = Instant::now();
+        complete_device_login(&opts, &authorization, &AtomicBool::new(false)).unwrap();
+        assert_eq!(polls.load(Ordering::SeqCst), 3);
+        // slow_down adds to the interval for the remaining polls
+        assert!(started.elapsed() >= opts.slow_down_step);
+
+        let saved = std::fs::read_to_string(dir.path().join("auth.json")).unwrap();
+        assert!(saved.contains("\"refresh_token\": \"refresh\""));
+    }
+
+    #[test]
+    fn test_device_login_denied_and_cancelled() {
+        let dir = tempdir().unwrap();
+        let (issuer, _) = mock_issuer(false);
+        let opts = options(dir.path(), issuer);
+
+        let mut authorization = request_device_code(&opts).unwrap();
+        authorization.interval = Duration::ZERO;
+        let err = complete_device_login(&opts, &authorization, &AtomicBool::new(false)).unwrap_err();
+        // pending and slow_down come first, then the denial
+        assert!(matches!(err, DeviceCodeError::Denied), "{}", err);
+        assert!(!dir.path().join("auth.json").exists());
+
+        let err = complete_device_login(&opts, &authorization, &AtomicBool::new(true)).unwrap_err();
+        assert!(matches!(err, DeviceCodeError::Cancelled));
+    }
+}
@@ -0,0 +1,296 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use ring::signature::{RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_FIXED, RSA_PKCS1_2048_8192_SHA256};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// Tolerated difference between our clock and the issuer's.
+pub const CLOCK_SKEW: Duration = Duration::seconds(60);
+/// How long a fetched key set is trusted before asking the issuer again.
+const JWKS_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClaimError {
+    #[error("Malformed token: {0}")]
+    Malformed(String),
+    #[error("Token expired at {0}")]
+    Expired(DateTime<Utc>),
+    #[error("Token is not valid before {0}")]
+    NotYetValid(DateTime<Utc>),
+    #[error("Issuer mismatch: expected {expected}, found {found}")]
+    IssuerMismatch { expected: String, found: String },
+    #[error("Token was not issued for client {0}")]
+    AudienceMismatch(String),
+    #[error("Signature verification failed: {0}")]
+    Signature(String),
+    #[error("Failed to fetch signing keys: {0}")]
+    Jwks(String),
+}
+
+impl ClaimError {
+    /// Expiry is recoverable by refreshing; everything else means the token
+    /// can't be trusted at all.
+    pub fn is_expired(&self) -> bool {
+        matches!(self, ClaimError::Expired(_))
+    }
+}
+
+/// What a token must satisfy to be accepted.
+#[derive(Debug, Clone)]
+pub struct ClaimValidation<'a> {
+    pub issuer: &'a str,
+    pub audience: &'a str,
+    pub now: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+struct DecodedJwt<'a> {
+    header: Header,
+    claims: Value,
+    signing_input: &'a str,
+    signature: Vec<u8>,
+}
+
+fn decode(jwt: &str) -> Result<DecodedJwt<'_>, ClaimError> {
+    let malformed = |what: &str| ClaimError::Malformed(what.to_string());
+    let (signing_input, signature) = jwt.rsplit_once('.').ok_or_else(|| malformed("expected three segments"))?;
+    let (header, payload) = signing_input.split_once('.').ok_or_else(|| malformed("expected three segments"))?;
+
+    let header_bytes = URL_SAFE_NO_PAD.decode(header).map_err(|_| malformed("header is not base64url"))?;
+    let payload_bytes = URL_SAFE_NO_PAD.decode(payload).map_err(|_| malformed("payload is not base64url"))?;
+    Ok(DecodedJwt {
+        header: serde_json::from_slice(&header_bytes).map_err(|e| ClaimError::Malformed(e.to_string()))?,
+        claims: serde_json::from_slice(&payload_bytes).map_err(|e| ClaimError::Malformed(e.to_string()))?,
+        signing_input,
+        signature: URL_SAFE_NO_PAD.decode(signature).map_err(|_| malformed("signature is not base64url"))?,
+    })
+}
+
+/// Checks `exp`/`nbf` (with clock skew), `iss` and `aud`. Does not look at
+/// the signature; see [`JwksCache::verify`].
+pub fn validate_claims(jwt: &str, expected: &ClaimValidation) -> Result<(), ClaimError> {
+    let claims = decode(jwt)?.claims;
+    let timestamp = |name: &str| claims.get(name).and_then(|v| v.as_i64()).and_then(|t| DateTime::from_timestamp(t, 0));
+
+    let expires_at = timestamp("exp").ok_or_else(|| ClaimError::Malformed("missing exp claim".to_string()))?;
+    if expires_at + CLOCK_SKEW < expected.now {
+        return Err(ClaimError::Expired(expires_at));
+    }
+    if let Some(not_before) = timestamp("nbf") {
+        if not_before - CLOCK_SKEW > expected.now {
+            return Err(ClaimError::NotYetValid(not_before));
+        }
+    }
+
+    let issuer = claims.get("iss").and_then(|v| v.as_str()).unwrap_or_default();
+    if issuer.trim_end_matches('/') != expected.issuer.trim_end_matches('/') {
+        return Err(ClaimError::IssuerMismatch {
+            expected: expected.issuer.to_string(),
+            found: issuer.to_string(),
+        });
+    }
+
+    // `aud` may be a single string or an array of them
+    let audience_ok = match claims.get("aud") {
+        Some(Value::String(aud)) => aud == expected.audience,
+        Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(expected.audience)),
+        _ => false,
+    };
+    if !audience_ok {
+        return Err(ClaimError::AudienceMismatch(expected.audience.to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kid: Option<String>,
+    kty: String,
+    // RSA
+    n: Option<String>,
+    e: Option<String>,
+    // EC
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// The issuer's signing keys from `{issuer}/.well-known/jwks.json`, fetched
+/// lazily and refetched when stale or when a token names an unknown `kid`.
+pub struct JwksCache {
+    url: String,
+    keys: Mutex<Option<(Instant, Vec<Jwk>)>>,
+}
+
+impl JwksCache {
+    pub fn new(issuer: &str) -> Self {
+        Self {
+            url: format!("{}/.well-known/jwks.json", issuer.trim_end_matches('/')),
+            keys: Mutex::new(None),
+        }
+    }
+
+    pub async fn verify(&self, jwt: &str) -> Result<(), ClaimError> {
+        let decoded = decode(jwt)?;
+        let kid = decoded.header.kid.as_deref();
+
+        let mut cached = self.keys.lock().await;
+        let fresh = cached.as_ref().is_some_and(|(at, _)| at.elapsed() < JWKS_TTL);
+        let known = cached.as_ref().is_some_and(|(_, keys)| find_key(keys, kid).is_some());
+        if !fresh || !known {
+            *cached = Some((Instant::now(), self.fetch().await?));
+        }
+        let keys = &cached.as_ref().expect("key set just stored").1;
+        let key = find_key(keys, kid)
+            .ok_or_else(|| ClaimError::Signature(format!("no signing key with kid {:?}", kid)))?;
+        verify_signature(&decoded, key)
+    }
+
+    async fn fetch(&self) -> Result<Vec<Jwk>, ClaimError> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|e| ClaimError::Jwks(e.to_string()))?;
+        let response = client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ClaimError::Jwks(e.to_string()))?;
+        let set: JwkSet = response.json().await.map_err(|e| ClaimError::Jwks(e.to_string()))?;
+        Ok(set.keys)
+    }
+}
+
+fn find_key<'a>(keys: &'a [Jwk], kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.iter().find(|k| k.kid.as_deref() == Some(kid)),
+        None if keys.len() == 1 => keys.first(),
+        None => None,
+    }
+}
+
+fn verify_signature(decoded: &DecodedJwt, key: &Jwk) -> Result<(), ClaimError> {
+    let component = |value: &Option<String>, name: &str| {
+        value
+            .as_deref()
+            .and_then(|v| URL_SAFE_NO_PAD.decode(v).ok())
+            .ok_or_else(|| ClaimError::Signature(format!("signing key has no usable {}", name)))
+    };
+    let message = decoded.signing_input.as_bytes();
+    let result = match (decoded.header.alg.as_str(), key.kty.as_str()) {
+        ("RS256", "RSA") => RsaPublicKeyComponents {
+            n: component(&key.n, "n")?,
+            e: component(&key.e, "e")?,
+        }
+        .verify(&RSA_PKCS1_2048_8192_SHA256, message, &decoded.signature),
+        ("ES256", "EC") if key.crv.as_deref() == Some("P-256") => {
+            let mut point = vec![0x04];
+            point.extend(component(&key.x, "x")?);
+            point.extend(component(&key.y, "y")?);
+            UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, point).verify(message, &decoded.signature)
+        }
+        (alg, kty) => {
+            return Err(ClaimError::Signature(format!("unsupported algorithm {} for {} key", alg, kty)));
+        }
+    };
+    result.map_err(|_| ClaimError::Signature("signature does not match".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    use serde_json::json;
+
+    const ISSUER: &str = "https://auth.example.com";
+    const CLIENT: &str = "app_test";
+
+    fn unsigned(claims: Value) -> String {
+        let header = URL_SAFE_NO_PAD.encode(json!({"alg": "none"}).to_string());
+        format!("{}.{}.", header, URL_SAFE_NO_PAD.encode(claims.to_string()))
+    }
+
+    fn expected(now: DateTime<Utc>) -> ClaimValidation<'static> {
+        ClaimValidation {
+            issuer: ISSUER,
+            audience: CLIENT,
+            now,
+        }
+    }
+
+    #[test]
+    fn test_validate_claims() {
+        let now = Utc::now();
+        let exp = now.timestamp() + 600;
+        let valid = unsigned(json!({"iss": ISSUER, "aud": [CLIENT, "other"], "exp": exp}));
+        assert!(validate_claims(&valid, &expected(now)).is_ok());
+
+        // within skew is still fine, beyond it is expired
+        assert!(validate_claims(&valid, &expected(now + Duration::seconds(630))).is_ok());
+        let err = validate_claims(&valid, &expected(now + Duration::seconds(700))).unwrap_err();
+        assert!(err.is_expired());
+
+        let wrong_iss = unsigned(json!({"iss": "https://evil.example.com", "aud": CLIENT, "exp": exp}));
+        assert!(matches!(validate_claims(&wrong_iss, &expected(now)), Err(ClaimError::IssuerMismatch { .. })));
+        let wrong_aud = unsigned(json!({"iss": ISSUER, "aud": "someone-else", "exp": exp}));
+        assert!(matches!(validate_claims(&wrong_aud, &expected(now)), Err(ClaimError::AudienceMismatch(_))));
+        let no_exp = unsigned(json!({"iss": ISSUER, "aud": CLIENT}));
+        assert!(matches!(validate_claims(&no_exp, &expected(now)), Err(ClaimError::Malformed(_))));
+        assert!(matches!(validate_claims("not-a-jwt", &expected(now)), Err(ClaimError::Malformed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_jwks_signature_verification() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let public = key_pair.public_key().as_ref();
+        let jwks = json!({"keys": [{
+            "kid": "k1", "kty": "EC", "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(&public[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&public[33..]),
+        }]})
+        .to_string();
+
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let hits_clone = hits.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                assert_eq!(request.url(), "/.well-known/jwks.json");
+                hits_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let _ = request.respond(tiny_http::Response::from_string(jwks.clone()));
+            }
+        });
+
+        let header = URL_SAFE_NO_PAD.encode(json!({"alg": "ES256", "kid": "k1"}).to_string());
+        let payload = URL_SAFE_NO_PAD.encode(json!({"email": "user@example.com"}).to_string());
+        let signing_input = format!("{}.{}", header, payload);
+        let signature = key_pair.sign(&rng, signing_input.as_bytes()).unwrap();
+        let token = format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.as_ref()));
+
+        let cache = JwksCache::new(&issuer);
+        cache.verify(&token).await.unwrap();
+        cache.verify(&token).await.unwrap();
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let forged_payload = URL_SAFE_NO_PAD.encode(json!({"email": "admin@example.com"}).to_string());
+        let forged = token.replacen(&payload, &forged_payload, 1);
+        assert!(matches!(cache.verify(&forged).await, Err(ClaimError::Signature(_))));
+    }
+}
@@ -1,11 +1,16 @@
+pub mod accounts;
 pub mod oauth_server;
 pub mod token_data;
 pub mod pkce;
 pub mod auth_storage;
+pub mod credential_resolver;
+pub mod credential_store;
+pub mod device_code;
+pub mod jwt;
 
 pub use oauth_server::{ServerOptions, run_login_server};
 pub use token_data::{AuthMode};
-pub use auth_storage::{load_auth, login_with_api_key, logout};
+pub use auth_storage::{load_auth, login_with_api_key, logout, refresh_tokens_if_needed, RefreshOutcome};
 
 // OpenAI OAuth client ID for Codex (same as CLI)
 pub const CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
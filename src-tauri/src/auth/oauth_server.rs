@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use base64::{Engine, engine::general_purpose};
 use tiny_http::{Header, Request, Response, Server};
@@ -10,53 +11,128 @@ use url::Url;
 
 use crate::auth::pkce::{PkceCodes, generate_pkce};
 use crate::auth::auth_storage::save_tokens;
-use crate::auth::token_data::TokenData;
+use crate::auth::credential_store::SecretBackend;
+use crate::auth::device_code::SLOW_DOWN_STEP;
+use crate::auth::token_data::{IdTokenInfo, TokenData};
 
-const DEFAULT_ISSUER: &str = "https://auth.openai.com";
+pub const DEFAULT_ISSUER: &str = "https://auth.openai.com";
+/// Overrides `DEFAULT_ISSUER`, e.g. to log in against a staging server.
+pub const ISSUER_ENV_VAR: &str = "CODEXIA_AUTH_ISSUER";
 const DEFAULT_PORT: u16 = 1455;
+/// How long to wait for the browser callback before giving up.
+pub const DEFAULT_LOGIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// How often the server loop wakes up to check for cancellation and timeout.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ServerOptions {
     pub codex_home: PathBuf,
+    /// Unlocks the encrypted store the tokens are saved to, if enabled.
+    pub backend: Option<Arc<dyn SecretBackend>>,
     pub client_id: String,
     pub issuer: String,
     pub port: u16,
     pub open_browser: bool,
+    pub timeout: Duration,
+    /// Device code logins add this to the poll interval each time the
+    /// issuer answers `slow_down`.
+    pub slow_down_step: Duration,
 }
 
 impl ServerOptions {
     pub fn new(codex_home: PathBuf, client_id: String) -> Self {
         Self {
             codex_home,
+            backend: None,
             client_id,
             issuer: DEFAULT_ISSUER.to_string(),
             port: DEFAULT_PORT,
             open_browser: true,
+            timeout: DEFAULT_LOGIN_TIMEOUT,
+            slow_down_step: SLOW_DOWN_STEP,
         }
     }
 }
 
+/// The issuer logins, refreshes and token checks all use.
+pub fn configured_issuer() -> String {
+    std::env::var(ISSUER_ENV_VAR)
+        .ok()
+        .map(|issuer| issuer.trim().trim_end_matches('/').to_string())
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or_else(|| DEFAULT_ISSUER.to_string())
+}
+
 pub struct LoginServer {
     pub auth_url: String,
+    /// Port actually bound, which differs from the requested one after a fallback.
+    pub port: u16,
     pub server_handle: thread::JoinHandle<io::Result<()>>,
+    shutdown: ShutdownHandle,
 }
 
 impl LoginServer {
+    /// Waits for the login to finish. Cancellation is reported as
+    /// `ErrorKind::Interrupted` and an expired login as `ErrorKind::TimedOut`.
     pub fn block_until_done(self) -> io::Result<()> {
         self.server_handle
             .join()
             .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to join server thread"))?
     }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+}
+
+/// Stops a running login server from another thread.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+    server: Arc<Server>,
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+        self.server.unblock();
+    }
+
+    pub fn is_same(&self, other: &ShutdownHandle) -> bool {
+        Arc::ptr_eq(&self.flag, &other.flag)
+    }
+}
+
+enum RequestOutcome {
+    Continue,
+    Completed,
+    Failed(String),
+}
+
+/// Binds the requested port, falling back to an ephemeral one when it is
+/// taken (for example by a login started from the CLI).
+fn bind_server(port: u16) -> io::Result<(Server, u16)> {
+    let server = match Server::http(format!("127.0.0.1:{}", port)) {
+        Ok(server) => server,
+        Err(e) if port != 0 => {
+            log::warn!("Login port {} unavailable ({}), using an ephemeral port", port, e);
+            Server::http("127.0.0.1:0").map_err(|e| io::Error::new(io::ErrorKind::AddrInUse, e))?
+        }
+        Err(e) => return Err(io::Error::new(io::ErrorKind::AddrInUse, e)),
+    };
+    let actual_port = server
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .ok_or_else(|| io::Error::other("Login server is not bound to a TCP port"))?;
+    Ok((server, actual_port))
 }
 
 pub fn run_login_server(
     opts: ServerOptions,
     shutdown_flag: Option<Arc<AtomicBool>>,
 ) -> io::Result<LoginServer> {
-    let server = Server::http(format!("127.0.0.1:{}", opts.port))
-        .map_err(|e| io::Error::new(io::ErrorKind::AddrInUse, e))?;
-    
-    let actual_port = opts.port; // Use the configured port since tiny_http doesn't provide server_addr()
+    let (server, actual_port) = bind_server(opts.port)?;
     let server = Arc::new(server);
     
     let shutdown_flag = shutdown_flag.unwrap_or_else(|| Arc::new(AtomicBool::new(false)));
@@ -92,41 +168,49 @@ pub fn run_login_server(
     // Clone data for the server thread
     let server_clone = server.clone();
     let shutdown_flag_clone = shutdown_flag.clone();
-    let codex_home = opts.codex_home.clone();
-    let client_id = opts.client_id.clone();
-    let issuer = opts.issuer.clone();
+    let deadline = Instant::now() + opts.timeout;
     
     let server_handle = thread::spawn(move || -> io::Result<()> {
-        for request in server_clone.incoming_requests() {
+        loop {
             if shutdown_flag_clone.load(Ordering::SeqCst) {
-                break;
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "Login cancelled"));
             }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Login timed out"));
+            }
+
+            let Some(request) = server_clone.recv_timeout(POLL_INTERVAL.min(deadline - now))? else {
+                continue;
+            };
             
             match handle_request(
                 request,
                 &pkce,
                 &state,
-                &codex_home,
-                &client_id,
-                &issuer,
+                &opts,
                 &redirect_uri,
             ) {
-                Ok(should_continue) => {
-                    if !should_continue {
-                        break;
-                    }
+                Ok(RequestOutcome::Continue) => {}
+                Ok(RequestOutcome::Completed) => return Ok(()),
+                Ok(RequestOutcome::Failed(message)) => {
+                    return Err(io::Error::other(message));
                 }
                 Err(e) => {
                     log::error!("Error handling request: {}", e);
                 }
             }
         }
-        Ok(())
     });
     
     Ok(LoginServer {
         auth_url: auth_url_string,
+        port: actual_port,
         server_handle,
+        shutdown: ShutdownHandle {
+            flag: shutdown_flag,
+            server,
+        },
     })
 }
 
@@ -134,11 +218,9 @@ fn handle_request(
     request: Request,
     pkce: &PkceCodes,
     expected_state: &str,
-    codex_home: &PathBuf,
-    client_id: &str,
-    issuer: &str,
+    opts: &ServerOptions,
     redirect_uri: &str,
-) -> io::Result<bool> {
+) -> io::Result<RequestOutcome> {
     let url = request.url();
     
     if url.starts_with("/callback") {
@@ -170,7 +252,7 @@ fn handle_request(
                 .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap())
                 .with_status_code(400);
             let _ = request.respond(response);
-            return Ok(false);
+            return Ok(RequestOutcome::Failed(format!("Authorization failed: {}", error_msg)));
         }
         
         // Validate state
@@ -180,22 +262,22 @@ fn handle_request(
                 .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap())
                 .with_status_code(400);
             let _ = request.respond(response);
-            return Ok(false);
+            return Ok(RequestOutcome::Failed("Invalid state parameter".to_string()));
         }
         
         // Exchange code for tokens
         if let Some(auth_code) = code {
-            match exchange_code_for_tokens(auth_code, pkce, client_id, issuer, redirect_uri) {
+            match exchange_code_for_tokens(auth_code, pkce, &opts.client_id, &opts.issuer, redirect_uri) {
                 Ok(tokens) => {
                     // Save tokens to auth.json
-                    if let Err(e) = save_tokens(codex_home, &tokens) {
+                    if let Err(e) = save_tokens(&opts.codex_home, opts.backend.clone(), &tokens) {
                         log::error!("Failed to save tokens: {}", e);
                         let response_body = "<html><body><h1>Authentication Failed</h1><p>Failed to save tokens</p></body></html>";
                         let response = Response::from_string(response_body)
                             .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap())
                             .with_status_code(500);
                         let _ = request.respond(response);
-                        return Ok(false);
+                        return Ok(RequestOutcome::Failed(format!("Failed to save tokens: {}", e)));
                     }
                     
                     // Success response
@@ -203,7 +285,7 @@ fn handle_request(
                     let response = Response::from_string(response_body)
                         .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap());
                     let _ = request.respond(response);
-                    return Ok(false); // Stop server
+                    return Ok(RequestOutcome::Completed);
                 }
                 Err(e) => {
                     log::error!("Failed to exchange code for tokens: {}", e);
@@ -215,7 +297,7 @@ fn handle_request(
                         .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap())
                         .with_status_code(500);
                     let _ = request.respond(response);
-                    return Ok(false);
+                    return Ok(RequestOutcome::Failed(format!("Token exchange failed: {}", e)));
                 }
             }
         }
@@ -227,7 +309,7 @@ fn handle_request(
         .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap());
     let _ = request.respond(response);
     
-    Ok(true) // Continue server
+    Ok(RequestOutcome::Continue)
 }
 
 fn exchange_code_for_tokens(
@@ -259,7 +341,14 @@ fn exchange_code_for_tokens(
     }
     
     let token_response: serde_json::Value = response.json()?;
-    
+    parse_token_response(&token_response)
+}
+
+/// Extracts the tokens from a successful authorization-code or device-code
+/// grant, where all three tokens are required.
+pub(crate) fn parse_token_response(
+    token_response: &serde_json::Value,
+) -> Result<TokenData, Box<dyn std::error::Error>> {
     let access_token = token_response
         .get("access_token")
         .and_then(|v| v.as_str())
@@ -287,9 +376,120 @@ fn exchange_code_for_tokens(
     })
 }
 
+/// Tokens returned by a refresh grant. The issuer may omit the ID token and
+/// refresh token, in which case the existing ones stay valid.
+pub struct RefreshedTokens {
+    pub id_token: Option<IdTokenInfo>,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+}
+
+pub fn refresh_access_token(
+    issuer: &str,
+    client_id: &str,
+    refresh_token: &str,
+) -> Result<RefreshedTokens, Box<dyn std::error::Error>> {
+    let token_url = format!("{}/token", issuer);
+
+    let params = [
+        ("grant_type", "refresh_token"),
+        ("client_id", client_id),
+        ("refresh_token", refresh_token),
+        ("scope", "openid profile email"),
+    ];
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let response = client
+        .post(&token_url)
+        .form(&params)
+        .send()?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Token refresh failed ({}): {}", status, error_text).into());
+    }
+
+    let token_response: serde_json::Value = response.json()?;
+
+    let access_token = token_response
+        .get("access_token")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing access_token")?
+        .to_string();
+
+    let refresh_token = token_response
+        .get("refresh_token")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let id_token = match token_response.get("id_token").and_then(|v| v.as_str()) {
+        Some(jwt) => Some(crate::auth::token_data::parse_id_token(jwt)?),
+        None => None,
+    };
+
+    Ok(RefreshedTokens {
+        id_token,
+        access_token,
+        refresh_token,
+    })
+}
+
 fn generate_state() -> String {
     use rand::RngCore;
     let mut bytes = [0u8; 32];
     rand::thread_rng().fill_bytes(&mut bytes);
     general_purpose::URL_SAFE_NO_PAD.encode(bytes)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn options(codex_home: PathBuf, port: u16, timeout: Duration) -> ServerOptions {
+        ServerOptions {
+            port,
+            open_browser: false,
+            timeout,
+            ..ServerOptions::new(codex_home, "client".to_string())
+        }
+    }
+
+    #[test]
+    fn test_port_fallback_and_cancel() {
+        let dir = tempdir().unwrap();
+        let busy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let busy_port = busy.local_addr().unwrap().port();
+
+        let server = run_login_server(options(dir.path().to_path_buf(), busy_port, DEFAULT_LOGIN_TIMEOUT), None).unwrap();
+        assert_ne!(server.port, busy_port);
+        assert!(server.auth_url.contains(&format!("127.0.0.1%3A{}%2Fcallback", server.port)));
+
+        server.shutdown_handle().shutdown();
+        let err = server.block_until_done().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_login_timeout() {
+        let dir = tempdir().unwrap();
+        let server = run_login_server(options(dir.path().to_path_buf(), 0, Duration::from_millis(100)), None).unwrap();
+        let err = server.block_until_done().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_callback_error_fails_login() {
+        let dir = tempdir().unwrap();
+        let server = run_login_server(options(dir.path().to_path_buf(), 0, DEFAULT_LOGIN_TIMEOUT), None).unwrap();
+        let url = format!("http://127.0.0.1:{}/callback?error=access_denied", server.port);
+        let response = reqwest::blocking::get(&url).unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+
+        let err = server.block_until_done().unwrap_err();
+        assert!(err.to_string().contains("access_denied"));
+    }
+}
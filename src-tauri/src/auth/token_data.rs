@@ -1,10 +1,13 @@
 use base64::Engine;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Clone, Debug, PartialEq, Copy)]
+#[derive(Clone, Debug, PartialEq, Copy, Serialize, Deserialize)]
 pub enum AuthMode {
+    #[serde(rename = "api_key")]
     ApiKey,
+    #[serde(rename = "chatgpt")]
     ChatGPT,
 }
 
@@ -130,6 +133,18 @@ pub fn parse_id_token(jwt: &str) -> Result<IdTokenInfo, TokenError> {
     })
 }
 
+/// Reads the `exp` claim of a JWT without verifying it. Returns `None` for
+/// opaque tokens or tokens without an expiry.
+pub fn parse_jwt_expiry(jwt: &str) -> Option<DateTime<Utc>> {
+    let payload_b64 = jwt.split('.').nth(1)?;
+    let payload_bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload_b64)
+        .ok()?;
+    let payload: serde_json::Value = serde_json::from_slice(&payload_bytes).ok()?;
+    let exp = payload.get("exp")?.as_i64()?;
+    DateTime::from_timestamp(exp, 0)
+}
+
 // Custom serialization/deserialization for IdTokenInfo
 fn serialize_id_token<S>(token: &IdTokenInfo, serializer: S) -> Result<S::Ok, S::Error>
 where
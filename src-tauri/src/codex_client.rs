@@ -3,15 +3,22 @@ use chrono;
 use serde_json;
 use std::process::Stdio;
 use std::collections::HashMap;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::fs::OpenOptions;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
-use crate::config::{read_model_providers, read_profiles};
+use crate::auth::credential_resolver::{provider_env_var, resolve_credential, CredentialSource};
+use crate::auth::credential_store::{unseal_for_spawn, CredentialStoreError};
+use crate::auth::OPENAI_API_KEY_ENV_VAR;
+use crate::config::read_model_providers;
 use crate::protocol::{CodexConfig, Event, InputItem, Op, Submission};
+use crate::services::context_window::ContextMonitor;
+use crate::services::effective_config::resolve_effective_config;
+use crate::services::usage::TokenUsage;
+use crate::state::CodexState;
 
 // Helper function to extract session_id from codex events
 fn get_session_id_from_event(event: &Event) -> Option<String> {
@@ -28,13 +35,18 @@ pub struct CodexClient {
     session_id: String,
     process: Option<Child>,
     stdin_tx: Option<mpsc::UnboundedSender<String>>,
-    #[allow(dead_code)]
     config: CodexConfig,
+    /// Where this session's API key came from (e.g. `env:WORK_KEY`), never the key.
+    credential_source: Option<String>,
 }
 
 impl CodexClient {
     pub async fn new(app: &AppHandle, session_id: String, config: CodexConfig) -> Result<Self> {
-        log::debug!("Creating CodexClient for session and config: {} {:?}", session_id, config);
+        // Not the whole config: it may carry an inline API key
+        log::debug!(
+            "Creating CodexClient for session {} (provider: {}, model: {}, cwd: {})",
+            session_id, config.provider, config.model, config.working_directory
+        );
 
         // Build codex command based on configuration
         let (command, args): (String, Vec<String>) =
@@ -51,156 +63,67 @@ impl CodexClient {
             cmd.args(&args);
         }
         cmd.arg("proto");
+
+        let effective = resolve_effective_config(&config)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
         
         // Set up environment variables for API keys
         let mut env_vars = HashMap::new();
-        log::debug!("Config provider: {}, API key present: {}", 
-                   config.provider, 
-                   config.api_key.as_ref().map_or(false, |k| !k.is_empty()));
-        
-        if let Some(api_key) = &config.api_key {
-            if !api_key.is_empty() {
-                log::debug!("API key provided, length: {}", api_key.len());
-                // Try to get the env_key from provider configuration first
-                if let Ok(providers) = read_model_providers().await {
-                    log::debug!("Successfully read providers, available: {:?}", providers.keys().collect::<Vec<_>>());
-                    
-                    // Try exact match first, then lowercase match
-                    let provider_config = providers.get(&config.provider)
-                        .or_else(|| providers.get(&config.provider.to_lowercase()));
-                    
-                    if let Some(provider_config) = provider_config {
-                        log::debug!("Found provider config: {:?}", provider_config);
-                        if !provider_config.env_key.is_empty() {
-                            log::debug!("Setting env var {} from provider config", provider_config.env_key);
-                            env_vars.insert(provider_config.env_key.clone(), api_key.clone());
-                        } else {
-                            log::debug!("Provider config has empty env_key");
-                        }
-                    } else {
-                        log::debug!("Provider {} not found in config", config.provider);
-                    }
-                } else {
-                    log::debug!("Failed to read providers, using fallback mapping");
-                    // Fallback mapping if config reading fails
-                    let env_var_name = match config.provider.as_str() {
-                        "gemini" => "GEMINI_API_KEY",
-                        "openai" => "OPENAI_API_KEY", 
-                        "openrouter" => "OPENROUTER_API_KEY",
-                        "ollama" => "OLLAMA_API_KEY",
-                        _ => "OPENAI_API_KEY", // fallback
-                    };
-                    log::debug!("Using fallback env var: {}", env_var_name);
-                    env_vars.insert(env_var_name.to_string(), api_key.clone());
-                }
-            } else {
-                log::debug!("API key is empty");
-            }
-        } else {
-            log::debug!("No API key provided");
+        let mut credential_source = None;
+        let codex_home = dirs::home_dir().map(|home| home.join(".codex"));
+
+        let backend = app.state::<CodexState>().credential_backend.lock().await.clone();
+        let providers = read_model_providers().await.ok();
+        let env_var = provider_env_var(&effective.provider_id, providers.as_ref());
+
+        // An explicit source wins; otherwise a non-empty inline key is used
+        let source = config.credential_source.clone().or_else(|| {
+            config
+                .api_key
+                .as_deref()
+                .is_some_and(|k| !k.is_empty())
+                .then_some(CredentialSource::Inline)
+        });
+        if let Some(source) = source {
+            let home = codex_home.clone().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+            let env_var = env_var.clone().map_err(|e| anyhow::anyhow!(e))?;
+            let credential = resolve_credential(&source, config.api_key.as_deref(), &home, backend.clone())
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
+            log::info!(
+                "Session {} uses API key from {} via {}",
+                session_id, credential.source, env_var
+            );
+            env_vars.insert(env_var, credential.secret.expose().to_string());
+            credential_source = Some(credential.source);
         }
 
-        // Load provider configuration from config.toml if provider is specified
-        if !config.provider.is_empty() && config.provider != "openai" {
-            if let Ok(providers) = read_model_providers().await {
-                if let Ok(_profiles) = read_profiles().await {
-                    // Check if there's a matching provider in config (try exact match first, then lowercase)
-                    let provider_config = providers.get(&config.provider)
-                        .or_else(|| providers.get(&config.provider.to_lowercase()));
-                    
-                    if let Some(provider_config) = provider_config {
-                        // Set model provider based on config
-                        cmd.arg("-c")
-                            .arg(format!("model_provider={}", provider_config.name));
-
-                        // Set base URL if available
-                        if !provider_config.base_url.is_empty() {
-                            cmd.arg("-c")
-                                .arg(format!("base_url={}", provider_config.base_url));
-                        }
-
-                        // API key will be provided via environment variable - no need to modify provider config
-
-                        // Always use model from config (user selection), not from profile
-                        // This ensures user's model choice in the GUI takes precedence
-                        if !config.model.is_empty() {
-                            cmd.arg("-c").arg(format!("model={}", config.model));
-                        }
-                    } else {
-                        // Fallback to original logic for custom providers
-                        if config.use_oss {
-                            cmd.arg("-c").arg("model_provider=oss");
-                        } else {
-                            cmd.arg("-c")
-                                .arg(format!("model_provider={}", config.provider));
-                        }
-
-                        if !config.model.is_empty() {
-                            cmd.arg("-c").arg(format!("model={}", config.model));
-                        }
-
-                        // API key will be provided via environment variable for custom providers
+        // Credentials in the encrypted store are only decrypted here, right
+        // before the CLI starts. A locked store only matters when the session
+        // has no key of its own.
+        let mut auth_file = None;
+        if let Some(codex_home) = codex_home {
+            let has_key = credential_source.is_some()
+                || env_var.as_ref().is_ok_and(|var| std::env::var(var).is_ok_and(|k| !k.is_empty()));
+            match tokio::task::spawn_blocking(move || unseal_for_spawn(&codex_home, backend)).await? {
+                Ok(unsealed) => {
+                    if let Some(api_key) = unsealed.api_key {
+                        env_vars
+                            .entry(OPENAI_API_KEY_ENV_VAR.to_string())
+                            .or_insert(api_key);
                     }
+                    auth_file = unsealed.auth_file;
                 }
-            } else {
-                // Fallback to original logic if config reading fails
-                if config.use_oss {
-                    cmd.arg("-c").arg("model_provider=oss");
-                } else {
-                    cmd.arg("-c")
-                        .arg(format!("model_provider={}", config.provider));
-                }
-
-                if !config.model.is_empty() {
-                    cmd.arg("-c").arg(format!("model={}", config.model));
+                Err(CredentialStoreError::Locked) if has_key => {
+                    log::info!("Session {} starts without the locked credential store", session_id);
                 }
-
-                // API key will be provided via environment variable
-            }
-        } else {
-            // Original logic for OSS and default cases
-            if config.use_oss {
-                cmd.arg("-c").arg("model_provider=oss");
-            }
-
-            if !config.model.is_empty() {
-                cmd.arg("-c").arg(format!("model={}", config.model));
+                Err(e) => return Err(anyhow::anyhow!("Failed to read encrypted credentials: {}", e)),
             }
-
-            // API key will be provided via environment variable for OpenAI
-        }
-
-        if !config.approval_policy.is_empty() {
-            cmd.arg("-c")
-                .arg(format!("approval_policy={}", config.approval_policy));
         }
 
-        if !config.sandbox_mode.is_empty() {
-            let sandbox_config = match config.sandbox_mode.as_str() {
-                "read-only" => "sandbox_mode=read-only".to_string(),
-                "workspace-write" => "sandbox_mode=workspace-write".to_string(),
-                "danger-full-access" => "sandbox_mode=danger-full-access".to_string(),
-                _ => "sandbox_mode=workspace-write".to_string(),
-            };
-            cmd.arg("-c").arg(sandbox_config);
-        }
-
-        // Enable streaming by setting show_raw_agent_reasoning=true
-        // This is required for agent_message_delta events to be generated
-        cmd.arg("-c").arg("show_raw_agent_reasoning=true");
-
-        // Set working directory for the process
-        if !config.working_directory.is_empty() {
-            log::debug!("working_directory: {:?}", config.working_directory);
-            cmd.arg("-c").arg(format!("cwd={}", config.working_directory));
-        }
-
-        // Add custom arguments
-        if let Some(custom_args) = &config.custom_args {
-            for arg in custom_args {
-                cmd.arg(arg);
-            }
-        }
+        // Apply profile, provider/model overrides and the remaining CLI flags
+        cmd.args(&effective.args);
 
         // Print the command to be executed for debugging
         log::debug!("Starting codex with command: {:?}", cmd);
@@ -260,10 +183,15 @@ impl CodexClient {
         let app_clone = app.clone();
         let session_id_clone = session_id.clone();
         let config_clone = config.clone();
+        let usage = app.state::<CodexState>().usage.clone();
+        let effective_model = effective.model.as_ref().map_or(config.model.as_str(), |m| m.value.as_str());
+        usage.start_session(&session_id, &effective.provider_id, effective_model, &config.working_directory);
+        let mut context_monitor = ContextMonitor::new(&session_id, effective_model);
         tokio::spawn(async move {
             let reader = BufReader::new(stdout);
             let mut lines = reader.lines();
             let mut seq: u64 = 0;
+            let mut auth_file = auth_file;
 
             // Prepare CLI-style log file path
             let cli_log_path = match dirs::home_dir() {
@@ -316,6 +244,21 @@ impl CodexClient {
         }
 
             while let Ok(Some(line)) = lines.next_line().await {
+                // The CLI has loaded its credentials by the time it prints
+                // anything, so unsealed tokens can leave the disk. The lease
+                // is kept until the session ends to catch later refreshes.
+                if seq == 0 {
+                    if let Some(mut lease) = auth_file.take() {
+                        auth_file = tokio::task::spawn_blocking(move || {
+                            if let Err(e) = lease.hide() {
+                                log::warn!("Failed to remove unsealed credentials: {}", e);
+                            }
+                            lease
+                        })
+                        .await
+                        .ok();
+                    }
+                }
                 // log::debug!("📥 Received line from codex: {}", line);
                 let parsed = serde_json::from_str::<Event>(&line);
 
@@ -345,6 +288,24 @@ impl CodexClient {
                 seq = seq.saturating_add(1);
 
                 if let Ok(event) = parsed {
+                    usage.record_event(&session_id_clone, &event.msg);
+                    if let crate::protocol::EventMsg::SessionConfigured { model, .. } = &event.msg {
+                        context_monitor.set_model(model);
+                    }
+                    if let Some(last_response) = TokenUsage::from_token_count(&event.msg) {
+                        let (context, crossed) = context_monitor.observe(&last_response);
+                        if crossed {
+                            log::info!(
+                                "Session {} is at {}% of the {} context window",
+                                session_id_clone,
+                                context.threshold.unwrap_or_default(),
+                                context.model
+                            );
+                            let _ = app_clone.emit("context-window-warning", &context);
+                        }
+                        let _ = app_clone.emit("context-usage", &context);
+                    }
+
                     // Write comprehensive CLI-style log entries to capture all agent activity
                     if let Some(cli_f) = cli_log_file.as_mut() {
                         let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.6fZ");
@@ -487,6 +448,10 @@ impl CodexClient {
                     log::warn!("Failed to parse codex event: {}", line);
                 }
             }
+            usage.end_session(&session_id_clone);
+            if let Some(lease) = auth_file {
+                let _ = tokio::task::spawn_blocking(move || drop(lease)).await;
+            }
             log::debug!("Stdout reader terminated for session: {}", session_id_clone);
         });
 
@@ -496,6 +461,7 @@ impl CodexClient {
             process: Some(process),
             stdin_tx: Some(stdin_tx),
             config: config.clone(),
+            credential_source,
         };
 
         Ok(client)
@@ -617,6 +583,16 @@ impl CodexClient {
         self.send_submission(submission).await
     }
 
+    pub async fn compact(&self) -> Result<()> {
+        self.log_to_cli_file("Compacting conversation").await;
+        let submission = Submission {
+            id: Uuid::new_v4().to_string(),
+            op: Op::Compact,
+        };
+
+        self.send_submission(submission).await
+    }
+
     pub async fn close_session(&mut self) -> Result<()> {
         log::debug!("Closing session: {}", self.session_id);
         
@@ -685,4 +661,12 @@ impl CodexClient {
     pub fn is_active(&self) -> bool {
         self.process.is_some() && self.stdin_tx.is_some()
     }
+
+    pub fn working_directory(&self) -> &str {
+        &self.config.working_directory
+    }
+
+    pub fn credential_source(&self) -> Option<&str> {
+        self.credential_source.as_deref()
+    }
 }
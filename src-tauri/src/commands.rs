@@ -1,9 +1,25 @@
 use crate::protocol::CodexConfig;
-use crate::services::{codex, session};
+use crate::services::{codex, effective_config, session};
 use crate::state::CodexState;
-use crate::auth::{AuthMode, ServerOptions, run_login_server, login_with_api_key, logout, CLIENT_ID, load_auth};
-use tauri::{AppHandle, State};
+use crate::config::read_mcp_servers;
+use crate::mcp::{probe_server, McpProbeReport};
+use crate::services::file_finder::FileMatch;
+use crate::services::usage::{UsageGroupBy, UsageRange, UsageReport};
+use crate::providers::{resolve_endpoint, test_endpoint, ProviderModels, ProviderTestReport};
+use crate::auth::{
+    AuthMode, ServerOptions, run_login_server, login_with_api_key, logout, CLIENT_ID,
+    refresh_tokens_if_needed, RefreshOutcome,
+};
+use crate::auth::accounts::{self, AccountSummary};
+use crate::auth::auth_storage::{load_auth_status, AuthStatus, CredentialState};
+use crate::auth::credential_store::{
+    encrypted_store_file, load_live_auth, migrate_auth_json, CredentialStore, CredentialStoreError, EncryptedStore, PassphraseBackend, SecretBackend,
+};
+use crate::auth::device_code::{complete_device_login, request_device_code, DeviceCodeError, DeviceCodePrompt};
+use tauri::{AppHandle, Emitter, State};
 use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 // Re-export types for external use
 pub use crate::services::session::Conversation;
@@ -69,16 +85,38 @@ pub async fn pause_session(state: State<'_, CodexState>, session_id: String) ->
     codex::pause_session(state, session_id).await
 }
 
+#[tauri::command]
+pub async fn compact_session(state: State<'_, CodexState>, session_id: String) -> Result<(), String> {
+    codex::compact_session(state, session_id).await
+}
+
+#[tauri::command]
+pub async fn find_files(
+    state: State<'_, CodexState>,
+    session_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<FileMatch>, String> {
+    codex::find_files(state, session_id, query, limit).await
+}
+
 #[tauri::command]
 pub async fn close_session(state: State<'_, CodexState>, session_id: String) -> Result<(), String> {
     codex::close_session(state, session_id).await
 }
 
 #[tauri::command]
-pub async fn get_running_sessions(state: State<'_, CodexState>) -> Result<Vec<String>, String> {
+pub async fn get_running_sessions(state: State<'_, CodexState>) -> Result<Vec<codex::RunningSession>, String> {
     codex::get_running_sessions(state).await
 }
 
+#[tauri::command]
+pub async fn resolve_effective_config(
+    config: CodexConfig,
+) -> Result<effective_config::EffectiveConfig, String> {
+    effective_config::resolve_effective_config(&config).await
+}
+
 #[tauri::command]
 pub async fn check_codex_version() -> Result<String, String> {
     codex::check_codex_version().await
@@ -157,90 +195,197 @@ pub async fn read_history_file() -> Result<String, String> {
     fs::read_to_string(&history_path).map_err(|e| format!("Failed to read history file: {}", e))
 }
 
+#[tauri::command]
+pub async fn probe_mcp_server(name: String) -> Result<McpProbeReport, String> {
+    let servers = read_mcp_servers().await?;
+    let config = servers
+        .get(&name)
+        .ok_or_else(|| format!("MCP server '{}' not found", name))?;
+    Ok(probe_server(&name, config).await)
+}
+
+#[tauri::command]
+pub async fn list_provider_models(
+    state: State<'_, CodexState>,
+    provider: String,
+    use_oss: Option<bool>,
+    refresh: Option<bool>,
+) -> Result<ProviderModels, String> {
+    let endpoint = resolve_endpoint(&provider, use_oss.unwrap_or(false)).await?;
+    state
+        .model_catalog
+        .list(&endpoint, refresh.unwrap_or(false))
+        .await
+}
+
+#[tauri::command]
+pub async fn test_model_provider(
+    name: String,
+    api_key: Option<String>,
+    model: Option<String>,
+) -> Result<ProviderTestReport, String> {
+    let mut endpoint = resolve_endpoint(&name, false).await?;
+    let key_source = match api_key.filter(|k| !k.is_empty()) {
+        Some(key) => {
+            endpoint.api_key = Some(key);
+            Some("argument".to_string())
+        }
+        None => endpoint.api_key.as_ref().and(endpoint.env_key.clone()),
+    };
+    let model = model.filter(|m| !m.is_empty());
+    Ok(test_endpoint(&endpoint, model.as_deref(), key_source).await)
+}
+
+/// Token usage and cost between `range.since` and `range.until`, grouped by
+/// turn, session, model, project or day.
+#[tauri::command]
+pub async fn get_usage_report(
+    state: State<'_, CodexState>,
+    range: Option<UsageRange>,
+    group_by: UsageGroupBy,
+) -> Result<UsageReport, String> {
+    let usage = state.usage.clone();
+    tokio::task::spawn_blocking(move || usage.report(range.unwrap_or_default(), group_by))
+        .await
+        .map_err(|e| format!("Usage report task failed: {}", e))?
+}
+
 // OAuth Authentication Commands
 
+/// Refreshes ChatGPT tokens that are close to expiry and notifies the
+/// frontend with `auth-changed`. Failures are logged; the stale tokens stay
+/// in place so the CLI can still report a meaningful error.
+async fn refresh_auth_if_needed(
+    app: &AppHandle,
+    codex_home: &std::path::Path,
+    backend: Option<Arc<dyn SecretBackend>>,
+    issuer: &str,
+) {
+    let codex_home = codex_home.to_path_buf();
+    let issuer = issuer.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        refresh_tokens_if_needed(&codex_home, backend, &issuer, CLIENT_ID).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(RefreshOutcome::Refreshed)) => {
+            log::info!("Refreshed ChatGPT access token");
+            if let Err(e) = app.emit("auth-changed", "token_refreshed") {
+                log::error!("Failed to emit auth-changed event: {}", e);
+            }
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => log::warn!("Token refresh failed: {}", e),
+        Err(e) => log::error!("Token refresh task failed: {}", e),
+    }
+}
+
 #[tauri::command]
-pub async fn get_auth_status() -> Result<Option<String>, String> {
+pub async fn get_auth_status(
+    app: AppHandle,
+    state: State<'_, CodexState>,
+    verify_signature: Option<bool>,
+) -> Result<Option<AuthStatus>, String> {
     let codex_home = dirs::home_dir()
         .ok_or("Could not find home directory")?
         .join(".codex");
 
-    // 1) Check for environment variable first (matches CLI behavior)
-    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-        if !api_key.is_empty() {
-            return Ok(Some("api_key".to_string()));
-        }
-    }
+    let backend = state.credential_backend.lock().await.clone();
+    refresh_auth_if_needed(&app, &codex_home, backend.clone(), &state.issuer).await;
 
-    // 2) Load auth via shared helper (reads ~/.codex/auth.json like the CLI)
-    match load_auth(&codex_home, false).await {
-        Ok(Some(auth)) => {
-            match auth.mode {
-                AuthMode::ApiKey => Ok(Some("api_key".to_string())),
-                AuthMode::ChatGPT => {
-                    // Read tokens directly from auth.json to avoid non-Send futures
-                    let auth_file = codex_home.join("auth.json");
-                    if auth_file.exists() {
-                        match std::fs::read_to_string(&auth_file) {
-                            Ok(content) => {
-                                match serde_json::from_str::<serde_json::Value>(&content) {
-                                    Ok(json) => {
-                                        if let Some(id_token) = json
-                                            .get("tokens")
-                                            .and_then(|t| t.get("id_token"))
-                                            .and_then(|v| v.as_str())
-                                        {
-                                            match crate::auth::token_data::parse_id_token(id_token) {
-                                                Ok(info) => {
-                                                    let email = info
-                                                        .email
-                                                        .as_deref()
-                                                        .unwrap_or("chatgpt")
-                                                        .to_string();
-                                                    let plan = info
-                                                        .get_chatgpt_plan_type()
-                                                        .unwrap_or_else(|| "unknown".to_string());
-                                                    Ok(Some(format!("chatgpt:{}:{}", email, plan)))
-                                                }
-                                                Err(_) => Ok(Some("chatgpt:invalid".to_string())),
-                                            }
-                                        } else {
-                                            Ok(Some("chatgpt:invalid".to_string()))
-                                        }
-                                    }
-                                    Err(_) => Ok(Some("chatgpt:invalid".to_string())),
-                                }
-                            }
-                            Err(_) => Ok(Some("chatgpt:invalid".to_string())),
-                        }
-                    } else {
-                        Ok(Some("chatgpt:invalid".to_string()))
-                    }
+    let status_home = codex_home.clone();
+    let status_backend = backend.clone();
+    let issuer = state.issuer.clone();
+    let mut status = tokio::task::spawn_blocking(move || {
+        load_auth_status(&status_home, status_backend, &issuer, CLIENT_ID).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Auth status task failed: {}", e))?
+    .map_err(|e| format!("Failed to read auth status: {}", e))?;
+
+    // Signature checks need the issuer's key set, so they are opt-in
+    if let Some(status) = status.as_mut() {
+        if verify_signature.unwrap_or(false)
+            && status.mode == Some(AuthMode::ChatGPT)
+            && status.state == CredentialState::Valid
+        {
+            let id_token = tokio::task::spawn_blocking(move || load_live_auth(&codex_home, backend))
+                .await
+                .ok()
+                .and_then(Result::ok)
+                .flatten()
+                .and_then(|auth| auth.tokens)
+                .map(|tokens| tokens.id_token.raw_jwt);
+            if let Some(id_token) = id_token {
+                if let Err(e) = state.jwks.verify(&id_token).await {
+                    log::warn!("Stored ID token rejected: {}", e);
+                    status.state = CredentialState::Invalid;
                 }
             }
         }
-        Ok(None) => Ok(None),
-        Err(e) => Err(format!("Failed to read auth status: {}", e)),
     }
+    Ok(status)
 }
 
 #[tauri::command]
-pub async fn start_login_flow() -> Result<String, String> {
+pub async fn start_login_flow(
+    app: AppHandle,
+    state: State<'_, CodexState>,
+    port: Option<u16>,
+    timeout_secs: Option<u64>,
+) -> Result<String, String> {
     let codex_home = dirs::home_dir()
         .ok_or("Could not find home directory")?
         .join(".codex");
     
-    let opts = ServerOptions::new(codex_home, CLIENT_ID.to_string());
+    let mut opts = ServerOptions::new(codex_home, CLIENT_ID.to_string());
+    opts.issuer = state.issuer.clone();
+    opts.backend = state.credential_backend.lock().await.clone();
+    if let Some(port) = port {
+        opts.port = port;
+    }
+    if let Some(secs) = timeout_secs {
+        opts.timeout = std::time::Duration::from_secs(secs);
+    }
+
+    // Only one browser login can be pending; a new attempt replaces the old one.
+    let mut active = state.login_server.lock().await;
+    if let Some(previous) = active.take() {
+        previous.shutdown();
+    }
     
     match run_login_server(opts, None) {
         Ok(server) => {
             let auth_url = server.auth_url.clone();
-            
-            // Spawn a task to handle the server completion
+            let handle = server.shutdown_handle();
+            *active = Some(handle.clone());
+            drop(active);
+
+            let login_server = state.login_server.clone();
             tokio::spawn(async move {
-                if let Err(e) = server.block_until_done() {
+                let result = tokio::task::spawn_blocking(move || server.block_until_done())
+                    .await
+                    .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+
+                {
+                    let mut active = login_server.lock().await;
+                    if active.as_ref().is_some_and(|current| current.is_same(&handle)) {
+                        *active = None;
+                    }
+                }
+
+                if let Err(e) = &result {
                     log::error!("Login server error: {}", e);
                 }
+                emit_login_result(
+                    &app,
+                    result.map_err(|e| {
+                        let cancelled = e.kind() == std::io::ErrorKind::Interrupted;
+                        let timed_out = e.kind() == std::io::ErrorKind::TimedOut;
+                        (e.to_string(), cancelled, timed_out)
+                    }),
+                );
             });
             
             Ok(auth_url)
@@ -249,28 +394,126 @@ pub async fn start_login_flow() -> Result<String, String> {
     }
 }
 
+/// Emits `login-completed`, or `login-failed` with the error and whether it
+/// was a cancellation or timeout.
+fn emit_login_result(app: &AppHandle, result: Result<(), (String, bool, bool)>) {
+    let emitted = match result {
+        Ok(()) => app.emit("login-completed", ()),
+        Err((error, cancelled, timed_out)) => app.emit(
+            "login-failed",
+            serde_json::json!({
+                "error": error,
+                "cancelled": cancelled,
+                "timed_out": timed_out,
+            }),
+        ),
+    };
+    if let Err(e) = emitted {
+        log::error!("Failed to emit login event: {}", e);
+    }
+}
+
+/// Starts an OAuth device authorization login for machines whose browser
+/// can't reach the localhost callback. Returns the code the user enters at
+/// the verification URL; completion is reported through login events.
+#[tauri::command]
+pub async fn start_device_login(
+    app: AppHandle,
+    state: State<'_, CodexState>,
+) -> Result<DeviceCodePrompt, String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+    let mut opts = ServerOptions::new(codex_home, CLIENT_ID.to_string());
+    opts.issuer = state.issuer.clone();
+    opts.backend = state.credential_backend.lock().await.clone();
+
+    let request_opts = opts.clone();
+    let authorization = tokio::task::spawn_blocking(move || request_device_code(&request_opts))
+        .await
+        .map_err(|e| format!("Device login task failed: {}", e))?
+        .map_err(|e| format!("Failed to start device login: {}", e))?;
+    let prompt = authorization.prompt.clone();
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    if let Some(previous) = state.device_login.lock().await.replace(cancel.clone()) {
+        previous.store(true, Ordering::SeqCst);
+    }
+
+    let device_login = state.device_login.clone();
+    tokio::spawn(async move {
+        let flag = cancel.clone();
+        let result = tokio::task::spawn_blocking(move || complete_device_login(&opts, &authorization, &flag))
+            .await
+            .unwrap_or_else(|e| Err(DeviceCodeError::InvalidResponse(e.to_string())));
+
+        {
+            let mut active = device_login.lock().await;
+            if active.as_ref().is_some_and(|current| Arc::ptr_eq(current, &cancel)) {
+                *active = None;
+            }
+        }
+
+        if let Err(e) = &result {
+            log::error!("Device login error: {}", e);
+        }
+        emit_login_result(
+            &app,
+            result.map_err(|e| {
+                let cancelled = matches!(e, DeviceCodeError::Cancelled);
+                let timed_out = matches!(e, DeviceCodeError::Expired);
+                (e.to_string(), cancelled, timed_out)
+            }),
+        );
+    });
+
+    Ok(prompt)
+}
+
+/// Stops a pending browser or device-code login. Returns false when none
+/// was running.
+#[tauri::command]
+pub async fn cancel_login_flow(state: State<'_, CodexState>) -> Result<bool, String> {
+    let mut cancelled = false;
+    if let Some(handle) = state.login_server.lock().await.take() {
+        handle.shutdown();
+        cancelled = true;
+    }
+    if let Some(flag) = state.device_login.lock().await.take() {
+        flag.store(true, Ordering::SeqCst);
+        cancelled = true;
+    }
+    Ok(cancelled)
+}
+
 #[tauri::command]
-pub async fn login_with_api_key_command(api_key: String) -> Result<(), String> {
+pub async fn login_with_api_key_command(state: State<'_, CodexState>, api_key: String) -> Result<(), String> {
     let codex_home = dirs::home_dir()
         .ok_or("Could not find home directory")?
         .join(".codex");
+    let backend = state.credential_backend.lock().await.clone();
     
-    login_with_api_key(&codex_home, &api_key)
-        .map_err(|e| format!("Failed to save API key: {}", e))
+    tokio::task::spawn_blocking(move || {
+        login_with_api_key(&codex_home, backend, &api_key).map_err(|e| format!("Failed to save API key: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Login task failed: {}", e))?
 }
 
 #[tauri::command]
-pub async fn logout_command() -> Result<bool, String> {
+pub async fn logout_command(state: State<'_, CodexState>) -> Result<bool, String> {
     let codex_home = dirs::home_dir()
         .ok_or("Could not find home directory")?
         .join(".codex");
+    let backend = state.credential_backend.lock().await.clone();
     
-    logout(&codex_home)
-        .map_err(|e| format!("Failed to logout: {}", e))
+    tokio::task::spawn_blocking(move || logout(&codex_home, backend).map_err(|e| format!("Failed to logout: {}", e)))
+        .await
+        .map_err(|e| format!("Logout task failed: {}", e))?
 }
 
 #[tauri::command]
-pub async fn get_auth_token() -> Result<Option<String>, String> {
+pub async fn get_auth_token(state: State<'_, CodexState>) -> Result<Option<String>, String> {
     // 1) Environment variable takes precedence
     if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
         if !api_key.is_empty() {
@@ -278,15 +521,130 @@ pub async fn get_auth_token() -> Result<Option<String>, String> {
         }
     }
 
-    // 2) If an API key is stored in auth.json (CLI-compatible), return it
+    // 2) Else an API key stored in auth.json or the encrypted store
     let codex_home = dirs::home_dir()
         .ok_or("Could not find home directory")?
         .join(".codex");
-    match load_auth(&codex_home, false).await {
-        Ok(Some(auth)) => match auth.mode {
-            AuthMode::ApiKey => Ok(auth.get_api_key()),
-            AuthMode::ChatGPT => Ok(None),
-        },
+    let backend = state.credential_backend.lock().await.clone();
+    let auth = tokio::task::spawn_blocking(move || load_live_auth(&codex_home, backend))
+        .await
+        .map_err(|e| format!("Auth task failed: {}", e))?;
+    match auth {
+        Ok(Some(auth)) => {
+            let plan_uses_api_key = auth.tokens.as_ref().is_none_or(|t| t.is_plan_that_should_use_api_key());
+            Ok(auth.openai_api_key.filter(|key| !key.is_empty() && plan_uses_api_key))
+        }
         _ => Ok(None),
     }
 }
+
+// Account vault commands
+
+#[tauri::command]
+pub async fn list_accounts(state: State<'_, CodexState>) -> Result<Vec<AccountSummary>, String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+    let backend = state.credential_backend.lock().await.clone();
+
+    tokio::task::spawn_blocking(move || accounts::list_accounts(&codex_home, backend))
+        .await
+        .map_err(|e| format!("Account task failed: {}", e))?
+        .map_err(|e| format!("Failed to list accounts: {}", e))
+}
+
+/// Saves the credentials currently logged in under `name`.
+#[tauri::command]
+pub async fn save_current_account(state: State<'_, CodexState>, name: String) -> Result<(), String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+    let backend = state.credential_backend.lock().await.clone();
+
+    tokio::task::spawn_blocking(move || accounts::save_current_account(&codex_home, backend, &name))
+        .await
+        .map_err(|e| format!("Account task failed: {}", e))?
+        .map_err(|e| format!("Failed to save account: {}", e))
+}
+
+#[tauri::command]
+pub async fn switch_account(app: AppHandle, state: State<'_, CodexState>, name: String) -> Result<(), String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+    let backend = state.credential_backend.lock().await.clone();
+
+    tokio::task::spawn_blocking(move || accounts::switch_account(&codex_home, backend, &name))
+        .await
+        .map_err(|e| format!("Account task failed: {}", e))?
+        .map_err(|e| format!("Failed to switch account: {}", e))?;
+    if let Err(e) = app.emit("auth-changed", "account_switched") {
+        log::error!("Failed to emit auth-changed event: {}", e);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_account(state: State<'_, CodexState>, name: String) -> Result<(), String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+    let backend = state.credential_backend.lock().await.clone();
+
+    tokio::task::spawn_blocking(move || accounts::remove_account(&codex_home, backend, &name))
+        .await
+        .map_err(|e| format!("Account task failed: {}", e))?
+        .map_err(|e| format!("Failed to remove account: {}", e))
+}
+
+// Encrypted credential store commands
+
+/// Moves auth.json and the account vault into the passphrase-encrypted store
+/// and keeps it unlocked for this app session. Returns false when there were
+/// no credentials to migrate.
+#[tauri::command]
+pub async fn encrypt_credentials(state: State<'_, CodexState>, passphrase: String) -> Result<bool, String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+
+    let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new(passphrase));
+    let store_backend = backend.clone();
+    let migrated = tokio::task::spawn_blocking(move || {
+        let migrated = migrate_auth_json(&codex_home, &EncryptedStore::new(&codex_home, store_backend.clone()))?;
+        accounts::seal_vault(&codex_home, store_backend).map_err(|e| CredentialStoreError::Storage(e.to_string()))?;
+        Ok::<_, CredentialStoreError>(migrated)
+    })
+    .await
+    .map_err(|e| format!("Credential migration task failed: {}", e))?
+    .map_err(|e| format!("Failed to encrypt credentials: {}", e))?;
+
+    *state.credential_backend.lock().await = Some(backend);
+    Ok(migrated)
+}
+
+#[tauri::command]
+pub async fn unlock_credentials(state: State<'_, CodexState>, passphrase: String) -> Result<(), String> {
+    let codex_home = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".codex");
+    if !encrypted_store_file(&codex_home).exists() {
+        return Err("No encrypted credential store found".to_string());
+    }
+
+    let backend: Arc<dyn SecretBackend> = Arc::new(PassphraseBackend::new(passphrase));
+    let store_backend = backend.clone();
+    tokio::task::spawn_blocking(move || EncryptedStore::new(&codex_home, store_backend).load())
+        .await
+        .map_err(|e| format!("Credential unlock task failed: {}", e))?
+        .map_err(|e| format!("Failed to unlock credentials: {}", e))?;
+
+    *state.credential_backend.lock().await = Some(backend);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn lock_credentials(state: State<'_, CodexState>) -> Result<(), String> {
+    *state.credential_backend.lock().await = None;
+    Ok(())
+}
@@ -1,26 +1,288 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::command;
 
+use crate::mcp::interop::{parse_mcp_json, to_mcp_json, ImportedMcpServer, SkippedMcpServer};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectConfig {
     pub trust_level: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type")]
 pub enum McpServerConfig {
     #[serde(rename = "stdio")]
     Stdio {
         command: String,
+        #[serde(default)]
         args: Vec<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         env: Option<HashMap<String, String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cwd: Option<String>,
+        #[serde(flatten)]
+        options: McpServerOptions,
     },
     #[serde(rename = "http")]
-    Http { url: String },
+    Http {
+        url: String,
+        /// Name of the environment variable holding the bearer token. The
+        /// token itself is never written to `config.toml`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bearer_token_env_var: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        http_headers: Option<HashMap<String, String>>,
+        /// Header name -> environment variable supplying its value.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        env_http_headers: Option<HashMap<String, String>>,
+        #[serde(flatten)]
+        options: McpServerOptions,
+    },
+}
+
+/// Settings shared by every MCP transport.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpServerOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_sec: Option<f64>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for McpServerOptions {
+    fn default() -> Self {
+        Self {
+            startup_timeout_ms: None,
+            tool_timeout_sec: None,
+            enabled: true,
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl McpServerConfig {
+    pub fn options(&self) -> &McpServerOptions {
+        match self {
+            McpServerConfig::Stdio { options, .. } | McpServerConfig::Http { options, .. } => options,
+        }
+    }
+
+    /// Checks the entry against what the Codex CLI accepts in `config.toml`.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            McpServerConfig::Stdio { command, cwd, .. } => {
+                if command.trim().is_empty() {
+                    return Err("Command must not be empty".to_string());
+                }
+                if let Some(cwd) = cwd {
+                    if !Path::new(cwd).is_dir() {
+                        return Err(format!("Working directory '{}' does not exist", cwd));
+                    }
+                }
+            }
+            McpServerConfig::Http {
+                url,
+                bearer_token_env_var,
+                http_headers,
+                env_http_headers,
+                ..
+            } => {
+                let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+                if parsed.scheme() != "http" && parsed.scheme() != "https" {
+                    return Err(format!("Unsupported URL scheme '{}'", parsed.scheme()));
+                }
+                if let Some(var) = bearer_token_env_var {
+                    if !is_env_var_name(var) {
+                        return Err(format!(
+                            "'{}' is not an environment variable name; store the token in an env var and reference it by name",
+                            var
+                        ));
+                    }
+                }
+                if let Some(headers) = http_headers {
+                    if headers.keys().any(|h| h.eq_ignore_ascii_case("authorization")) {
+                        return Err(
+                            "Authorization must not be stored in plain headers; use bearer_token_env_var or env_http_headers"
+                                .to_string(),
+                        );
+                    }
+                }
+                if let Some(headers) = env_http_headers {
+                    if let Some((header, var)) = headers.iter().find(|(_, var)| !is_env_var_name(var)) {
+                        return Err(format!(
+                            "Header '{}' must reference an environment variable name, got '{}'",
+                            header, var
+                        ));
+                    }
+                }
+            }
+        }
+
+        let options = self.options();
+        if options.startup_timeout_ms == Some(0) {
+            return Err("startup_timeout_ms must be greater than zero".to_string());
+        }
+        if let Some(secs) = options.tool_timeout_sec {
+            if !secs.is_finite() || secs <= 0.0 {
+                return Err("tool_timeout_sec must be a positive number".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `[mcp_servers.<name>]` table as the Codex CLI reads it: the transport is
+/// implied by `command` vs `url` rather than a `type` key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RawMcpServerConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bearer_token_env_var: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http_headers: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env_http_headers: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    startup_timeout_ms: Option<u64>,
+    /// Newer CLI spelling of the startup timeout; only read, never written.
+    #[serde(default, skip_serializing)]
+    startup_timeout_sec: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_timeout_sec: Option<f64>,
+    #[serde(default = "default_enabled", skip_serializing_if = "is_true")]
+    enabled: bool,
+}
+
+impl TryFrom<RawMcpServerConfig> for McpServerConfig {
+    type Error = String;
+
+    fn try_from(raw: RawMcpServerConfig) -> Result<Self, Self::Error> {
+        let options = McpServerOptions {
+            startup_timeout_ms: raw
+                .startup_timeout_ms
+                .or_else(|| raw.startup_timeout_sec.map(|s| (s * 1000.0) as u64)),
+            tool_timeout_sec: raw.tool_timeout_sec,
+            enabled: raw.enabled,
+        };
+        match (raw.command, raw.url) {
+            (Some(command), None) => Ok(McpServerConfig::Stdio {
+                command,
+                args: raw.args,
+                env: raw.env,
+                cwd: raw.cwd,
+                options,
+            }),
+            (None, Some(url)) => Ok(McpServerConfig::Http {
+                url,
+                bearer_token_env_var: raw.bearer_token_env_var,
+                http_headers: raw.http_headers,
+                env_http_headers: raw.env_http_headers,
+                options,
+            }),
+            (Some(_), Some(_)) => Err("only one of `command` or `url` may be set".to_string()),
+            (None, None) => Err("either `command` or `url` must be set".to_string()),
+        }
+    }
+}
+
+impl From<&McpServerConfig> for RawMcpServerConfig {
+    fn from(config: &McpServerConfig) -> Self {
+        let options = config.options();
+        let mut raw = RawMcpServerConfig {
+            startup_timeout_ms: options.startup_timeout_ms,
+            tool_timeout_sec: options.tool_timeout_sec,
+            enabled: options.enabled,
+            ..Default::default()
+        };
+        match config {
+            McpServerConfig::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+                ..
+            } => {
+                raw.command = Some(command.clone());
+                raw.args = args.clone();
+                raw.env = env.clone();
+                raw.cwd = cwd.clone();
+            }
+            McpServerConfig::Http {
+                url,
+                bearer_token_env_var,
+                http_headers,
+                env_http_headers,
+                ..
+            } => {
+                raw.url = Some(url.clone());
+                raw.bearer_token_env_var = bearer_token_env_var.clone();
+                raw.http_headers = http_headers.clone();
+                raw.env_http_headers = env_http_headers.clone();
+            }
+        }
+        raw
+    }
+}
+
+/// (De)serializes `mcp_servers` in the CLI's `config.toml` layout.
+mod mcp_servers_toml {
+    use super::{McpServerConfig, RawMcpServerConfig};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn serialize<S>(servers: &HashMap<String, McpServerConfig>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        servers
+            .iter()
+            .map(|(name, config)| (name, RawMcpServerConfig::from(config)))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashMap<String, McpServerConfig>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        HashMap::<String, RawMcpServerConfig>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, raw)| {
+                McpServerConfig::try_from(raw)
+                    .map(|config| (name.clone(), config))
+                    .map_err(|e| serde::de::Error::custom(format!("mcp_servers.{}: {}", name, e)))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,13 +296,17 @@ pub struct ModelProvider {
 pub struct Profile {
     pub model_provider: String,
     pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_mode: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodexConfig {
     #[serde(default)]
     pub projects: HashMap<String, ProjectConfig>,
-    #[serde(default)]
+    #[serde(default, with = "mcp_servers_toml")]
     pub mcp_servers: HashMap<String, McpServerConfig>,
     #[serde(default)]
     pub model_providers: HashMap<String, ModelProvider>,
@@ -116,6 +382,11 @@ pub async fn read_mcp_servers() -> Result<HashMap<String, McpServerConfig>, Stri
 
 #[command]
 pub async fn add_mcp_server(name: String, config: McpServerConfig) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("MCP server name must not be empty".to_string());
+    }
+    config.validate()?;
+
     let config_path = get_config_path()?;
 
     let mut codex_config: CodexConfig = if config_path.exists() {
@@ -147,6 +418,105 @@ pub async fn add_mcp_server(name: String, config: McpServerConfig) -> Result<(),
     Ok(())
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct McpImportReport {
+    /// Servers written to `config.toml` (or that would be, for a dry run).
+    pub imported: Vec<ImportedMcpServer>,
+    /// Names already configured with an identical definition.
+    pub unchanged: Vec<String>,
+    /// Names already configured differently; only replaced when `overwrite` is set.
+    pub conflicts: Vec<String>,
+    pub skipped: Vec<SkippedMcpServer>,
+}
+
+fn expand_home(path: &str) -> Result<PathBuf, String> {
+    if let Some(rest) = path.strip_prefix("~/") {
+        let home = dirs::home_dir().ok_or("Could not find home directory")?;
+        Ok(home.join(rest))
+    } else {
+        Ok(PathBuf::from(path))
+    }
+}
+
+#[command]
+pub async fn import_mcp_servers(
+    file_path: String,
+    overwrite: bool,
+    dry_run: bool,
+) -> Result<McpImportReport, String> {
+    let source = expand_home(&file_path)?;
+    let content = fs::read_to_string(&source)
+        .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    let (servers, skipped) = parse_mcp_json(&content)?;
+
+    let config_path = get_config_path()?;
+    let mut codex_config: CodexConfig = if config_path.exists() {
+        let content = fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read config file: {}", e))?;
+        toml::from_str(&content).map_err(|e| format!("Failed to parse config file: {}", e))?
+    } else {
+        CodexConfig {
+            projects: HashMap::new(),
+            mcp_servers: HashMap::new(),
+            model_providers: HashMap::new(),
+            profiles: HashMap::new(),
+        }
+    };
+
+    let mut report = McpImportReport {
+        skipped,
+        ..Default::default()
+    };
+    for server in servers {
+        match codex_config.mcp_servers.get(&server.name) {
+            Some(existing) if *existing == server.config => {
+                report.unchanged.push(server.name);
+                continue;
+            }
+            Some(_) => {
+                report.conflicts.push(server.name.clone());
+                if !overwrite {
+                    continue;
+                }
+            }
+            None => {}
+        }
+        codex_config
+            .mcp_servers
+            .insert(server.name.clone(), server.config.clone());
+        report.imported.push(server);
+    }
+
+    if dry_run || report.imported.is_empty() {
+        return Ok(report);
+    }
+
+    let toml_content =
+        toml::to_string(&codex_config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    fs::write(&config_path, toml_content)
+        .map_err(|e| format!("Failed to write config file: {}", e))?;
+
+    Ok(report)
+}
+
+#[command]
+pub async fn export_mcp_servers(file_path: String) -> Result<usize, String> {
+    let servers = read_mcp_servers().await?;
+    let json = serde_json::to_string_pretty(&to_mcp_json(&servers))
+        .map_err(|e| format!("Failed to serialize MCP servers: {}", e))?;
+
+    let target = expand_home(&file_path)?;
+    fs::write(&target, json).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+
+    Ok(servers.len())
+}
+
 #[command]
 pub async fn read_model_providers() -> Result<HashMap<String, ModelProvider>, String> {
     let config_path = get_config_path()?;
@@ -351,3 +721,70 @@ pub async fn delete_mcp_server(name: String) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mcp_servers_round_trip_cli_layout() {
+        let toml_str = r#"
+[mcp_servers.docs]
+command = "npx"
+args = ["-y", "docs-server"]
+cwd = "/tmp"
+startup_timeout_sec = 2.5
+
+[mcp_servers.remote]
+url = "https://mcp.example.com/mcp"
+bearer_token_env_var = "EXAMPLE_TOKEN"
+env_http_headers = { "X-Org" = "EXAMPLE_ORG" }
+tool_timeout_sec = 30.0
+enabled = false
+"#;
+        let config: CodexConfig = toml::from_str(toml_str).unwrap();
+        match &config.mcp_servers["docs"] {
+            McpServerConfig::Stdio { args, cwd, options, .. } => {
+                assert_eq!(args, &vec!["-y".to_string(), "docs-server".to_string()]);
+                assert_eq!(cwd.as_deref(), Some("/tmp"));
+                assert_eq!(options.startup_timeout_ms, Some(2500));
+                assert!(options.enabled);
+            }
+            other => panic!("expected stdio server, got {:?}", other),
+        }
+        assert!(!config.mcp_servers["remote"].options().enabled);
+
+        let written = toml::to_string(&config).unwrap();
+        assert!(!written.contains("type ="));
+        assert!(written.contains("startup_timeout_ms = 2500"));
+        let reread: CodexConfig = toml::from_str(&written).unwrap();
+        assert_eq!(reread.mcp_servers, config.mcp_servers);
+    }
+
+    #[test]
+    fn test_mcp_server_validation() {
+        let http = |headers: Option<HashMap<String, String>>, bearer: Option<&str>| McpServerConfig::Http {
+            url: "https://mcp.example.com/mcp".to_string(),
+            bearer_token_env_var: bearer.map(|b| b.to_string()),
+            http_headers: headers,
+            env_http_headers: None,
+            options: McpServerOptions::default(),
+        };
+        assert!(http(None, Some("EXAMPLE_TOKEN")).validate().is_ok());
+        assert!(http(None, Some("sk-live-secret value")).validate().is_err());
+        let plaintext = HashMap::from([("Authorization".to_string(), "Bearer abc".to_string())]);
+        assert!(http(Some(plaintext), None).validate().is_err());
+
+        let stdio = McpServerConfig::Stdio {
+            command: "server".to_string(),
+            args: vec![],
+            env: None,
+            cwd: None,
+            options: McpServerOptions {
+                startup_timeout_ms: Some(0),
+                ..Default::default()
+            },
+        };
+        assert!(stdio.validate().is_err());
+    }
+}
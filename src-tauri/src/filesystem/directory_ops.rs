@@ -1,15 +1,39 @@
 use super::file_types::FileEntry;
+use super::git_status::{git_status_map, status_for};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
 
-#[tauri::command]
-pub async fn read_directory(path: String) -> Result<Vec<FileEntry>, String> {
-    let expanded_path = if path.starts_with("~/") {
+/// Trees with more entries than this are streamed as `directory-tree-chunk`
+/// events instead of returned at once.
+const DEFAULT_TREE_CHUNK_SIZE: usize = 2_000;
+const DEFAULT_TREE_DEPTH: usize = 3;
+
+pub(crate) fn expand_path(path: &str) -> Result<PathBuf, String> {
+    if let Some(rest) = path.strip_prefix("~/") {
         let home = dirs::home_dir().ok_or_else(|| "Cannot find home directory".to_string())?;
-        home.join(&path[2..])
+        Ok(home.join(rest))
     } else {
-        Path::new(&path).to_path_buf()
-    };
+        Ok(Path::new(path).to_path_buf())
+    }
+}
+
+fn sort_entries(entries: &mut [FileEntry]) {
+    // Directories first, then files
+    entries.sort_by(|a, b| match (a.is_directory, b.is_directory) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+}
+
+#[tauri::command]
+pub async fn read_directory(path: String) -> Result<Vec<FileEntry>, String> {
+    let expanded_path = expand_path(&path)?;
 
     if !expanded_path.exists() || !expanded_path.is_dir() {
         return Err("Directory does not exist".to_string());
@@ -50,6 +74,8 @@ pub async fn read_directory(path: String) -> Result<Vec<FileEntry>, String> {
                             is_directory,
                             size,
                             extension,
+                            git_status: None,
+                            children: None,
                         });
                     }
                     Err(_) => continue,
@@ -59,16 +85,228 @@ pub async fn read_directory(path: String) -> Result<Vec<FileEntry>, String> {
         Err(e) => return Err(format!("Failed to read directory: {}", e)),
     }
 
-    // Sort directories first, then files
-    entries.sort_by(|a, b| match (a.is_directory, b.is_directory) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
+    sort_entries(&mut entries);
 
     Ok(entries)
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TreeOptions {
+    /// Hide names starting with `.` (default true). `.git` is always hidden.
+    #[serde(default)]
+    pub hide_dotfiles: Option<bool>,
+    /// Honor `.gitignore`, `.ignore` and git's global excludes (default true).
+    #[serde(default)]
+    pub respect_gitignore: Option<bool>,
+    /// Extra gitignore-style patterns, e.g. the excluded folders setting.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Annotate entries with `git status` (default true).
+    #[serde(default)]
+    pub git_status: Option<bool>,
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryTree {
+    pub root: String,
+    /// Nested entries, or empty when the tree is streamed.
+    pub entries: Vec<FileEntry>,
+    /// Set when entries arrive as `directory-tree-chunk` events instead.
+    pub stream_id: Option<String>,
+}
+
+/// Payload of `directory-tree-chunk`. Entries are flat, parents before their
+/// children, with `children` left unset; the last chunk has `done` set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryTreeChunk {
+    pub stream_id: String,
+    pub entries: Vec<FileEntry>,
+    pub done: bool,
+    pub error: Option<String>,
+}
+
+/// Walks `root` up to `depth` levels and returns the nested tree when it has
+/// at most `chunk_size` entries. Larger trees go to `emit_chunk` in
+/// `chunk_size` pieces, the last one flagged as final, and `None` is
+/// returned.
+pub fn walk_directory_tree(
+    root: &Path,
+    depth: usize,
+    options: &TreeOptions,
+    mut emit_chunk: impl FnMut(Vec<FileEntry>, bool),
+) -> Result<Option<Vec<FileEntry>>, String> {
+    if !root.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+    let respect_gitignore = options.respect_gitignore.unwrap_or(true);
+    let chunk_size = options.chunk_size.unwrap_or(DEFAULT_TREE_CHUNK_SIZE).max(1);
+    let statuses = if options.git_status.unwrap_or(true) {
+        git_status_map(root).unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in &options.exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|e| format!("Invalid exclude pattern '{}': {}", pattern, e))?;
+    }
+    let overrides = overrides.build().map_err(|e| format!("Invalid exclude patterns: {}", e))?;
+
+    let walker = WalkBuilder::new(root)
+        .max_depth(Some(depth.max(1)))
+        .hidden(options.hide_dotfiles.unwrap_or(true))
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        .parents(respect_gitignore)
+        .require_git(false)
+        .overrides(overrides)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let mut pending: Vec<(usize, FileEntry)> = Vec::new();
+    let mut streaming = false;
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::debug!("Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+        let is_directory = entry.file_type().is_some_and(|t| t.is_dir());
+        let path = entry.path();
+        pending.push((
+            entry.depth(),
+            FileEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                is_directory,
+                size: if is_directory { None } else { entry.metadata().ok().map(|m| m.len()) },
+                extension: if is_directory {
+                    None
+                } else {
+                    path.extension().and_then(|ext| ext.to_str()).map(|s| s.to_string())
+                },
+                git_status: status_for(&statuses, path),
+                children: None,
+            },
+        ));
+
+        if pending.len() > chunk_size {
+            streaming = true;
+            let rest = pending.split_off(chunk_size);
+            emit_chunk(pending.into_iter().map(|(_, e)| e).collect(), false);
+            pending = rest;
+        }
+    }
+
+    if streaming {
+        emit_chunk(pending.into_iter().map(|(_, e)| e).collect(), true);
+        return Ok(None);
+    }
+    Ok(Some(nest_entries(root, pending, depth.max(1))))
+}
+
+/// Builds the nested tree from walk output. Directories above `max_depth`
+/// get a (possibly empty) children list; those at it are left unloaded.
+fn nest_entries(root: &Path, flat: Vec<(usize, FileEntry)>, max_depth: usize) -> Vec<FileEntry> {
+    let mut by_parent: HashMap<PathBuf, Vec<FileEntry>> = HashMap::new();
+    for (depth, mut entry) in flat {
+        if entry.is_directory && depth < max_depth {
+            entry.children = Some(Vec::new());
+        }
+        let parent = Path::new(&entry.path).parent().map(Path::to_path_buf).unwrap_or_default();
+        by_parent.entry(parent).or_default().push(entry);
+    }
+
+    fn attach(dir: &Path, by_parent: &mut HashMap<PathBuf, Vec<FileEntry>>) -> Vec<FileEntry> {
+        let mut entries = by_parent.remove(dir).unwrap_or_default();
+        for entry in entries.iter_mut() {
+            if entry.children.is_some() {
+                entry.children = Some(attach(Path::new(&entry.path), by_parent));
+            }
+        }
+        sort_entries(&mut entries);
+        entries
+    }
+    attach(root, &mut by_parent)
+}
+
+/// Lists `root` recursively up to `depth` levels (default 3), skipping
+/// ignored files. Large trees are streamed as `directory-tree-chunk`
+/// events tagged with the caller's `stream_id`, which may arrive before
+/// the command returns; listen for them before invoking.
+#[tauri::command]
+pub async fn read_directory_tree(
+    app: AppHandle,
+    root: String,
+    stream_id: String,
+    depth: Option<usize>,
+    options: Option<TreeOptions>,
+) -> Result<DirectoryTree, String> {
+    let root_path = expand_path(&root)?;
+    let depth = depth.unwrap_or(DEFAULT_TREE_DEPTH);
+    let options = options.unwrap_or_default();
+
+    let (first_tx, first_rx) = tokio::sync::oneshot::channel();
+    let task_stream_id = stream_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut first_tx = Some(first_tx);
+        let emit_chunk = |entries: Vec<FileEntry>, done: bool| {
+            // The command returns as soon as streaming starts
+            if let Some(tx) = first_tx.take() {
+                let _ = tx.send(Ok(None));
+            }
+            let chunk = DirectoryTreeChunk {
+                stream_id: task_stream_id.clone(),
+                entries,
+                done,
+                error: None,
+            };
+            if let Err(e) = app.emit("directory-tree-chunk", &chunk) {
+                log::error!("Failed to emit directory tree chunk: {}", e);
+            }
+        };
+        let result = walk_directory_tree(&root_path, depth, &options, emit_chunk);
+        if let Some(tx) = first_tx.take() {
+            let _ = tx.send(result);
+        } else if let Err(e) = result {
+            let chunk = DirectoryTreeChunk {
+                stream_id: task_stream_id,
+                entries: Vec::new(),
+                done: true,
+                error: Some(e),
+            };
+            let _ = app.emit("directory-tree-chunk", &chunk);
+        }
+    });
+
+    let entries = first_rx
+        .await
+        .map_err(|_| "Directory walk ended unexpectedly".to_string())??;
+    Ok(match entries {
+        Some(entries) => DirectoryTree {
+            root,
+            entries,
+            stream_id: None,
+        },
+        None => DirectoryTree {
+            root,
+            entries: Vec::new(),
+            stream_id: Some(stream_id),
+        },
+    })
+}
+
 #[tauri::command]
 pub async fn get_default_directories() -> Result<Vec<String>, String> {
     let home = dirs::home_dir().ok_or_else(|| "Cannot find home directory".to_string())?;
@@ -84,3 +322,108 @@ pub async fn get_default_directories() -> Result<Vec<String>, String> {
 
     Ok(default_dirs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::git_status::GitFileStatus;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn names(entries: &[FileEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    fn sample_tree(root: &Path) {
+        for dir in ["src/nested/deep", "target", "node_modules", ".github"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("README.md"), "readme").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/nested/deep/file.rs"), "").unwrap();
+        fs::write(root.join("target/out"), "").unwrap();
+        fs::write(root.join("node_modules/dep.js"), "").unwrap();
+        fs::write(root.join(".github/ci.yml"), "").unwrap();
+    }
+
+    #[test]
+    fn test_tree_honors_ignore_rules_and_depth() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        sample_tree(root);
+
+        let options = TreeOptions {
+            exclude: vec!["node_modules".to_string()],
+            ..Default::default()
+        };
+        let tree = walk_directory_tree(root, 2, &options, |_, _| panic!("small trees aren't streamed"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(names(&tree), vec!["src", "README.md"]);
+        let src = tree[0].children.as_ref().unwrap();
+        assert_eq!(names(src), vec!["nested", "main.rs"]);
+        // Below the requested depth: not loaded yet
+        assert!(src[0].children.is_none());
+
+        let options = TreeOptions {
+            hide_dotfiles: Some(false),
+            respect_gitignore: Some(false),
+            ..Default::default()
+        };
+        let tree = walk_directory_tree(root, 1, &options, |_, _| {}).unwrap().unwrap();
+        assert_eq!(
+            names(&tree),
+            vec![".github", "node_modules", "src", "target", ".gitignore", "README.md"]
+        );
+    }
+
+    #[test]
+    fn test_large_trees_are_streamed_in_chunks() {
+        let dir = tempdir().unwrap();
+        sample_tree(dir.path());
+
+        let options = TreeOptions {
+            chunk_size: Some(2),
+            respect_gitignore: Some(false),
+            ..Default::default()
+        };
+        let mut chunks = Vec::new();
+        let result = walk_directory_tree(dir.path(), 10, &options, |entries, done| chunks.push((entries, done))).unwrap();
+        assert!(result.is_none());
+
+        let total: usize = chunks.iter().map(|(entries, _)| entries.len()).sum();
+        assert_eq!(total, 10);
+        assert!(chunks.iter().all(|(entries, _)| entries.len() <= 2));
+        assert_eq!(chunks.iter().filter(|(_, done)| *done).count(), 1);
+        assert!(chunks.last().unwrap().1);
+    }
+
+    #[test]
+    fn test_tree_git_annotations() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        sample_tree(root);
+        let git = |args: &[&str]| {
+            let status = Command::new("git").args(args).current_dir(root).output().unwrap().status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "src/main.rs"]);
+
+        let options = TreeOptions {
+            exclude: vec!["node_modules".to_string()],
+            ..Default::default()
+        };
+        let tree = walk_directory_tree(root, 3, &options, |_, _| {}).unwrap().unwrap();
+        assert_eq!(names(&tree), vec!["src", "README.md"]);
+        let src = &tree[0];
+        assert_eq!(src.git_status, Some(GitFileStatus::Modified));
+        let children = src.children.as_ref().unwrap();
+        assert_eq!(children[1].name, "main.rs");
+        assert_eq!(children[1].git_status, Some(GitFileStatus::Added));
+        // Untracked directories are reported as a whole
+        assert_eq!(children[0].git_status, Some(GitFileStatus::Untracked));
+        assert_eq!(tree[1].git_status, Some(GitFileStatus::Untracked));
+    }
+}
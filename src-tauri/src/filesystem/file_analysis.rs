@@ -1,68 +1,456 @@
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::State;
 
-#[tauri::command]
-pub async fn calculate_file_tokens(file_path: String) -> Result<Option<u32>, String> {
-    let path = Path::new(&file_path);
+use super::file_parsers::{csv::extract_csv_text, pdf::extract_pdf_text, xlsx::extract_xlsx_text};
+use super::tokenizer::{count_text_tokens, image_tokens};
+use crate::state::CodexState;
 
-    if !path.exists() || path.is_dir() {
-        return Ok(None);
-    }
+/// Directory scans stop after this many countable files unless told otherwise.
+const DEFAULT_MAX_FILES: usize = 10_000;
+/// Past this many entries the least recently used tenth is evicted.
+const MAX_CACHE_ENTRIES: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Text,
+    Pdf,
+    Csv,
+    Xlsx,
+    Image,
+    Audio,
+}
 
+fn token_kind(path: &Path) -> Option<TokenKind> {
     let extension = path
         .extension()
         .and_then(|ext| ext.to_str())
         .map(|s| s.to_lowercase());
 
-    // Only calculate tokens for text, image, audio, and document files
-    let is_supported = match extension.as_deref() {
+    match extension.as_deref() {
         Some("txt") | Some("md") | Some("rs") | Some("js") | Some("ts") | Some("tsx")
         | Some("jsx") | Some("py") | Some("java") | Some("cpp") | Some("c") | Some("h")
         | Some("css") | Some("html") | Some("json") | Some("xml") | Some("yaml") | Some("yml")
-        | Some("toml") | Some("cfg") | Some("ini") | Some("sh") | Some("png") | Some("jpg")
-        | Some("jpeg") | Some("gif") | Some("webp") | Some("mp3") | Some("wav") | Some("flac")
-        | Some("ogg") | Some("pdf") | Some("csv") | Some("xlsx") => true,
-        _ => false,
-    };
+        | Some("toml") | Some("cfg") | Some("ini") | Some("sh") => Some(TokenKind::Text),
+        Some("pdf") => Some(TokenKind::Pdf),
+        Some("csv") => Some(TokenKind::Csv),
+        Some("xlsx") => Some(TokenKind::Xlsx),
+        Some("png") | Some("jpg") | Some("jpeg") | Some("gif") | Some("webp") => Some(TokenKind::Image),
+        Some("mp3") | Some("wav") | Some("flac") | Some("ogg") => Some(TokenKind::Audio),
+        _ => None,
+    }
+}
 
-    if !is_supported {
+/// Tokens `path` takes up in the context of `model`'s family (o200k when
+/// unset), or `None` for unsupported files. Documents are counted on the
+/// text their parsers extract, which is what gets attached to a message;
+/// images by 512px tiles.
+pub fn count_file_tokens(path: &Path, model: Option<&str>) -> Result<Option<u64>, String> {
+    let Some(kind) = token_kind(path) else {
         return Ok(None);
+    };
+
+    let tokens = match kind {
+        TokenKind::Text => {
+            let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            count_text_tokens(&String::from_utf8_lossy(&bytes), model) as u64
+        }
+        TokenKind::Pdf => count_text_tokens(&extract_pdf_text(path)?, model) as u64,
+        TokenKind::Csv => count_text_tokens(&extract_csv_text(path)?, model) as u64,
+        TokenKind::Xlsx => count_text_tokens(&extract_xlsx_text(path)?, model) as u64,
+        TokenKind::Image => {
+            let size = imagesize::size(path).map_err(|e| format!("Failed to read image size: {}", e))?;
+            image_tokens(size.width as u64, size.height as u64, model)
+        }
+        TokenKind::Audio => {
+            let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+            // Audio tokens estimation (very rough)
+            metadata.len() / 10000 + 50
+        }
+    };
+    Ok(Some(tokens))
+}
+
+struct CachedCount {
+    modified: Option<SystemTime>,
+    size: u64,
+    tokens: Option<u64>,
+    /// `TokenCountCache::clock` at the last lookup or insert.
+    last_used: u64,
+}
+
+/// Token counts per file and model, reused while the file's mtime and size
+/// are unchanged.
+pub struct TokenCountCache {
+    entries: Mutex<HashMap<(PathBuf, String), CachedCount>>,
+    clock: AtomicU64,
+    capacity: usize,
+}
+
+impl Default for TokenCountCache {
+    fn default() -> Self {
+        Self::with_capacity(MAX_CACHE_ENTRIES)
     }
+}
 
-    // For now, return a simple estimation based on file size
-    // In a real implementation, you would use tiktoken-rs here
-    match fs::metadata(path) {
-        Ok(metadata) => {
-            let size = metadata.len();
-            // Rough estimation: ~4 characters per token for text files
-            let estimated_tokens = match extension.as_deref() {
-                Some("png") | Some("jpg") | Some("jpeg") | Some("gif") | Some("webp") => {
-                    // Image tokens estimation (very rough)
-                    (size / 1000) as u32 + 100
-                }
-                Some("mp3") | Some("wav") | Some("flac") | Some("ogg") => {
-                    // Audio tokens estimation (very rough)
-                    (size / 10000) as u32 + 50
-                }
-                Some("pdf") => {
-                    // PDF tokens estimation (rough, based on compressed text)
-                    (size / 8) as u32 + 100
-                }
-                Some("csv") => {
-                    // CSV tokens estimation (similar to text but more structured)
-                    (size / 5) as u32
-                }
-                Some("xlsx") => {
-                    // XLSX tokens estimation (compressed format)
-                    (size / 15) as u32 + 200
-                }
-                _ => {
-                    // Text files
-                    (size / 4) as u32
+impl TokenCountCache {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            capacity,
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Drops the least recently used tenth of the entries.
+    fn evict(&self, entries: &mut HashMap<(PathBuf, String), CachedCount>) {
+        let mut stamps: Vec<u64> = entries.values().map(|e| e.last_used).collect();
+        let evicted = (self.capacity / 10).clamp(1, stamps.len());
+        let (_, cutoff, _) = stamps.select_nth_unstable(evicted - 1);
+        let cutoff = *cutoff;
+        entries.retain(|_, e| e.last_used > cutoff);
+    }
+
+    /// Returns the count and whether it came from the cache.
+    pub fn count(&self, path: &Path, model: Option<&str>) -> Result<(Option<u64>, bool), String> {
+        let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        if metadata.is_dir() {
+            return Ok((None, false));
+        }
+        let modified = metadata.modified().ok();
+        let key = (path.to_path_buf(), model.unwrap_or_default().to_string());
+
+        {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(entry) = entries.get_mut(&key) {
+                if entry.modified == modified && entry.size == metadata.len() {
+                    entry.last_used = self.tick();
+                    return Ok((entry.tokens, true));
                 }
+            }
+        }
+
+        let tokens = count_file_tokens(path, model)?;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            self.evict(&mut entries);
+        }
+        entries.insert(
+            key,
+            CachedCount {
+                modified,
+                size: metadata.len(),
+                tokens,
+                last_used: self.tick(),
+            },
+        );
+        Ok((tokens, false))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileTokenCount {
+    pub path: String,
+    /// `None` for unsupported files and files that failed to read.
+    pub tokens: Option<u32>,
+    pub cached: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenCountSummary {
+    pub files: Vec<FileTokenCount>,
+    pub total: u64,
+    /// Directory scans only: more countable files existed than were counted.
+    pub truncated: bool,
+}
+
+/// Which files `calculate_directory_tokens` skips.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenIgnoreOptions {
+    /// Honor `.gitignore`, `.ignore` and git's global excludes (default true).
+    #[serde(default)]
+    pub respect_gitignore: Option<bool>,
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Extra gitignore-style patterns such as `node_modules` or `*.lock`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub max_files: Option<usize>,
+}
+
+/// Counts `paths` in parallel. Results keep the order of `paths`.
+pub fn count_tokens_batch(cache: &TokenCountCache, paths: &[PathBuf], model: Option<&str>) -> TokenCountSummary {
+    let files: Vec<FileTokenCount> = paths
+        .par_iter()
+        .map(|path| {
+            let (tokens, cached, error) = match cache.count(path, model) {
+                Ok((tokens, cached)) => (tokens, cached, None),
+                Err(e) => (None, false, Some(e)),
             };
-            Ok(Some(estimated_tokens))
+            FileTokenCount {
+                path: path.to_string_lossy().to_string(),
+                tokens: tokens.map(|t| t.min(u32::MAX as u64) as u32),
+                cached,
+                error,
+            }
+        })
+        .collect();
+    let total = files.iter().filter_map(|f| f.tokens).map(u64::from).sum();
+
+    TokenCountSummary {
+        files,
+        total,
+        truncated: false,
+    }
+}
+
+/// Countable files under `root`, in walk order, and whether the list was cut
+/// at `max_files`.
+fn collect_countable_files(root: &Path, options: &TokenIgnoreOptions) -> Result<(Vec<PathBuf>, bool), String> {
+    let respect_gitignore = options.respect_gitignore.unwrap_or(true);
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in &options.exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|e| format!("Invalid exclude pattern '{}': {}", pattern, e))?;
+    }
+    let overrides = overrides.build().map_err(|e| format!("Invalid exclude patterns: {}", e))?;
+
+    let walker = WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        .parents(respect_gitignore)
+        .require_git(false)
+        .overrides(overrides)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+
+    let max_files = options.max_files.unwrap_or(DEFAULT_MAX_FILES);
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::debug!("Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) || token_kind(entry.path()).is_none() {
+            continue;
+        }
+        if files.len() >= max_files {
+            return Ok((files, true));
+        }
+        files.push(entry.into_path());
+    }
+    Ok((files, false))
+}
+
+pub fn count_directory_tokens(
+    cache: &TokenCountCache,
+    root: &Path,
+    model: Option<&str>,
+    options: &TokenIgnoreOptions,
+) -> Result<TokenCountSummary, String> {
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+    let (files, truncated) = collect_countable_files(root, options)?;
+    let mut summary = count_tokens_batch(cache, &files, model);
+    summary.truncated = truncated;
+    Ok(summary)
+}
+
+fn file_tokens(cache: &TokenCountCache, path: &Path, model: Option<&str>) -> Result<Option<u32>, String> {
+    if !path.exists() || path.is_dir() {
+        return Ok(None);
+    }
+    let (tokens, _) = cache.count(path, model)?;
+    Ok(tokens.map(|t| t.min(u32::MAX as u64) as u32))
+}
+
+#[tauri::command]
+pub async fn calculate_file_tokens(
+    state: State<'_, CodexState>,
+    file_path: String,
+    model: Option<String>,
+) -> Result<Option<u32>, String> {
+    let cache = state.token_cache.clone();
+    let model = model.filter(|m| !m.is_empty());
+    tokio::task::spawn_blocking(move || file_tokens(&cache, Path::new(&file_path), model.as_deref()))
+        .await
+        .map_err(|e| format!("Token counting failed: {}", e))?
+}
+
+/// Counts several files at once, e.g. the current context selection.
+#[tauri::command]
+pub async fn calculate_tokens_batch(
+    state: State<'_, CodexState>,
+    paths: Vec<String>,
+    model: Option<String>,
+) -> Result<TokenCountSummary, String> {
+    let cache = state.token_cache.clone();
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let model = model.filter(|m| !m.is_empty());
+    tokio::task::spawn_blocking(move || count_tokens_batch(&cache, &paths, model.as_deref()))
+        .await
+        .map_err(|e| format!("Token counting failed: {}", e))
+}
+
+/// What attaching the folder at `root` as context would cost.
+#[tauri::command]
+pub async fn calculate_directory_tokens(
+    state: State<'_, CodexState>,
+    root: String,
+    model: Option<String>,
+    options: Option<TokenIgnoreOptions>,
+) -> Result<TokenCountSummary, String> {
+    let cache = state.token_cache.clone();
+    let model = model.filter(|m| !m.is_empty());
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || count_directory_tokens(&cache, Path::new(&root), model.as_deref(), &options))
+        .await
+        .map_err(|e| format!("Token counting failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_text_and_csv_are_tokenized() {
+        let dir = tempdir().unwrap();
+        let cache = TokenCountCache::default();
+        let text = dir.path().join("notes.md");
+        std::fs::write(&text, "hello world").unwrap();
+        assert_eq!(file_tokens(&cache, &text, None).unwrap(), Some(2));
+
+        let csv = dir.path().join("data.csv");
+        std::fs::write(&csv, "name,score\nada,10\n").unwrap();
+        let expected = count_text_tokens("name,score\nada,10\n", Some("gpt-4")) as u32;
+        let counted = file_tokens(&cache, &csv, Some("gpt-4")).unwrap();
+        assert_eq!(counted, Some(expected));
+
+        let unsupported = dir.path().join("archive.zip");
+        std::fs::write(&unsupported, "PK").unwrap();
+        assert_eq!(file_tokens(&cache, &unsupported, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_image_tokens_from_dimensions() {
+        let dir = tempdir().unwrap();
+        let cache = TokenCountCache::default();
+        let image = dir.path().join("shot.png");
+        // PNG signature and IHDR header for a 1024x1024 image
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&1024u32.to_be_bytes());
+        png.extend_from_slice(&1024u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        std::fs::write(&image, png).unwrap();
+
+        assert_eq!(file_tokens(&cache, &image, None).unwrap(), Some(765));
+    }
+
+    #[test]
+    fn test_batch_uses_cache_until_file_changes() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let missing = dir.path().join("missing.txt");
+        std::fs::write(&a, "hello world").unwrap();
+        let cache = TokenCountCache::default();
+        let paths = vec![a.clone(), missing];
+
+        let first = count_tokens_batch(&cache, &paths, None);
+        assert_eq!(first.total, 2);
+        assert!(!first.files[0].cached);
+        assert!(first.files[1].error.is_some());
+
+        assert!(count_tokens_batch(&cache, &paths, None).files[0].cached);
+        // Another model is counted separately.
+        assert!(!count_tokens_batch(&cache, &paths, Some("gpt-4")).files[0].cached);
+
+        std::fs::write(&a, "hello world, again").unwrap();
+        let changed = count_tokens_batch(&cache, &paths, None);
+        assert!(!changed.files[0].cached);
+        assert!(changed.total > 2);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let dir = tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..4).map(|i| dir.path().join(format!("{}.txt", i))).collect();
+        for path in &paths {
+            std::fs::write(path, "hello world").unwrap();
         }
-        Err(_) => Ok(None),
+        let cache = TokenCountCache::with_capacity(3);
+        for path in &paths[..3] {
+            cache.count(path, None).unwrap();
+        }
+        // Touch the oldest so the second becomes least recently used
+        assert!(cache.count(&paths[0], None).unwrap().1);
+
+        assert!(!cache.count(&paths[3], None).unwrap().1);
+        assert!(!cache.count(&paths[1], None).unwrap().1);
+        assert!(cache.count(&paths[3], None).unwrap().1);
+        // Re-adding 1 evicted the next least recently used, 2
+        assert!(cache.count(&paths[0], None).unwrap().1);
+        assert!(!cache.count(&paths[2], None).unwrap().1);
+    }
+
+    #[test]
+    fn test_directory_respects_ignore_rules() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("target/out.txt"), "ignored").unwrap();
+        std::fs::write(root.join("node_modules/dep.js"), "excluded").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("src/logo.bin"), [0u8; 4]).unwrap();
+        std::fs::write(root.join("README.md"), "hello world").unwrap();
+
+        let cache = TokenCountCache::default();
+        let options = TokenIgnoreOptions {
+            exclude: vec!["node_modules".to_string()],
+            ..Default::default()
+        };
+        let summary = count_directory_tokens(&cache, root, None, &options).unwrap();
+        let names: Vec<&str> = summary
+            .files
+            .iter()
+            .map(|f| f.path.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(names, vec!["README.md", "main.rs"]);
+        assert!(!summary.truncated);
+
+        let everything = TokenIgnoreOptions {
+            respect_gitignore: Some(false),
+            max_files: Some(2),
+            ..Default::default()
+        };
+        let summary = count_directory_tokens(&cache, root, None, &everything).unwrap();
+        assert_eq!(summary.files.len(), 2);
+        assert!(summary.truncated);
     }
 }
@@ -1,62 +1,352 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-#[tauri::command]
-pub async fn read_file(file_path: String) -> Result<String, String> {
-    let expanded_path = if file_path.starts_with("~/") {
-        let home = dirs::home_dir().ok_or_else(|| "Cannot find home directory".to_string())?;
-        home.join(&file_path[2..])
+use super::directory_ops::expand_path;
+use super::file_range::{ChunkContent, ContentKind, OpenedFile, TextEncoding, MAX_RANGE_BYTES};
+use super::write_policy::{check_write_content, check_write_path, WritePolicy};
+
+/// Largest file `read_file` returns whole, as editable text.
+const MAX_EDITABLE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileContent {
+    pub content: String,
+    /// Pass back to `write_file` to detect changes made in the meantime.
+    /// None when the file can't be edited: it isn't UTF-8 text or only its
+    /// start was read.
+    pub version: Option<String>,
+    /// Modification time in milliseconds since the epoch.
+    pub modified: Option<u64>,
+    pub size: u64,
+    /// Bytes `content` covers; less than `size` when only the start was
+    /// read. Continue with `read_file_range` from here.
+    pub length: u64,
+    pub mime_type: String,
+    /// None for binary files, whose `content` is a hex preview.
+    pub encoding: Option<TextEncoding>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WrittenFile {
+    pub version: String,
+    pub modified: Option<u64>,
+}
+
+/// Errors of `write_file`, serialized with a `kind` tag so the UI can
+/// offer to reload on a conflict or ask before writing outside projects.
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileWriteError {
+    #[error("{path} changed on disk since it was read")]
+    Conflict {
+        path: String,
+        expected_version: String,
+        /// None when the file has since been deleted.
+        current_version: Option<String>,
+    },
+    #[error("{message}")]
+    NotAllowed { message: String },
+    #[error("{path} matches the write deny list ({pattern})")]
+    Denied { path: String, pattern: String },
+    /// Retry with confirmation to write anyway.
+    #[error("{path} is outside the trusted project directories")]
+    OutsideWorkspace { path: String, trusted_roots: Vec<String> },
+    #[error("Failed to write file: {message}")]
+    Io { message: String },
+}
+
+impl From<std::io::Error> for FileWriteError {
+    fn from(e: std::io::Error) -> Self {
+        FileWriteError::Io { message: e.to_string() }
+    }
+}
+
+/// Content hash identifying what a reader saw. Unlike mtime it survives
+/// `touch` and coarse timestamp resolution.
+pub fn content_version(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn modified_millis(metadata: &fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// True when most line breaks in `bytes` are CRLF.
+fn uses_crlf(bytes: &[u8]) -> bool {
+    let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+    let crlf = bytes.windows(2).filter(|w| w == b"\r\n").count();
+    newlines > 0 && crlf * 2 > newlines
+}
+
+/// Converts `content` to the existing file's line endings.
+fn match_line_endings(content: &str, crlf: bool) -> String {
+    let normalized = content.replace("\r\n", "\n");
+    if crlf {
+        normalized.replace('\n', "\r\n")
     } else {
-        Path::new(&file_path).to_path_buf()
+        normalized
+    }
+}
+
+/// Replaces `path` with `content` via a temp file and rename, keeping the
+/// old file's permissions and line endings. With `expected_version`, fails
+/// with a conflict if the file no longer matches it.
+pub fn write_file_atomic(
+    path: &Path,
+    content: &str,
+    expected_version: Option<&str>,
+) -> Result<WrittenFile, FileWriteError> {
+    // Write through symlinks rather than replacing them
+    let path: PathBuf = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let existing = match fs::read(&path) {
+        Ok(bytes) => Some(bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let check_version = |current: Option<&[u8]>| match expected_version {
+        Some(expected) => {
+            let current_version = current.map(content_version);
+            if current_version.as_deref() == Some(expected) {
+                Ok(())
+            } else {
+                Err(FileWriteError::Conflict {
+                    path: path.to_string_lossy().to_string(),
+                    expected_version: expected.to_string(),
+                    current_version,
+                })
+            }
+        }
+        None => Ok(()),
     };
+    check_version(existing.as_deref())?;
 
-    if !expanded_path.exists() || expanded_path.is_dir() {
-        return Err("File does not exist or is a directory".to_string());
+    let content = match &existing {
+        Some(bytes) => match_line_endings(content, uses_crlf(bytes)),
+        None => content.to_string(),
+    };
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let mut temp = tempfile::NamedTempFile::new_in(parent)?;
+    temp.write_all(content.as_bytes())?;
+    temp.flush()?;
+    if existing.is_some() {
+        let permissions = fs::metadata(&path)?.permissions();
+        fs::set_permissions(temp.path(), permissions)?;
+    } else {
+        #[cfg(unix)]
+        {
+            // NamedTempFile is 0600; new files get the usual 0644
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(temp.path(), fs::Permissions::from_mode(0o644))?;
+        }
     }
+    temp.as_file().sync_all()?;
 
-    // Check file size to prevent reading very large files
-    if let Ok(metadata) = fs::metadata(&expanded_path) {
-        if metadata.len() > 1024 * 1024 {
-            // 1MB limit
-            return Err("File is too large to display".to_string());
+    // Re-check right before the rename to keep the race window small
+    if expected_version.is_some() {
+        let current = match fs::read(&path) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        check_version(current.as_deref())?;
+    }
+    temp.persist(&path).map_err(|e| FileWriteError::from(e.error))?;
+
+    Ok(WrittenFile {
+        version: content_version(content.as_bytes()),
+        modified: fs::metadata(&path).ok().as_ref().and_then(modified_millis),
+    })
+}
+
+/// Reads UTF-8 text up to 1 MB whole. Anything else comes back as a
+/// preview: the first chunk of large or non-UTF-8 text, decoded, or a hex
+/// dump of binary files.
+fn load_file(path: &Path) -> Result<FileContent, String> {
+    let mut file = OpenedFile::open(path)?;
+    let modified = fs::metadata(path).ok().as_ref().and_then(modified_millis);
+
+    let utf8 = matches!(file.kind, ContentKind::Text { encoding: TextEncoding::Utf8, .. });
+    if utf8 && file.size <= MAX_EDITABLE_BYTES {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let version = content_version(&bytes);
+        let length = bytes.len() as u64;
+        // Invalid UTF-8 past the sniffed sample falls through to a preview
+        if let Ok(content) = String::from_utf8(bytes) {
+            return Ok(FileContent {
+                content,
+                version: Some(version),
+                modified,
+                size: length,
+                length,
+                mime_type: file.mime_type.clone(),
+                encoding: Some(TextEncoding::Utf8),
+            });
         }
     }
 
-    match fs::read_to_string(&expanded_path) {
-        Ok(content) => Ok(content),
-        Err(e) => Err(format!("Failed to read file: {}", e)),
+    let chunk = file.chunk(0, MAX_RANGE_BYTES, None)?;
+    let (content, encoding) = match chunk.content {
+        ChunkContent::Text { encoding, text } => (text, Some(encoding)),
+        ChunkContent::Binary { hex_preview } => (hex_preview, None),
+    };
+    Ok(FileContent {
+        content,
+        version: None,
+        modified,
+        size: chunk.size,
+        length: chunk.offset + chunk.length,
+        mime_type: chunk.mime_type,
+        encoding,
+    })
+}
+
+#[tauri::command]
+pub async fn read_file(file_path: String) -> Result<FileContent, String> {
+    let expanded_path = expand_path(&file_path)?;
+
+    if !expanded_path.exists() || expanded_path.is_dir() {
+        return Err("File does not exist or is a directory".to_string());
     }
+
+    tokio::task::spawn_blocking(move || load_file(&expanded_path))
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?
+}
+
+/// Project directories marked trusted in `~/.codex/config.toml`.
+async fn trusted_project_roots() -> Result<Vec<PathBuf>, String> {
+    let projects = crate::config::read_codex_config().await?;
+    Ok(projects
+        .into_iter()
+        .filter(|project| project.trust_level == "trusted")
+        .map(|project| PathBuf::from(project.path))
+        .collect())
 }
 
+/// Writes UTF-8 text to `file_path`. Paths on the deny list are refused;
+/// paths outside trusted projects need `confirm_outside_workspace`.
 #[tauri::command]
-pub async fn write_file(file_path: String, content: String) -> Result<(), String> {
-    let expanded_path = if file_path.starts_with("~/") {
-        let home = dirs::home_dir().ok_or_else(|| "Cannot find home directory".to_string())?;
-        home.join(&file_path[2..])
-    } else {
-        Path::new(&file_path).to_path_buf()
-    };
+pub async fn write_file(
+    file_path: String,
+    content: String,
+    expected_version: Option<String>,
+    confirm_outside_workspace: Option<bool>,
+) -> Result<WrittenFile, FileWriteError> {
+    let not_allowed = |message| FileWriteError::NotAllowed { message };
+    let expanded_path = expand_path(&file_path).map_err(not_allowed)?;
+    let policy = WritePolicy::load(&WritePolicy::default_dir()).map_err(not_allowed)?;
+    let trusted_roots = trusted_project_roots().await.map_err(not_allowed)?;
+    check_write_path(
+        &policy,
+        &expanded_path,
+        &trusted_roots,
+        confirm_outside_workspace.unwrap_or(false),
+    )?;
 
-    // Basic safety check: only allow writing to text files
-    let extension = expanded_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|s| s.to_lowercase());
-
-    let is_text_file = match extension.as_deref() {
-        Some("txt") | Some("md") | Some("json") | Some("xml") | Some("yaml") | Some("yml")
-        | Some("js") | Some("jsx") | Some("ts") | Some("tsx") | Some("rs") | Some("py")
-        | Some("java") | Some("cpp") | Some("c") | Some("h") | Some("css") | Some("html")
-        | Some("toml") | Some("cfg") | Some("ini") | Some("sh") | Some("log") => true,
-        _ => false,
-    };
+    tokio::task::spawn_blocking(move || {
+        check_write_content(&expanded_path, &content)?;
+        write_file_atomic(&expanded_path, &content, expected_version.as_deref())
+    })
+    .await
+    .map_err(|e| FileWriteError::Io { message: e.to_string() })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_versioned_write_detects_conflicts() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        fs::write(&path, "one\n").unwrap();
+        let version = content_version(b"one\n");
+
+        let written = write_file_atomic(&path, "two\n", Some(&version)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+        assert_eq!(written.version, content_version(b"two\n"));
 
-    if !is_text_file {
-        return Err("Only text files can be edited".to_string());
+        // The first version is stale now
+        match write_file_atomic(&path, "three\n", Some(&version)) {
+            Err(FileWriteError::Conflict { current_version, .. }) => {
+                assert_eq!(current_version, Some(written.version.clone()));
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            write_file_atomic(&path, "four\n", Some(&written.version)),
+            Err(FileWriteError::Conflict { current_version: None, .. })
+        ));
+        // Without a version the write always goes through
+        write_file_atomic(&path, "four\n", None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "four\n");
+        assert!(fs::read_dir(dir.path()).unwrap().count() == 1, "temp file left behind");
     }
 
-    match fs::write(&expanded_path, content) {
-        Ok(()) => Ok(()),
-        Err(e) => Err(format!("Failed to write file: {}", e)),
+    #[test]
+    fn test_read_falls_back_to_previews() {
+        let dir = tempdir().unwrap();
+        let text = dir.path().join("notes.md");
+        fs::write(&text, "hello\n").unwrap();
+        let file = load_file(&text).unwrap();
+        assert_eq!(file.content, "hello\n");
+        assert_eq!(file.version.as_deref(), Some(content_version(b"hello\n").as_str()));
+
+        let latin1 = dir.path().join("old.txt");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+        let file = load_file(&latin1).unwrap();
+        assert_eq!((file.content.as_str(), file.encoding), ("café\n", Some(TextEncoding::Latin1)));
+        assert_eq!(file.version, None);
+
+        let large = dir.path().join("large.log");
+        fs::write(&large, "line\n".repeat(300_000)).unwrap();
+        let file = load_file(&large).unwrap();
+        assert_eq!((file.size, file.length), (1_500_000, MAX_RANGE_BYTES));
+        assert_eq!(file.version, None);
+
+        let binary = dir.path().join("blob.bin");
+        fs::write(&binary, b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0").unwrap();
+        let file = load_file(&binary).unwrap();
+        assert_eq!(file.encoding, None);
+        assert_ne!(file.mime_type, "text/plain");
+        assert!(file.content.starts_with("00000000  7f 45 4c 46"));
+    }
+
+    #[test]
+    fn test_write_keeps_line_endings_and_permissions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "#!/bin/sh\r\necho hi\r\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        write_file_atomic(&path, "#!/bin/sh\necho bye\n", None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "#!/bin/sh\r\necho bye\r\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o755);
+        }
+
+        let serialized = serde_json::to_value(FileWriteError::Conflict {
+            path: "a".to_string(),
+            expected_version: "x".to_string(),
+            current_version: None,
+        })
+        .unwrap();
+        assert_eq!(serialized["kind"], "conflict");
     }
 }
@@ -14,7 +14,12 @@ pub async fn read_csv_content(file_path: String) -> Result<String, String> {
         return Err("File does not exist or is a directory".to_string());
     }
 
-    let file = std::fs::File::open(expanded_path)
+    extract_csv_text(&expanded_path)
+}
+
+/// Header and the first 1000 rows of the CSV file, one line each.
+pub fn extract_csv_text(path: &Path) -> Result<String, String> {
+    let file = std::fs::File::open(path)
         .map_err(|e| format!("Failed to open CSV file: {}", e))?;
 
     let mut reader = Reader::from_reader(file);
@@ -14,7 +14,11 @@ pub async fn read_pdf_content(file_path: String) -> Result<String, String> {
         return Err("File does not exist or is a directory".to_string());
     }
 
-    match extract_text(&expanded_path) {
+    extract_pdf_text(&expanded_path)
+}
+
+pub fn extract_pdf_text(path: &Path) -> Result<String, String> {
+    match extract_text(path) {
         Ok(content) => Ok(content),
         Err(e) => Err(format!("Failed to extract PDF content: {}", e)),
     }
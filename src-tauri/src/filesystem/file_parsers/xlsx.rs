@@ -14,8 +14,13 @@ pub async fn read_xlsx_content(file_path: String) -> Result<String, String> {
         return Err("File does not exist or is a directory".to_string());
     }
 
+    extract_xlsx_text(&expanded_path)
+}
+
+/// The first worksheet's first 1000 rows, tab-separated.
+pub fn extract_xlsx_text(path: &Path) -> Result<String, String> {
     let mut workbook: Xlsx<_> =
-        open_workbook(&expanded_path).map_err(|e| format!("Failed to open XLSX file: {}", e))?;
+        open_workbook(path).map_err(|e| format!("Failed to open XLSX file: {}", e))?;
 
     let mut content = String::new();
 
@@ -0,0 +1,665 @@
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::sync::Mutex;
+
+use super::directory_ops::expand_path;
+use crate::state::CodexState;
+
+/// Most bytes one range, line or tail read returns.
+pub(super) const MAX_RANGE_BYTES: u64 = 1024 * 1024;
+const MAX_HEX_PREVIEW_BYTES: usize = 4096;
+const SNIFF_BYTES: usize = 8192;
+const DEFAULT_TAIL_LINES: usize = 100;
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl TextEncoding {
+    fn unit(self) -> usize {
+        match self {
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+            _ => 1,
+        }
+    }
+
+    fn is_newline(self, unit: &[u8]) -> bool {
+        match self {
+            TextEncoding::Utf16Le => unit == [b'\n', 0],
+            TextEncoding::Utf16Be => unit == [0, b'\n'],
+            _ => unit == [b'\n'],
+        }
+    }
+
+    /// Decodes `bytes`, leaving a character cut off at the end undecoded
+    /// unless `at_eof`. Returns the text and the bytes consumed.
+    fn decode(self, bytes: &[u8], at_eof: bool) -> (String, usize) {
+        match self {
+            TextEncoding::Latin1 => (bytes.iter().map(|&b| b as char).collect(), bytes.len()),
+            TextEncoding::Utf8 => {
+                let mut end = bytes.len();
+                if !at_eof {
+                    // Back up to the last lead byte and keep it if complete
+                    if let Some(lead) = (end.saturating_sub(4)..end).rev().find(|&i| bytes[i] & 0xC0 != 0x80) {
+                        let needed = match bytes[lead] {
+                            b if b >= 0xF0 => 4,
+                            b if b >= 0xE0 => 3,
+                            b if b >= 0xC0 => 2,
+                            _ => 1,
+                        };
+                        if lead + needed > end {
+                            end = lead;
+                        }
+                    }
+                }
+                (String::from_utf8_lossy(&bytes[..end]).to_string(), end)
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let mut end = bytes.len() & !1;
+                let unit_at = |i: usize| match self {
+                    TextEncoding::Utf16Le => u16::from_le_bytes([bytes[i], bytes[i + 1]]),
+                    _ => u16::from_be_bytes([bytes[i], bytes[i + 1]]),
+                };
+                if !at_eof && end >= 2 && (0xD800..0xDC00).contains(&unit_at(end - 2)) {
+                    end -= 2;
+                }
+                let units = (0..end).step_by(2).map(unit_at);
+                let text = char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect();
+                (text, if at_eof { bytes.len() } else { end })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    /// Text in `encoding`, whose byte order mark takes `bom` bytes.
+    Text { encoding: TextEncoding, bom: usize },
+    Binary,
+}
+
+/// Classifies a file from its first bytes: a BOM, valid UTF-8, UTF-16
+/// without a BOM (NULs in every other byte), Latin-1, else binary.
+pub fn sniff_content(sample: &[u8]) -> ContentKind {
+    let text = |encoding, bom| ContentKind::Text { encoding, bom };
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return text(TextEncoding::Utf8, 3);
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return text(TextEncoding::Utf16Le, 2);
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return text(TextEncoding::Utf16Be, 2);
+    }
+
+    if sample.contains(&0) {
+        let pairs = sample.len() / 2;
+        let zeros_at = |parity: usize| sample.iter().skip(parity).step_by(2).filter(|&&b| b == 0).count();
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if pairs > 0 && odd * 10 >= pairs * 3 && even * 10 < pairs {
+            return text(TextEncoding::Utf16Le, 0);
+        }
+        if pairs > 0 && even * 10 >= pairs * 3 && odd * 10 < pairs {
+            return text(TextEncoding::Utf16Be, 0);
+        }
+        return ContentKind::Binary;
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => return text(TextEncoding::Utf8, 0),
+        // Cut off mid-character at the end of the sample
+        Err(e) if e.error_len().is_none() => return text(TextEncoding::Utf8, 0),
+        Err(_) => {}
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    if control * 10 > sample.len() {
+        ContentKind::Binary
+    } else {
+        text(TextEncoding::Latin1, 0)
+    }
+}
+
+fn mime_type(path: &Path, sample: &[u8], kind: ContentKind) -> String {
+    if let Some(found) = infer::get(sample) {
+        return found.mime_type().to_string();
+    }
+    match mime_guess::from_path(path).first() {
+        Some(guess) => guess.essence_str().to_string(),
+        None if kind == ContentKind::Binary => "application/octet-stream".to_string(),
+        None => "text/plain".to_string(),
+    }
+}
+
+/// `xxd`-style dump with absolute offsets.
+pub fn hex_preview(bytes: &[u8], offset: u64) -> String {
+    let mut out = String::new();
+    for (row, line) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = line
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        out.push_str(&format!("{:08x}  {:<47}  |{}|\n", offset + row as u64 * 16, hex.join(" "), ascii));
+    }
+    out
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChunkContent {
+    Text { encoding: TextEncoding, text: String },
+    Binary { hex_preview: String },
+}
+
+/// Part of a file. `offset` and `length` are the bytes actually covered,
+/// which may be nudged to character boundaries.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChunk {
+    pub path: String,
+    pub size: u64,
+    pub offset: u64,
+    pub length: u64,
+    /// The chunk runs to the end of the file.
+    pub eof: bool,
+    pub mime_type: String,
+    /// 1-based number of the first line, for line reads.
+    pub first_line: Option<usize>,
+    pub line_count: Option<usize>,
+    #[serde(flatten)]
+    pub content: ChunkContent,
+}
+
+pub(super) struct OpenedFile {
+    path: PathBuf,
+    file: File,
+    pub(super) size: u64,
+    pub(super) kind: ContentKind,
+    pub(super) mime_type: String,
+}
+
+impl OpenedFile {
+    pub(super) fn open(path: &Path) -> Result<Self, String> {
+        if path.is_dir() {
+            return Err("File does not exist or is a directory".to_string());
+        }
+        let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let size = file.metadata().map_err(|e| format!("Failed to read file: {}", e))?.len();
+        let mut sample = Vec::with_capacity(SNIFF_BYTES);
+        (&mut file)
+            .take(SNIFF_BYTES as u64)
+            .read_to_end(&mut sample)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let kind = sniff_content(&sample);
+        Ok(Self {
+            path: path.to_path_buf(),
+            mime_type: mime_type(path, &sample, kind),
+            file,
+            size,
+            kind,
+        })
+    }
+
+    fn read_at(&mut self, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| (&mut self.file).take(length).read_to_end(&mut bytes))
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        Ok(bytes)
+    }
+
+    /// First offset at or after `offset` that starts a character.
+    fn align(&mut self, offset: u64) -> Result<u64, String> {
+        let ContentKind::Text { encoding, bom } = self.kind else {
+            return Ok(offset);
+        };
+        let offset = offset.max(bom as u64).min(self.size);
+        Ok(match encoding {
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => offset + (offset - bom as u64) % 2,
+            TextEncoding::Utf8 => {
+                let head = self.read_at(offset, 4)?;
+                offset + head.iter().take_while(|&&b| b & 0xC0 == 0x80).count() as u64
+            }
+            TextEncoding::Latin1 => offset,
+        })
+    }
+
+    pub(super) fn chunk(&mut self, offset: u64, length: u64, lines: Option<(usize, usize)>) -> Result<FileChunk, String> {
+        let length = length.min(MAX_RANGE_BYTES);
+        let (offset, length, content) = match self.kind {
+            ContentKind::Text { encoding, .. } => {
+                let offset = self.align(offset)?;
+                let bytes = self.read_at(offset, length)?;
+                let at_eof = offset + bytes.len() as u64 >= self.size;
+                let (text, consumed) = encoding.decode(&bytes, at_eof);
+                (offset, consumed as u64, ChunkContent::Text { encoding, text })
+            }
+            ContentKind::Binary => {
+                // Only what the preview shows, so `length` continues the read
+                let offset = offset.min(self.size);
+                let bytes = self.read_at(offset, length.min(MAX_HEX_PREVIEW_BYTES as u64))?;
+                let hex_preview = hex_preview(&bytes, offset);
+                (offset, bytes.len() as u64, ChunkContent::Binary { hex_preview })
+            }
+        };
+        Ok(FileChunk {
+            path: self.path.to_string_lossy().to_string(),
+            size: self.size,
+            offset,
+            length,
+            eof: offset + length >= self.size,
+            mime_type: self.mime_type.clone(),
+            first_line: lines.map(|(first, _)| first),
+            line_count: lines.map(|(_, count)| count),
+            content,
+        })
+    }
+
+    /// Calls `on_newline` with the offset of each line break from `start`
+    /// on, until it returns false.
+    fn scan_newlines(&mut self, start: u64, encoding: TextEncoding, mut on_newline: impl FnMut(u64) -> bool) -> Result<(), String> {
+        let io_error = |e: std::io::Error| format!("Failed to read file: {}", e);
+        self.file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+        let mut reader = BufReader::with_capacity(64 * 1024, &mut self.file);
+        let unit = encoding.unit();
+        let mut pos = start;
+        let mut carry: Option<u8> = None;
+        loop {
+            let buf = reader.fill_buf().map_err(io_error)?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            let len = buf.len();
+            let mut i = 0;
+            if let Some(first) = carry.take() {
+                if encoding.is_newline(&[first, buf[0]]) && !on_newline(pos - 1) {
+                    return Ok(());
+                }
+                i = 1;
+            }
+            while i + unit <= len {
+                if encoding.is_newline(&buf[i..i + unit]) && !on_newline(pos + i as u64) {
+                    return Ok(());
+                }
+                i += unit;
+            }
+            if i < len {
+                carry = Some(buf[i]);
+            }
+            reader.consume(len);
+            pos += len as u64;
+        }
+    }
+
+    /// Lines `first..first + count` (1-based), stopping early at the byte cap.
+    fn lines(&mut self, first: usize, count: usize) -> Result<FileChunk, String> {
+        let ContentKind::Text { encoding, bom } = self.kind else {
+            return Err("Binary files have no lines".to_string());
+        };
+        let (first, count) = (first.max(1), count.max(1));
+        let unit = encoding.unit() as u64;
+        let mut start = if first == 1 { Some(bom as u64) } else { None };
+        let mut end = None;
+        let (mut line, mut taken, mut capped) = (1, 0, false);
+        self.scan_newlines(bom as u64, encoding, |newline| {
+            let next = newline + unit;
+            line += 1;
+            match start {
+                None if line == first => start = Some(next),
+                Some(start) => {
+                    if next - start > MAX_RANGE_BYTES {
+                        capped = true;
+                        return false;
+                    }
+                    taken += 1;
+                    end = Some(next);
+                    if taken == count {
+                        return false;
+                    }
+                }
+                None => {}
+            }
+            true
+        })?;
+
+        let Some(start) = start else {
+            // Past the last line
+            return self.chunk(self.size, 0, Some((first, 0)));
+        };
+        let rest = end.unwrap_or(start);
+        let (end, taken) = if taken == count || capped {
+            (rest, taken)
+        } else if rest < self.size {
+            // A last line without a trailing newline
+            (self.size, taken + 1)
+        } else {
+            (rest, taken)
+        };
+        self.chunk(start, end - start, Some((first, taken)))
+    }
+
+    /// Offset where the last `count` lines begin, looking at most
+    /// `MAX_RANGE_BYTES` back from the end.
+    fn tail_start(&mut self, count: usize) -> Result<u64, String> {
+        let ContentKind::Text { encoding, .. } = self.kind else {
+            return Err("Binary files can't be tailed".to_string());
+        };
+        let window = self.align(self.size.saturating_sub(MAX_RANGE_BYTES))?;
+        let unit = encoding.unit() as u64;
+        let mut newlines = Vec::new();
+        self.scan_newlines(window, encoding, |newline| {
+            newlines.push(newline);
+            true
+        })?;
+        // A final newline ends the last line rather than starting another
+        if newlines.last().is_some_and(|&last| last + unit >= self.size) {
+            newlines.pop();
+        }
+        Ok(match newlines.len().checked_sub(count) {
+            Some(i) if count > 0 => newlines[i] + unit,
+            _ if count == 0 => self.size,
+            _ => window,
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn read_file_range(file_path: String, offset: u64, length: u64) -> Result<FileChunk, String> {
+    let path = expand_path(&file_path)?;
+    tokio::task::spawn_blocking(move || OpenedFile::open(&path)?.chunk(offset, length, None))
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?
+}
+
+/// Reads `line_count` lines starting at `start_line` (1-based).
+#[tauri::command]
+pub async fn read_file_lines(file_path: String, start_line: usize, line_count: usize) -> Result<FileChunk, String> {
+    let path = expand_path(&file_path)?;
+    tokio::task::spawn_blocking(move || OpenedFile::open(&path)?.lines(start_line, line_count))
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?
+}
+
+/// Payload of `file-tail`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileTailEvent {
+    pub tail_id: String,
+    pub text: String,
+    /// Byte offset `text` starts at.
+    pub offset: u64,
+    /// The file shrank (truncated or rotated); `text` starts it over.
+    pub reset: bool,
+}
+
+/// A running tail in `CodexState::tails`.
+pub struct TailHandle {
+    pub stop: Arc<AtomicBool>,
+    /// Label of the window that started it.
+    pub window: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileTail {
+    pub tail_id: String,
+    /// The last lines as of starting.
+    pub chunk: FileChunk,
+}
+
+/// Polls `path` for appended text after `offset`, until `stop` is set.
+fn follow_file(
+    path: PathBuf,
+    encoding: TextEncoding,
+    bom: u64,
+    mut offset: u64,
+    stop: Arc<AtomicBool>,
+    mut on_text: impl FnMut(String, u64, bool),
+) {
+    // Reported with the next text, as a truncate is often followed by a write
+    let mut reset = false;
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(TAIL_POLL_INTERVAL);
+        let Ok(size) = fs::metadata(&path).map(|m| m.len()) else {
+            // Gone for now, e.g. mid-rotation
+            continue;
+        };
+        if size < offset {
+            offset = bom.min(size);
+            reset = true;
+        }
+        if size == offset {
+            continue;
+        }
+        let bytes = match File::open(&path).and_then(|mut file| {
+            let mut bytes = Vec::new();
+            file.seek(SeekFrom::Start(offset))?;
+            file.take((size - offset).min(MAX_RANGE_BYTES)).read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::debug!("Tail of {} failed to read: {}", path.display(), e);
+                continue;
+            }
+        };
+        // Keep a partly written character for the next poll
+        let (text, consumed) = encoding.decode(&bytes, false);
+        if consumed > 0 {
+            on_text(text, offset, reset);
+            reset = false;
+        }
+        offset += consumed as u64;
+    }
+}
+
+/// Returns the last `lines` lines (default 100) and then emits `file-tail`
+/// events as text is appended, until `stop_tail` or the window closes.
+#[tauri::command]
+pub async fn tail_file(
+    app: AppHandle,
+    window: Window,
+    state: State<'_, CodexState>,
+    file_path: String,
+    lines: Option<usize>,
+) -> Result<FileTail, String> {
+    let path = expand_path(&file_path)?;
+    let lines = lines.unwrap_or(DEFAULT_TAIL_LINES);
+    let open_path = path.clone();
+    let (chunk, kind) = tokio::task::spawn_blocking(move || {
+        let mut file = OpenedFile::open(&open_path)?;
+        let start = file.tail_start(lines)?;
+        let chunk = file.chunk(start, file.size - start, None)?;
+        Ok::<_, String>((chunk, file.kind))
+    })
+    .await
+    .map_err(|e| format!("Failed to read file: {}", e))??;
+    let ContentKind::Text { encoding, bom } = kind else {
+        return Err("Binary files can't be tailed".to_string());
+    };
+
+    let tail_id = uuid::Uuid::new_v4().to_string();
+    let stop = Arc::new(AtomicBool::new(false));
+    state.tails.lock().await.insert(
+        tail_id.clone(),
+        TailHandle {
+            stop: stop.clone(),
+            window: window.label().to_string(),
+        },
+    );
+
+    let event_tail_id = tail_id.clone();
+    let offset = chunk.offset + chunk.length;
+    thread::spawn(move || {
+        follow_file(path, encoding, bom as u64, offset, stop, |text, offset, reset| {
+            let event = FileTailEvent {
+                tail_id: event_tail_id.clone(),
+                text,
+                offset,
+                reset,
+            };
+            if let Err(e) = app.emit("file-tail", &event) {
+                log::error!("Failed to emit file tail: {}", e);
+            }
+        });
+        app.state::<CodexState>().tails.blocking_lock().remove(&event_tail_id);
+    });
+
+    Ok(FileTail { tail_id, chunk })
+}
+
+/// Returns false when the tail had already stopped.
+#[tauri::command]
+pub async fn stop_tail(state: State<'_, CodexState>, tail_id: String) -> Result<bool, String> {
+    match state.tails.lock().await.remove(&tail_id) {
+        Some(tail) => {
+            tail.stop.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Stops the tails a window started; called once it is destroyed.
+pub async fn stop_window_tails(tails: &Mutex<HashMap<String, TailHandle>>, window: &str) {
+    tails.lock().await.retain(|_, tail| {
+        if tail.window != window {
+            return true;
+        }
+        tail.stop.store(true, Ordering::SeqCst);
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    fn text_of(chunk: &FileChunk) -> &str {
+        match &chunk.content {
+            ChunkContent::Text { text, .. } => text,
+            ChunkContent::Binary { .. } => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_sniffing() {
+        let text = |encoding, bom| ContentKind::Text { encoding, bom };
+        assert_eq!(sniff_content(b"plain"), text(TextEncoding::Utf8, 0));
+        assert_eq!(sniff_content("héllo".as_bytes()), text(TextEncoding::Utf8, 0));
+        assert_eq!(sniff_content(&"héllo".as_bytes()[..2]), text(TextEncoding::Utf8, 0));
+        assert_eq!(sniff_content(b"\xEF\xBB\xBFbom"), text(TextEncoding::Utf8, 3));
+        assert_eq!(sniff_content(b"caf\xe9 cr\xe8me"), text(TextEncoding::Latin1, 0));
+        assert_eq!(sniff_content(b"\xFF\xFEh\0i\0"), text(TextEncoding::Utf16Le, 2));
+        assert_eq!(sniff_content(b"h\0e\0l\0l\0o\0"), text(TextEncoding::Utf16Le, 0));
+        assert_eq!(sniff_content(b"\0h\0e\0l\0l\0o"), text(TextEncoding::Utf16Be, 0));
+        assert_eq!(sniff_content(b"\x7fELF\x02\x01\x01\0\0\0\0\0"), ContentKind::Binary);
+    }
+
+    #[test]
+    fn test_ranges_and_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "one\ntwö\nthree\nfour").unwrap();
+        let mut file = OpenedFile::open(&path).unwrap();
+
+        let chunk = file.chunk(0, 7, None).unwrap();
+        // "ö" is cut in half, so it is left for the next read
+        assert_eq!((text_of(&chunk), chunk.length, chunk.eof), ("one\ntw", 6, false));
+        // Starting mid-character skips to the next one
+        let chunk = file.chunk(7, 100, None).unwrap();
+        assert_eq!((text_of(&chunk), chunk.offset, chunk.eof), ("\nthree\nfour", 8, true));
+
+        let chunk = file.lines(2, 2).unwrap();
+        assert_eq!(text_of(&chunk), "twö\nthree\n");
+        assert_eq!((chunk.first_line, chunk.line_count), (Some(2), Some(2)));
+        let chunk = file.lines(3, 10).unwrap();
+        assert_eq!((text_of(&chunk), chunk.line_count), ("three\nfour", Some(2)));
+        assert_eq!(file.lines(9, 1).unwrap().line_count, Some(0));
+
+        let utf16 = dir.path().join("utf16.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("a\nb\nc\n".encode_utf16().flat_map(|u| u.to_le_bytes()));
+        fs::write(&utf16, bytes).unwrap();
+        let mut file = OpenedFile::open(&utf16).unwrap();
+        assert_eq!(text_of(&file.lines(2, 1).unwrap()), "b\n");
+        assert_eq!(text_of(&file.chunk(3, 100, None).unwrap()), "\nb\nc\n");
+    }
+
+    #[test]
+    fn test_binary_preview() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("image.png");
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        bytes.extend([0u8; 8192]);
+        fs::write(&path, &bytes).unwrap();
+
+        let mut file = OpenedFile::open(&path).unwrap();
+        let chunk = file.chunk(0, 16, None).unwrap();
+        assert_eq!(chunk.mime_type, "image/png");
+        match chunk.content {
+            ChunkContent::Binary { hex_preview } => {
+                assert!(hex_preview.starts_with("00000000  89 50 4e 47 0d 0a 1a 0a"));
+                assert!(hex_preview.trim_end().ends_with("|.PNG........IHDR|"));
+            }
+            ChunkContent::Text { .. } => panic!("expected binary"),
+        }
+        // Large reads cover only what the preview shows
+        let chunk = file.chunk(0, MAX_RANGE_BYTES, None).unwrap();
+        assert_eq!(chunk.length, MAX_HEX_PREVIEW_BYTES as u64);
+        assert!(!chunk.eof);
+        let next = file.chunk(chunk.length, MAX_RANGE_BYTES, None).unwrap();
+        assert_eq!(next.offset, MAX_HEX_PREVIEW_BYTES as u64);
+        assert!(file.lines(1, 1).is_err());
+    }
+
+    #[test]
+    fn test_tail_follows_appends_and_truncation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "1\n2\n3\n4\n").unwrap();
+        let mut file = OpenedFile::open(&path).unwrap();
+        let start = file.tail_start(2).unwrap();
+        let chunk = file.chunk(start, file.size - start, None).unwrap();
+        assert_eq!(text_of(&chunk), "3\n4\n");
+        assert_eq!(file.tail_start(10).unwrap(), 0);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let follow_stop = stop.clone();
+        let follow_path = path.clone();
+        let handle = thread::spawn(move || {
+            follow_file(follow_path, TextEncoding::Utf8, 0, 8, follow_stop, |text, offset, reset| {
+                let _ = tx.send((text, offset, reset));
+            })
+        });
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"5\n").unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), ("5\n".to_string(), 8, false));
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), ("new\n".to_string(), 0, true));
+
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::git_status::GitFileStatus;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
@@ -7,6 +9,12 @@ pub struct FileEntry {
     pub is_directory: bool,
     pub size: Option<u64>,
     pub extension: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_status: Option<GitFileStatus>,
+    /// Tree listings only: `None` for directories below the requested depth,
+    /// which the frontend loads on expand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<FileEntry>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
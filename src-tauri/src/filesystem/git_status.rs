@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,4 +66,94 @@ pub async fn get_git_status(directory: String) -> Result<GitStatus, String> {
     }
     
     Ok(git_status)
-}
\ No newline at end of file
+}
+
+/// Status of a single path, as shown next to it in the file tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitFileStatus {
+    Modified,
+    Staged,
+    Added,
+    Untracked,
+    Deleted,
+    Renamed,
+    Conflicted,
+}
+
+fn classify(code: &[u8]) -> Option<GitFileStatus> {
+    let (x, y) = (code[0], code[1]);
+    Some(match (x, y) {
+        (b'?', b'?') => GitFileStatus::Untracked,
+        (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => GitFileStatus::Conflicted,
+        (b'R', _) => GitFileStatus::Renamed,
+        (b'A', _) => GitFileStatus::Added,
+        (_, b'M') | (_, b'T') => GitFileStatus::Modified,
+        (b'D', _) | (_, b'D') => GitFileStatus::Deleted,
+        (b'M', _) | (b'T', _) => GitFileStatus::Staged,
+        _ => return None,
+    })
+}
+
+/// Changed paths under `root`, keyed by `root.join(relative path)`. Parent
+/// directories of changed files are marked `Modified`. `None` outside a
+/// git work tree.
+pub fn git_status_map(root: &Path) -> Option<HashMap<PathBuf, GitFileStatus>> {
+    let toplevel = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(root)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel.stdout).trim());
+    // `root` relative to the repository, to map git's paths back under it
+    let root_in_repo = root.canonicalize().ok()?.strip_prefix(toplevel.canonicalize().ok()?).ok()?.to_path_buf();
+
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "-z", "--untracked-files=normal"])
+        .current_dir(root)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+
+    let mut statuses = HashMap::new();
+    let mut records = output.stdout.split(|&b| b == 0);
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let status = classify(&record[..2]);
+        if record[0] == b'R' || record[0] == b'C' {
+            // The original path follows as its own record
+            records.next();
+        }
+        let Some(status) = status else {
+            continue;
+        };
+        let repo_path = String::from_utf8_lossy(&record[3..]);
+        let Ok(relative) = Path::new(repo_path.trim_end_matches('/')).strip_prefix(&root_in_repo) else {
+            continue;
+        };
+        let path = root.join(relative);
+        for ancestor in path.ancestors().skip(1) {
+            if ancestor == root || !ancestor.starts_with(root) {
+                break;
+            }
+            statuses.entry(ancestor.to_path_buf()).or_insert(GitFileStatus::Modified);
+        }
+        statuses.insert(path, status);
+    }
+    Some(statuses)
+}
+
+/// Looks up `path`, falling back to an untracked parent directory, which git
+/// reports as a whole.
+pub fn status_for(statuses: &HashMap<PathBuf, GitFileStatus>, path: &Path) -> Option<GitFileStatus> {
+    statuses.get(path).copied().or_else(|| {
+        path.ancestors()
+            .skip(1)
+            .filter_map(|ancestor| statuses.get(ancestor))
+            .find(|&&status| status == GitFileStatus::Untracked)
+            .copied()
+    })
+}
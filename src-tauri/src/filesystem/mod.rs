@@ -2,6 +2,11 @@ pub mod directory_ops;
 pub mod file_analysis;
 pub mod file_io;
 pub mod file_parsers;
+pub mod file_range;
 pub mod file_types;
 pub mod git_diff;
 pub mod git_status;
+pub mod search;
+pub mod tokenizer;
+pub mod watcher;
+pub mod write_policy;
@@ -0,0 +1,465 @@
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch};
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+use super::directory_ops::expand_path;
+use crate::state::CodexState;
+
+const DEFAULT_MAX_RESULTS: usize = 10_000;
+/// Long lines (minified files) are cut so one match can't flood the UI.
+const MAX_LINE_CHARS: usize = 1000;
+const BATCH_FILES: usize = 50;
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SearchOptions {
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Treat the pattern as a regex instead of literal text.
+    #[serde(default)]
+    pub regex: bool,
+    /// Globs a file must match, e.g. `*.rs` or `src/**`.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub context_lines: usize,
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Honor `.gitignore`, `.ignore` and git's global excludes (default true).
+    #[serde(default)]
+    pub respect_gitignore: Option<bool>,
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Match,
+    Context,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchLine {
+    pub line_number: u64,
+    pub text: String,
+    pub kind: LineKind,
+    /// Matched `[start, end)` char offsets into `text`.
+    pub ranges: Vec<[usize; 2]>,
+}
+
+/// The matches in one file, with their context lines in file order.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSearchResult {
+    pub path: String,
+    pub relative_path: String,
+    pub lines: Vec<SearchLine>,
+    pub match_count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchSummary {
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub match_count: usize,
+    /// Stopped at `max_results`.
+    pub truncated: bool,
+    pub cancelled: bool,
+}
+
+/// Payload of `search-results`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResultsEvent {
+    pub search_id: String,
+    pub files: Vec<FileSearchResult>,
+}
+
+/// Payload of `search-complete`, sent once per search.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchCompleteEvent {
+    pub search_id: String,
+    #[serde(flatten)]
+    pub summary: SearchSummary,
+    pub error: Option<String>,
+}
+
+pub fn build_matcher(pattern: &str, options: &SearchOptions) -> Result<RegexMatcher, String> {
+    if pattern.is_empty() {
+        return Err("Search pattern is empty".to_string());
+    }
+    RegexMatcherBuilder::new()
+        .case_insensitive(!options.case_sensitive)
+        .word(options.whole_word)
+        .fixed_strings(!options.regex)
+        .line_terminator(Some(b'\n'))
+        .build(pattern)
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+fn line_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).chars().take(MAX_LINE_CHARS).collect()
+}
+
+/// Collects one file's lines; stops early on cancel, binary data or the
+/// match budget.
+struct FileSink<'a> {
+    matcher: &'a RegexMatcher,
+    cancel: &'a AtomicBool,
+    budget: usize,
+    lines: Vec<SearchLine>,
+    match_count: usize,
+    binary: bool,
+}
+
+impl Sink for FileSink<'_> {
+    type Error = std::io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, Self::Error> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let bytes = mat.bytes();
+        let text = line_text(bytes);
+        let mut ranges = Vec::new();
+        self.matcher
+            .find_iter(bytes, |m| {
+                let start = String::from_utf8_lossy(&bytes[..m.start()]).chars().count();
+                let end = String::from_utf8_lossy(&bytes[..m.end()]).chars().count();
+                if start < MAX_LINE_CHARS && end > start {
+                    ranges.push([start, end.min(MAX_LINE_CHARS)]);
+                }
+                true
+            })
+            .map_err(std::io::Error::other)?;
+        self.lines.push(SearchLine {
+            line_number: mat.line_number().unwrap_or(0),
+            text,
+            kind: LineKind::Match,
+            ranges,
+        });
+        self.match_count += 1;
+        Ok(self.match_count < self.budget)
+    }
+
+    fn context(&mut self, _searcher: &Searcher, context: &SinkContext<'_>) -> Result<bool, Self::Error> {
+        self.lines.push(SearchLine {
+            line_number: context.line_number().unwrap_or(0),
+            text: line_text(context.bytes()),
+            kind: LineKind::Context,
+            ranges: Vec::new(),
+        });
+        Ok(true)
+    }
+
+    fn binary_data(&mut self, _searcher: &Searcher, _offset: u64) -> Result<bool, Self::Error> {
+        self.binary = true;
+        Ok(false)
+    }
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    parts.join("/")
+}
+
+/// Searches the non-ignored text files under `root` in parallel, handing
+/// each file with matches to `on_file` as soon as it is done.
+pub fn search_files(
+    root: &Path,
+    matcher: &RegexMatcher,
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+    on_file: impl Fn(FileSearchResult) + Sync,
+) -> Result<SearchSummary, String> {
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", root.display()));
+    }
+    let respect_gitignore = options.respect_gitignore.unwrap_or(true);
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in &options.include {
+        overrides
+            .add(pattern)
+            .map_err(|e| format!("Invalid include pattern '{}': {}", pattern, e))?;
+    }
+    for pattern in &options.exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|e| format!("Invalid exclude pattern '{}': {}", pattern, e))?;
+    }
+    let overrides = overrides.build().map_err(|e| format!("Invalid search globs: {}", e))?;
+
+    let walker = WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        .parents(respect_gitignore)
+        .require_git(false)
+        .overrides(overrides)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build_parallel();
+
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let files_searched = AtomicUsize::new(0);
+    let files_matched = AtomicUsize::new(0);
+    let match_count = AtomicUsize::new(0);
+    let truncated = AtomicBool::new(false);
+
+    walker.run(|| {
+        let mut searcher = SearcherBuilder::new()
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .line_number(true)
+            .before_context(options.context_lines)
+            .after_context(options.context_lines)
+            .build();
+        let (files_searched, files_matched, match_count, truncated, on_file) =
+            (&files_searched, &files_matched, &match_count, &truncated, &on_file);
+        Box::new(move |entry| {
+            if cancel.load(Ordering::Relaxed) || truncated.load(Ordering::Relaxed) {
+                return WalkState::Quit;
+            }
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return WalkState::Continue;
+            }
+
+            let mut sink = FileSink {
+                matcher,
+                cancel,
+                budget: max_results.saturating_sub(match_count.load(Ordering::Relaxed)).max(1),
+                lines: Vec::new(),
+                match_count: 0,
+                binary: false,
+            };
+            if let Err(e) = searcher.search_path(matcher, entry.path(), &mut sink) {
+                log::debug!("Skipping {}: {}", entry.path().display(), e);
+                return WalkState::Continue;
+            }
+            files_searched.fetch_add(1, Ordering::Relaxed);
+            if sink.binary || sink.match_count == 0 {
+                return WalkState::Continue;
+            }
+
+            let total = match_count.fetch_add(sink.match_count, Ordering::Relaxed) + sink.match_count;
+            files_matched.fetch_add(1, Ordering::Relaxed);
+            on_file(FileSearchResult {
+                path: entry.path().to_string_lossy().to_string(),
+                relative_path: relative_path(root, entry.path()),
+                lines: sink.lines,
+                match_count: sink.match_count,
+            });
+            if total >= max_results {
+                truncated.store(true, Ordering::Relaxed);
+                return WalkState::Quit;
+            }
+            WalkState::Continue
+        })
+    });
+
+    Ok(SearchSummary {
+        files_searched: files_searched.into_inner(),
+        files_matched: files_matched.into_inner(),
+        match_count: match_count.into_inner(),
+        truncated: truncated.into_inner(),
+        cancelled: cancel.load(Ordering::Relaxed),
+    })
+}
+
+/// Starts a search under `root` and returns at once. Results arrive as
+/// batched `search-results` events followed by one `search-complete`, all
+/// tagged with the caller's `search_id`; listen for them before invoking.
+#[tauri::command]
+pub async fn search_workspace(
+    app: AppHandle,
+    state: State<'_, CodexState>,
+    search_id: String,
+    root: String,
+    pattern: String,
+    options: Option<SearchOptions>,
+) -> Result<String, String> {
+    let root: PathBuf = expand_path(&root)?;
+    let options = options.unwrap_or_default();
+    // Bad patterns fail the command rather than the stream
+    let matcher = build_matcher(&pattern, &options)?;
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let mut searches = state.searches.lock().await;
+        if searches.contains_key(&search_id) {
+            return Err(format!("Search {} is already running", search_id));
+        }
+        searches.insert(search_id.clone(), cancel.clone());
+    }
+
+    let searches = state.searches.clone();
+    let task_search_id = search_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let pending = Mutex::new((Vec::new(), Instant::now()));
+        let flush = |files: Vec<FileSearchResult>| {
+            let event = SearchResultsEvent {
+                search_id: task_search_id.clone(),
+                files,
+            };
+            if let Err(e) = app.emit("search-results", &event) {
+                log::error!("Failed to emit search results: {}", e);
+            }
+        };
+
+        let result = search_files(&root, &matcher, &options, &cancel, |file| {
+            let mut pending = pending.lock().unwrap_or_else(|e| e.into_inner());
+            pending.0.push(file);
+            if pending.0.len() >= BATCH_FILES || pending.1.elapsed() >= BATCH_INTERVAL {
+                flush(std::mem::take(&mut pending.0));
+                pending.1 = Instant::now();
+            }
+        });
+        let rest = pending.into_inner().unwrap_or_else(|e| e.into_inner()).0;
+        if !rest.is_empty() {
+            flush(rest);
+        }
+
+        let (summary, error) = match result {
+            Ok(summary) => (summary, None),
+            Err(e) => (SearchSummary::default(), Some(e)),
+        };
+        let complete = SearchCompleteEvent {
+            search_id: task_search_id.clone(),
+            summary,
+            error,
+        };
+        if let Err(e) = app.emit("search-complete", &complete) {
+            log::error!("Failed to emit search completion: {}", e);
+        }
+        // A search started later under the same id keeps its own entry
+        let mut searches = searches.blocking_lock();
+        if searches.get(&task_search_id).is_some_and(|current| Arc::ptr_eq(current, &cancel)) {
+            searches.remove(&task_search_id);
+        }
+    });
+
+    Ok(search_id)
+}
+
+/// Stops a running search. Returns false when it had already finished.
+#[tauri::command]
+pub async fn cancel_search(state: State<'_, CodexState>, search_id: String) -> Result<bool, String> {
+    match state.searches.lock().await.remove(&search_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn search(root: &Path, pattern: &str, options: &SearchOptions) -> Vec<FileSearchResult> {
+        let matcher = build_matcher(pattern, options).unwrap();
+        let found = Mutex::new(Vec::new());
+        search_files(root, &matcher, options, &AtomicBool::new(false), |file| {
+            found.lock().unwrap().push(file)
+        })
+        .unwrap();
+        let mut found = found.into_inner().unwrap();
+        found.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        found
+    }
+
+    #[test]
+    fn test_search_modes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.rs"), "fn main() {}\nlet domain = 1;\nMAIN\n").unwrap();
+        fs::write(root.join("b.txt"), "main.rs\n").unwrap();
+
+        let options = SearchOptions::default();
+        let found = search(root, "main", &options);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].match_count, 3);
+        assert_eq!(found[0].lines[0].ranges, vec![[3, 7]]);
+
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: true,
+            include: vec!["*.rs".to_string()],
+            ..Default::default()
+        };
+        let found = search(root, "main", &options);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].lines.len(), 1);
+        assert_eq!(found[0].lines[0].line_number, 1);
+
+        // Literal unless regex mode is on
+        assert!(search(root, "ma.n", &SearchOptions::default()).is_empty());
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(search(root, r"ma.n\.rs", &options).len(), 1);
+        assert!(build_matcher("(", &options).is_err());
+    }
+
+    #[test]
+    fn test_context_ignores_and_binary_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::write(root.join("notes.md"), "one\ntwo\nneedle\nthree\nfour\n").unwrap();
+        fs::write(root.join("build/out.md"), "needle\n").unwrap();
+        fs::write(root.join("data.bin"), b"needle\x00\x01\x02").unwrap();
+
+        let options = SearchOptions {
+            context_lines: 1,
+            ..Default::default()
+        };
+        let found = search(root, "needle", &options);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].relative_path, "notes.md");
+        let lines: Vec<(u64, LineKind)> = found[0].lines.iter().map(|l| (l.line_number, l.kind)).collect();
+        assert_eq!(lines, vec![(2, LineKind::Context), (3, LineKind::Match), (4, LineKind::Context)]);
+    }
+
+    #[test]
+    fn test_cancel_and_limit() {
+        let dir = tempdir().unwrap();
+        for i in 0..20 {
+            fs::write(dir.path().join(format!("{}.txt", i)), "hit\nhit\n").unwrap();
+        }
+        let options = SearchOptions {
+            max_results: Some(5),
+            ..Default::default()
+        };
+        let matcher = build_matcher("hit", &options).unwrap();
+        let summary = search_files(dir.path(), &matcher, &options, &AtomicBool::new(false), |_| {}).unwrap();
+        assert!(summary.truncated);
+        assert!(summary.match_count >= 5 && summary.match_count < 40);
+
+        let summary = search_files(dir.path(), &matcher, &options, &AtomicBool::new(true), |_| {}).unwrap();
+        assert!(summary.cancelled);
+        assert_eq!(summary.match_count, 0);
+    }
+}
@@ -0,0 +1,67 @@
+use tiktoken_rs::{bpe_for_model, o200k_base_singleton, CoreBPE};
+
+/// The bundled BPE vocabulary for `model`'s family. Unknown models get
+/// o200k, which current OpenAI models share.
+pub fn tokenizer_for_model(model: Option<&str>) -> &'static CoreBPE {
+    model
+        .map(|m| m.rsplit('/').next().unwrap_or(m).to_lowercase())
+        .and_then(|m| bpe_for_model(&m).ok())
+        .unwrap_or_else(o200k_base_singleton)
+}
+
+pub fn count_text_tokens(text: &str, model: Option<&str>) -> usize {
+    tokenizer_for_model(model).count_ordinary(text)
+}
+
+/// Token cost of an image sent at high detail: scaled to fit 2048x2048,
+/// then so the short side is at most 768px, and billed per 512px tile.
+pub fn image_tokens(width: u64, height: u64, model: Option<&str>) -> u64 {
+    if width == 0 || height == 0 {
+        return 0;
+    }
+    let (base, per_tile) = match model.map(|m| m.to_lowercase()) {
+        Some(m) if m.contains("gpt-4o-mini") => (2833, 5667),
+        _ => (85, 170),
+    };
+
+    let (mut w, mut h) = (width as f64, height as f64);
+    let fit = (2048.0 / w.max(h)).min(1.0);
+    w *= fit;
+    h *= fit;
+    let shrink = (768.0 / w.min(h)).min(1.0);
+    w *= shrink;
+    h *= shrink;
+
+    let tiles = (w / 512.0).ceil() as u64 * (h / 512.0).ceil() as u64;
+    base + per_tile * tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_tokens_by_model_family() {
+        assert_eq!(count_text_tokens("hello world", None), 2);
+        assert_eq!(count_text_tokens("", Some("gpt-5")), 0);
+        // The two vocabularies split this differently.
+        let text = "これは日本語のテキストです";
+        assert_ne!(
+            count_text_tokens(text, Some("gpt-4")),
+            count_text_tokens(text, Some("gpt-4o"))
+        );
+        assert_eq!(
+            count_text_tokens(text, Some("openai/gpt-4o")),
+            count_text_tokens(text, Some("some-local-model"))
+        );
+    }
+
+    #[test]
+    fn test_image_tiles() {
+        assert_eq!(image_tokens(1024, 1024, None), 85 + 170 * 4);
+        assert_eq!(image_tokens(2048, 4096, Some("gpt-5")), 85 + 170 * 6);
+        assert_eq!(image_tokens(100, 100, None), 85 + 170);
+        assert_eq!(image_tokens(100, 100, Some("gpt-4o-mini")), 2833 + 5667);
+        assert_eq!(image_tokens(0, 100, None), 0);
+    }
+}
@@ -0,0 +1,612 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+use super::directory_ops::expand_path;
+use crate::state::CodexState;
+
+/// Quiet period after the last event before a batch is delivered.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+/// A batch is delivered this long after its first event even if events keep
+/// coming, e.g. from a build writing to an ignored `target/`.
+const MAX_BATCH_LATENCY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FsChange {
+    pub kind: FsChangeKind,
+    pub path: PathBuf,
+    /// The previous path of a rename.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<PathBuf>,
+}
+
+impl FsChange {
+    fn new(kind: FsChangeKind, path: PathBuf) -> Self {
+        Self {
+            kind,
+            path,
+            old_path: None,
+        }
+    }
+
+    /// Every path the change touches.
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.path).chain(self.old_path.as_ref())
+    }
+}
+
+/// Raw events of one debounce window, folded per path.
+#[derive(Default)]
+struct PendingChanges {
+    order: Vec<PathBuf>,
+    first: HashMap<PathBuf, FsChangeKind>,
+    renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PendingChanges {
+    fn push(&mut self, kind: FsChangeKind, path: PathBuf) {
+        if !self.first.contains_key(&path) {
+            self.first.insert(path.clone(), kind);
+            self.order.push(path);
+        }
+    }
+
+    fn add(&mut self, event: notify::Event) {
+        let kind = match event.kind {
+            EventKind::Access(_) => return,
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let mut paths = event.paths.into_iter();
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.renames.push((from, to));
+                }
+                return;
+            }
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => FsChangeKind::Created,
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => FsChangeKind::Deleted,
+            _ => FsChangeKind::Modified,
+        };
+        for path in event.paths {
+            self.push(kind, path);
+        }
+    }
+
+    /// Settles each path against what is on disk now, so e.g. a file
+    /// created and removed within the window produces nothing.
+    fn resolve(self) -> Vec<FsChange> {
+        let mut changes = Vec::new();
+        let mut covered = HashSet::new();
+        for (from, to) in self.renames {
+            if to.exists() {
+                changes.push(FsChange {
+                    kind: FsChangeKind::Renamed,
+                    path: to.clone(),
+                    old_path: Some(from.clone()),
+                });
+            } else {
+                changes.push(FsChange::new(FsChangeKind::Deleted, from.clone()));
+            }
+            covered.insert(from);
+            covered.insert(to);
+        }
+        for path in self.order {
+            if covered.contains(&path) {
+                continue;
+            }
+            let kind = match (self.first[&path], path.exists()) {
+                (FsChangeKind::Created, false) => continue,
+                (FsChangeKind::Created, true) => FsChangeKind::Created,
+                (_, true) => FsChangeKind::Modified,
+                (_, false) => FsChangeKind::Deleted,
+            };
+            changes.push(FsChange::new(kind, path));
+        }
+        changes
+    }
+}
+
+/// Recursive watcher that collects changes and hands them over in batches
+/// once events stop for the debounce period, or at the latest
+/// `MAX_BATCH_LATENCY` after the first one. Dropping it stops the
+/// background thread.
+pub struct DebouncedWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl DebouncedWatcher {
+    pub fn watch(
+        root: &Path,
+        debounce: Duration,
+        on_change: impl FnMut(Vec<FsChange>) + Send + 'static,
+    ) -> Result<Self, String> {
+        Self::watch_with_latency(root, debounce, MAX_BATCH_LATENCY, on_change)
+    }
+
+    fn watch_with_latency(
+        root: &Path,
+        debounce: Duration,
+        max_latency: Duration,
+        mut on_change: impl FnMut(Vec<FsChange>) + Send + 'static,
+    ) -> Result<Self, String> {
+        let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|e| format!("Failed to create watcher: {}", e))?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+        thread::spawn(move || {
+            // Ends when the watcher, and with it the sender, is dropped
+            while let Ok(first) = rx.recv() {
+                let mut pending = PendingChanges::default();
+                let mut collect = |event: notify::Result<notify::Event>| match event {
+                    Ok(event) => pending.add(event),
+                    Err(e) => log::warn!("Watch error: {}", e),
+                };
+                collect(first);
+                let deadline = Instant::now() + max_latency;
+                loop {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    match rx.recv_timeout(debounce.min(left)) {
+                        Ok(event) => collect(event),
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                let changes = pending.resolve();
+                if !changes.is_empty() {
+                    on_change(changes);
+                }
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// `.gitignore` and `.ignore` rules of the directories under a root, loaded
+/// lazily. Deleted paths can still be matched, unlike with a walk.
+struct IgnoreRules {
+    root: PathBuf,
+    dirs: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            dirs: HashMap::new(),
+        }
+    }
+
+    fn rules_for(&mut self, dir: &Path) -> &Gitignore {
+        self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+            let mut builder = GitignoreBuilder::new(dir);
+            for name in [".gitignore", ".ignore"] {
+                let file = dir.join(name);
+                if file.is_file() {
+                    if let Some(e) = builder.add(&file) {
+                        log::debug!("Bad ignore rules in {}: {}", file.display(), e);
+                    }
+                }
+            }
+            builder.build().unwrap_or_else(|_| Gitignore::empty())
+        })
+    }
+
+    fn is_ignored(&mut self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        let is_dir = path.is_dir();
+        // Deepest first: rules in deeper directories take precedence
+        let dirs: Vec<PathBuf> = relative
+            .ancestors()
+            .skip(1)
+            .map(|ancestor| self.root.join(ancestor))
+            .collect();
+        for dir in dirs {
+            match self.rules_for(&dir).matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    /// Drops cached rules after an ignore file changed.
+    fn invalidate(&mut self, ignore_file: &Path) {
+        if let Some(dir) = ignore_file.parent() {
+            self.dirs.remove(dir);
+        }
+    }
+}
+
+/// Payload of `workspace-changed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceChanges {
+    pub root: PathBuf,
+    /// Changes to files that aren't ignored.
+    pub changes: Vec<FsChange>,
+    /// Git status may be stale: the worktree or the repository's refs
+    /// or index changed.
+    pub git_changed: bool,
+}
+
+fn is_git_metadata(root: &Path, path: &Path) -> Option<bool> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut components = relative.components();
+    if components.next()?.as_os_str() != ".git" {
+        return None;
+    }
+    // Object writes alone don't change status; ref or index updates follow
+    let inner = components.next().map(|c| c.as_os_str().to_string_lossy().to_string());
+    Some(!matches!(inner.as_deref(), Some("objects") | Some("logs")) && !relative.ends_with("index.lock"))
+}
+
+fn filter_changes(root: &Path, rules: &mut IgnoreRules, changes: Vec<FsChange>) -> WorkspaceChanges {
+    let mut git_changed = false;
+    let mut visible = Vec::new();
+    for change in changes {
+        if let Some(relevant) = is_git_metadata(root, &change.path) {
+            git_changed |= relevant;
+            continue;
+        }
+        for path in change.paths() {
+            if matches!(path.file_name().and_then(|n| n.to_str()), Some(".gitignore") | Some(".ignore")) {
+                rules.invalidate(path);
+            }
+        }
+        // A rename stays visible if either side is
+        let ignored: Vec<PathBuf> = change.paths().filter(|p| rules.is_ignored(p)).cloned().collect();
+        if ignored.len() == change.paths().count() {
+            continue;
+        }
+        let change = match change.old_path {
+            Some(old_path) if ignored.contains(&old_path) => FsChange::new(FsChangeKind::Created, change.path),
+            Some(old_path) if ignored.contains(&change.path) => FsChange::new(FsChangeKind::Deleted, old_path),
+            _ => change,
+        };
+        visible.push(change);
+    }
+    git_changed |= !visible.is_empty();
+    WorkspaceChanges {
+        root: root.to_path_buf(),
+        changes: visible,
+        git_changed,
+    }
+}
+
+type Subscriber = Arc<Mutex<dyn FnMut(&WorkspaceChanges) + Send>>;
+type Watches = Mutex<HashMap<PathBuf, WorkspaceWatch>>;
+
+struct WorkspaceWatch {
+    _watcher: DebouncedWatcher,
+    subscribers: Arc<Mutex<HashMap<u64, Subscriber>>>,
+}
+
+/// Keeps a callback subscribed to a root; the root stops being watched
+/// once its last subscription drops.
+pub struct WorkspaceSubscription {
+    root: PathBuf,
+    id: u64,
+    watches: Weak<Watches>,
+}
+
+impl Drop for WorkspaceSubscription {
+    fn drop(&mut self) {
+        let Some(watches) = self.watches.upgrade() else {
+            return;
+        };
+        let mut watches = watches.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(watch) = watches.get(&self.root) else {
+            return;
+        };
+        let mut subscribers = watch.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.remove(&self.id);
+        if subscribers.is_empty() {
+            drop(subscribers);
+            watches.remove(&self.root);
+        }
+    }
+}
+
+/// One filtered watcher per workspace root, shared by everything that
+/// shows or indexes that workspace.
+#[derive(Default)]
+pub struct WorkspaceWatchers {
+    watches: Arc<Watches>,
+    /// `watch` calls per root, which share one subscription.
+    counted: Mutex<HashMap<PathBuf, (WorkspaceSubscription, usize)>>,
+    next_id: AtomicU64,
+}
+
+impl WorkspaceWatchers {
+    /// Calls `on_change` with each batch under `root` until the returned
+    /// subscription drops. The first subscriber's `debounce` applies.
+    pub fn subscribe(
+        &self,
+        root: &Path,
+        debounce: Duration,
+        on_change: impl FnMut(&WorkspaceChanges) + Send + 'static,
+    ) -> Result<WorkspaceSubscription, String> {
+        let root = root.canonicalize().map_err(|e| format!("Failed to open {}: {}", root.display(), e))?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let subscriber: Subscriber = Arc::new(Mutex::new(on_change));
+        let mut watches = self.watches.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(watch) = watches.get(&root) {
+            watch.subscribers.lock().unwrap_or_else(|e| e.into_inner()).insert(id, subscriber);
+        } else {
+            let subscribers = Arc::new(Mutex::new(HashMap::from([(id, subscriber)])));
+            let notified = subscribers.clone();
+            let mut rules = IgnoreRules::new(root.clone());
+            let watch_root = root.clone();
+            let watcher = DebouncedWatcher::watch(&root, debounce, move |changes| {
+                let changes = filter_changes(&watch_root, &mut rules, changes);
+                if !changes.git_changed {
+                    return;
+                }
+                // Called unlocked, so a callback may drop a subscription
+                let current: Vec<Subscriber> = notified.lock().unwrap_or_else(|e| e.into_inner()).values().cloned().collect();
+                for subscriber in current {
+                    let mut on_change = subscriber.lock().unwrap_or_else(|e| e.into_inner());
+                    (*on_change)(&changes);
+                }
+            })?;
+            watches.insert(
+                root.clone(),
+                WorkspaceWatch {
+                    _watcher: watcher,
+                    subscribers,
+                },
+            );
+        }
+        Ok(WorkspaceSubscription {
+            root,
+            id,
+            watches: Arc::downgrade(&self.watches),
+        })
+    }
+
+    /// Counted `subscribe` for callers that can't hold a subscription, like
+    /// the frontend. Returns the canonical root that events will carry.
+    /// Only the first caller's `on_change` is kept: later calls for the
+    /// same root just count towards `unwatch`, so callbacks must not differ
+    /// per caller.
+    pub fn watch(
+        &self,
+        root: &Path,
+        debounce: Duration,
+        on_change: impl FnMut(&WorkspaceChanges) + Send + 'static,
+    ) -> Result<PathBuf, String> {
+        let root = root.canonicalize().map_err(|e| format!("Failed to open {}: {}", root.display(), e))?;
+        let mut counted = self.counted.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, count)) = counted.get_mut(&root) {
+            *count += 1;
+            return Ok(root);
+        }
+        let subscription = self.subscribe(&root, debounce, on_change)?;
+        counted.insert(root.clone(), (subscription, 1));
+        Ok(root)
+    }
+
+    /// Returns false when `root` wasn't being watched.
+    pub fn unwatch(&self, root: &Path) -> bool {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let mut counted = self.counted.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((_, count)) = counted.get_mut(&root) else {
+            return false;
+        };
+        *count -= 1;
+        if *count == 0 {
+            counted.remove(&root);
+        }
+        true
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_watching(&self, root: &Path) -> bool {
+        self.watches.lock().unwrap_or_else(|e| e.into_inner()).contains_key(root)
+    }
+}
+
+/// Starts emitting `workspace-changed` for `root` and returns the root as
+/// the events spell it. Calls are counted, so pair each with
+/// `unwatch_workspace`.
+#[tauri::command]
+pub async fn watch_workspace(app: AppHandle, state: State<'_, CodexState>, root: String) -> Result<String, String> {
+    let root = expand_path(&root)?;
+    let root = state.workspace_watchers.watch(&root, DEFAULT_DEBOUNCE, move |changes| {
+        if let Err(e) = app.emit("workspace-changed", changes) {
+            log::error!("Failed to emit workspace changes: {}", e);
+        }
+    })?;
+    Ok(root.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn unwatch_workspace(state: State<'_, CodexState>, root: String) -> Result<bool, String> {
+    let root = expand_path(&root)?;
+    Ok(state.workspace_watchers.unwatch(&root))
+}
+
+/// Spells `path` the way `workspace-changed` events do.
+#[tauri::command]
+pub async fn canonicalize_path(path: String) -> Result<String, String> {
+    let expanded = expand_path(&path)?;
+    let canonical = expanded
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {}", expanded.display(), e))?;
+    Ok(canonical.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+    use tempfile::tempdir;
+
+    /// Collects batches until `done` holds for everything seen so far.
+    fn wait_for<T>(rx: &mpsc::Receiver<Vec<T>>, done: impl Fn(&[T]) -> bool) -> Vec<T> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut seen = Vec::new();
+        while !done(&seen) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(left) {
+                Ok(batch) => seen.extend(batch),
+                Err(_) => break,
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn test_changes_are_batched_and_settled() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("old.txt"), "x").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let _watcher = DebouncedWatcher::watch(&root, Duration::from_millis(100), move |changes| {
+            let _ = tx.send(changes);
+        })
+        .unwrap();
+
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        fs::write(root.join("gone.txt"), "").unwrap();
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        let batch = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(batch.contains(&FsChange::new(FsChangeKind::Created, root.join("a.txt"))));
+        assert!(batch.contains(&FsChange::new(FsChangeKind::Created, root.join("b.txt"))));
+        assert!(batch.iter().all(|c| c.path != root.join("gone.txt")));
+
+        fs::rename(root.join("old.txt"), root.join("new.txt")).unwrap();
+        let seen = wait_for(&rx, |seen| seen.iter().any(|c| c.path == root.join("new.txt")));
+        let renamed = seen.iter().find(|c| c.path == root.join("new.txt")).unwrap();
+        // Backends without rename tracking report the two halves instead
+        if renamed.kind == FsChangeKind::Renamed {
+            assert_eq!(renamed.old_path.as_deref(), Some(root.join("old.txt").as_path()));
+        } else {
+            assert_eq!(renamed.kind, FsChangeKind::Created);
+        }
+    }
+
+    #[test]
+    fn test_steady_events_still_flush() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let _watcher = DebouncedWatcher::watch_with_latency(
+            &root,
+            Duration::from_millis(200),
+            Duration::from_millis(500),
+            move |changes| {
+                let _ = tx.send(changes);
+            },
+        )
+        .unwrap();
+
+        // Writes every 20ms never leave the debounce period quiet
+        let started = Instant::now();
+        let mut delivered = None;
+        for i in 0..150 {
+            fs::write(root.join("busy.log"), i.to_string()).unwrap();
+            if let Ok(batch) = rx.try_recv() {
+                delivered = Some((batch, started.elapsed()));
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let (batch, after) = delivered.expect("no batch while events kept coming");
+        assert!(batch.iter().any(|c| c.path == root.join("busy.log")));
+        assert!(after < Duration::from_secs(2), "{:?}", after);
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("src/gen")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "gen/\n!keep.log\n").unwrap();
+
+        let mut rules = IgnoreRules::new(root.clone());
+        assert!(rules.is_ignored(&root.join("target/debug/app")));
+        assert!(rules.is_ignored(&root.join("build.log")));
+        assert!(rules.is_ignored(&root.join("src/gen/out.rs")));
+        assert!(!rules.is_ignored(&root.join("src/keep.log")));
+        assert!(!rules.is_ignored(&root.join("src/main.rs")));
+        assert!(rules.is_ignored(Path::new("/elsewhere/file")));
+
+        let changes = filter_changes(
+            &root,
+            &mut rules,
+            vec![
+                FsChange::new(FsChangeKind::Modified, root.join(".git/index")),
+                FsChange::new(FsChangeKind::Created, root.join("target/out")),
+            ],
+        );
+        assert!(changes.changes.is_empty());
+        assert!(changes.git_changed);
+
+        let changes = filter_changes(
+            &root,
+            &mut rules,
+            vec![FsChange {
+                kind: FsChangeKind::Renamed,
+                path: root.join("src/main.rs"),
+                old_path: Some(root.join("debug.log")),
+            }],
+        );
+        assert_eq!(changes.changes, vec![FsChange::new(FsChangeKind::Created, root.join("src/main.rs"))]);
+    }
+
+    #[test]
+    fn test_workspace_watch_is_shared_and_filtered() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+
+        let watchers = WorkspaceWatchers::default();
+        let (tx, rx) = mpsc::channel();
+        watchers
+            .watch(&root, Duration::from_millis(100), move |changes| {
+                let _ = tx.send(changes.changes.clone());
+            })
+            .unwrap();
+        watchers.watch(&root, Duration::from_millis(100), |_| {}).unwrap();
+
+        fs::write(root.join("target/out.o"), "").unwrap();
+        fs::write(root.join("main.rs"), "").unwrap();
+        let seen = wait_for(&rx, |seen| seen.iter().any(|c| c.path == root.join("main.rs")));
+        assert!(seen.iter().any(|c| c.path == root.join("main.rs")));
+        assert!(seen.iter().all(|c| !c.path.starts_with(root.join("target"))));
+
+        assert!(watchers.unwatch(&root));
+        assert!(watchers.unwatch(&root));
+        assert!(!watchers.unwatch(&root));
+        assert!(!watchers.is_watching(&root));
+    }
+}
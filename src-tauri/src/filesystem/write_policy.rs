@@ -0,0 +1,277 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::file_io::FileWriteError;
+use super::file_range::{sniff_content, ContentKind, TextEncoding};
+
+const POLICY_FILE: &str = "write_policy.toml";
+const SNIFF_BYTES: u64 = 8192;
+
+/// Paths the editor never writes, whatever the sandbox allows.
+const DEFAULT_DENY: &[&str] = &[
+    "**/.git/**",
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "id_rsa*",
+    "id_ed25519*",
+    "**/.ssh/**",
+    "**/.codex/auth.json",
+    "**/.codex/credentials.enc",
+    "**/.codex/accounts.*",
+];
+
+/// Templates checked into projects, exempt from the patterns above.
+const DEFAULT_ALLOW: &[&str] = &["*.example", "*.sample", "*.template"];
+
+/// Where `write_file` may write. Stored in `~/.codex/write_policy.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WritePolicy {
+    /// Globs of paths to refuse. Patterns without a `/` match file names
+    /// anywhere, e.g. `*.pem`.
+    #[serde(default = "default_deny")]
+    pub deny: Vec<String>,
+    /// Exceptions to `deny`, e.g. `.env.example` despite `.env.*`.
+    #[serde(default = "default_allow")]
+    pub allow: Vec<String>,
+}
+
+fn default_deny() -> Vec<String> {
+    DEFAULT_DENY.iter().map(|p| p.to_string()).collect()
+}
+
+fn default_allow() -> Vec<String> {
+    DEFAULT_ALLOW.iter().map(|p| p.to_string()).collect()
+}
+
+impl Default for WritePolicy {
+    fn default() -> Self {
+        Self {
+            deny: default_deny(),
+            allow: default_allow(),
+        }
+    }
+}
+
+impl WritePolicy {
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir().unwrap_or_default().join(".codex")
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(POLICY_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        glob_set(&self.deny)?;
+        glob_set(&self.allow)?;
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let content = toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize write policy: {}", e))?;
+        let path = dir.join(POLICY_FILE);
+        fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// The first deny pattern matching `path`, unless an allow pattern
+    /// matches it too.
+    pub fn denied_by(&self, path: &Path) -> Result<Option<&str>, String> {
+        let matches = glob_set(&self.deny)?.matches(path);
+        let Some(&first) = matches.first() else {
+            return Ok(None);
+        };
+        if glob_set(&self.allow)?.is_match(path) {
+            return Ok(None);
+        }
+        Ok(Some(self.deny[first].as_str()))
+    }
+}
+
+/// Patterns without a `/` match file names anywhere.
+fn glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let full = if pattern.contains('/') {
+            pattern.clone()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob = Glob::new(&full).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| format!("Invalid patterns: {}", e))
+}
+
+/// `path` with symlinks in its existing part resolved, so `..` and links
+/// can't step out of a trusted root.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => resolve(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Checks `path` against the deny list and, unless `confirmed`, requires
+/// it to lie inside one of `trusted_roots`.
+pub fn check_write_path(
+    policy: &WritePolicy,
+    path: &Path,
+    trusted_roots: &[PathBuf],
+    confirmed: bool,
+) -> Result<(), FileWriteError> {
+    let resolved = resolve(path);
+    let denied = policy
+        .denied_by(&resolved)
+        .map_err(|message| FileWriteError::NotAllowed { message })?;
+    if let Some(pattern) = denied {
+        return Err(FileWriteError::Denied {
+            path: resolved.to_string_lossy().to_string(),
+            pattern: pattern.to_string(),
+        });
+    }
+
+    let inside = trusted_roots.iter().any(|root| resolved.starts_with(resolve(root)));
+    if !inside && !confirmed {
+        return Err(FileWriteError::OutsideWorkspace {
+            path: resolved.to_string_lossy().to_string(),
+            trusted_roots: trusted_roots.iter().map(|r| r.to_string_lossy().to_string()).collect(),
+        });
+    }
+    Ok(())
+}
+
+/// Only UTF-8 text is written: the new content may not hold NUL bytes and
+/// an existing file must sniff as UTF-8.
+pub fn check_write_content(path: &Path, content: &str) -> Result<(), FileWriteError> {
+    if content.contains('\0') {
+        return Err(FileWriteError::NotAllowed {
+            message: "Content contains NUL bytes; only text files can be edited".to_string(),
+        });
+    }
+    let mut sample = Vec::new();
+    match fs::File::open(path) {
+        Ok(file) => {
+            file.take(SNIFF_BYTES).read_to_end(&mut sample)?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    }
+    match sniff_content(&sample) {
+        ContentKind::Text {
+            encoding: TextEncoding::Utf8,
+            ..
+        } => Ok(()),
+        ContentKind::Text { .. } => Err(FileWriteError::NotAllowed {
+            message: "Only UTF-8 text files can be edited".to_string(),
+        }),
+        ContentKind::Binary => Err(FileWriteError::NotAllowed {
+            message: "Binary files can't be edited".to_string(),
+        }),
+    }
+}
+
+#[tauri::command]
+pub async fn get_write_policy() -> Result<WritePolicy, String> {
+    WritePolicy::load(&WritePolicy::default_dir())
+}
+
+#[tauri::command]
+pub async fn save_write_policy(policy: WritePolicy) -> Result<(), String> {
+    policy.save(&WritePolicy::default_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_deny_list() {
+        let policy = WritePolicy::default();
+        let denied = |p: &str| policy.denied_by(Path::new(p)).unwrap().map(str::to_string);
+        assert_eq!(denied("/work/app/.env").as_deref(), Some(".env"));
+        assert_eq!(denied("/work/app/.git/config").as_deref(), Some("**/.git/**"));
+        assert_eq!(denied("/home/me/.ssh/config").as_deref(), Some("**/.ssh/**"));
+        assert_eq!(denied("/work/app/certs/server.pem").as_deref(), Some("*.pem"));
+        assert_eq!(denied("/work/app/src/env.rs"), None);
+        assert_eq!(denied("/work/app/.gitignore"), None);
+        assert_eq!(denied("/work/app/.env.production").as_deref(), Some(".env.*"));
+        assert_eq!(denied("/home/me/.codex/credentials.enc").as_deref(), Some("**/.codex/credentials.enc"));
+        assert_eq!(denied("/home/me/.codex/accounts.json").as_deref(), Some("**/.codex/accounts.*"));
+        assert_eq!(denied("/home/me/.codex/accounts.enc").as_deref(), Some("**/.codex/accounts.*"));
+        // Templates stay editable
+        assert_eq!(denied("/work/app/.env.example"), None);
+        assert_eq!(denied("/work/app/config/.env.sample"), None);
+
+        let dir = tempdir().unwrap();
+        let custom = WritePolicy {
+            deny: vec!["*.lock".to_string(), "/etc/**".to_string()],
+            allow: vec!["/etc/motd".to_string()],
+        };
+        custom.save(dir.path()).unwrap();
+        let loaded = WritePolicy::load(dir.path()).unwrap();
+        assert_eq!(loaded, custom);
+        assert!(loaded.denied_by(Path::new("/etc/hosts")).unwrap().is_some());
+        assert!(loaded.denied_by(Path::new("/etc/motd")).unwrap().is_none());
+        assert!(loaded.denied_by(Path::new("/work/.env")).unwrap().is_none());
+        let invalid = WritePolicy {
+            deny: vec!["[".to_string()],
+            ..WritePolicy::default()
+        };
+        assert!(invalid.save(dir.path()).is_err());
+        assert_eq!(WritePolicy::load(&dir.path().join("missing")).unwrap(), WritePolicy::default());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        let roots = vec![project.clone()];
+        let policy = WritePolicy::default();
+
+        assert!(check_write_path(&policy, &project.join("src/new.rs"), &roots, false).is_ok());
+        let escape = project.join("../outside.txt");
+        assert!(matches!(
+            check_write_path(&policy, &escape, &roots, false),
+            Err(FileWriteError::OutsideWorkspace { .. })
+        ));
+        assert!(check_write_path(&policy, &escape, &roots, true).is_ok());
+        // Confirmation doesn't override the deny list
+        assert!(matches!(
+            check_write_path(&policy, &project.join(".env"), &roots, true),
+            Err(FileWriteError::Denied { .. })
+        ));
+        assert!(check_write_path(&policy, &project.join(".env.example"), &roots, false).is_ok());
+    }
+
+    #[test]
+    fn test_content_sniffing() {
+        let dir = tempdir().unwrap();
+        let text = dir.path().join("Makefile");
+        fs::write(&text, "all:\n\techo hi\n").unwrap();
+        assert!(check_write_content(&text, "all:\n").is_ok());
+        assert!(check_write_content(&dir.path().join("new"), "hello").is_ok());
+        assert!(check_write_content(&text, "a\0b").is_err());
+
+        let binary = dir.path().join("data.txt");
+        fs::write(&binary, b"\x00\x01\x02\x03\xff").unwrap();
+        assert!(matches!(
+            check_write_content(&binary, "text"),
+            Err(FileWriteError::NotAllowed { .. })
+        ));
+        let latin1 = dir.path().join("old.txt");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+        assert!(check_write_content(&latin1, "cafe\n").is_err());
+    }
+}
@@ -3,32 +3,43 @@ mod codex_client;
 mod commands;
 mod config;
 mod filesystem;
+mod mcp;
 mod protocol;
+mod providers;
 mod services;
 mod state;
 mod utils;
 
 use commands::{
-    approve_execution, check_codex_version, close_session, delete_session_file,
+    approve_execution, check_codex_version, close_session, compact_session, delete_session_file, find_files,
     get_latest_session_id, get_running_sessions, get_session_files, read_session_file, read_history_file,
-    load_sessions_from_disk, pause_session, send_message, send_message_with_media, start_codex_session, stop_session,
+    load_sessions_from_disk, pause_session, resolve_effective_config, send_message, send_message_with_media, start_codex_session, stop_session,
+    probe_mcp_server, list_provider_models, test_model_provider, get_usage_report,
     // Authentication commands
-    get_auth_status, start_login_flow, login_with_api_key_command, logout_command, get_auth_token,
+    get_auth_status, start_login_flow, cancel_login_flow, start_device_login,
+    list_accounts, save_current_account, switch_account, remove_account,
+    encrypt_credentials, unlock_credentials, lock_credentials, login_with_api_key_command, logout_command, get_auth_token,
 };
 use config::{
     add_mcp_server, add_or_update_model_provider, add_or_update_profile, delete_mcp_server,
-    delete_profile, get_profile_config, get_project_name, get_provider_config, read_codex_config,
-    read_mcp_servers, read_model_providers, read_profiles, update_profile_model,
+    delete_profile, export_mcp_servers, get_profile_config, get_project_name, get_provider_config,
+    import_mcp_servers, read_codex_config, read_mcp_servers, read_model_providers, read_profiles,
+    update_profile_model,
 };
 use filesystem::{
-    directory_ops::{get_default_directories, read_directory},
-    file_analysis::calculate_file_tokens,
+    directory_ops::{get_default_directories, read_directory, read_directory_tree},
+    file_analysis::{calculate_directory_tokens, calculate_file_tokens, calculate_tokens_batch},
     file_io::{read_file, write_file},
+    file_range::{read_file_lines, read_file_range, stop_tail, stop_window_tails, tail_file},
     file_parsers::{csv::read_csv_content, pdf::read_pdf_content, xlsx::read_xlsx_content},
     git_diff::get_git_file_diff,
     git_status::get_git_status,
+    search::{cancel_search, search_workspace},
+    watcher::{canonicalize_path, unwatch_workspace, watch_workspace},
+    write_policy::{get_write_policy, save_write_policy},
 };
 use state::CodexState;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -52,6 +63,13 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(CodexState::new())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                let tails = window.state::<CodexState>().tails.clone();
+                let label = window.label().to_string();
+                tauri::async_runtime::spawn(async move { stop_window_tails(&tails, &label).await });
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             start_codex_session,
             send_message,
@@ -59,6 +77,8 @@ pub fn run() {
             approve_execution,
             stop_session,
             pause_session,
+            compact_session,
+            find_files,
             close_session,
             get_running_sessions,
             load_sessions_from_disk,
@@ -68,21 +88,39 @@ pub fn run() {
             read_session_file,
             read_history_file,
             check_codex_version,
+            resolve_effective_config,
             read_directory,
+            read_directory_tree,
             get_default_directories,
             calculate_file_tokens,
+            calculate_tokens_batch,
+            calculate_directory_tokens,
             read_file,
             write_file,
+            read_file_range,
+            read_file_lines,
+            tail_file,
+            stop_tail,
+            get_write_policy,
+            save_write_policy,
             read_pdf_content,
             read_csv_content,
             read_xlsx_content,
             get_git_file_diff,
             get_git_status,
+            search_workspace,
+            cancel_search,
+            watch_workspace,
+            unwatch_workspace,
+            canonicalize_path,
             read_codex_config,
             get_project_name,
             read_mcp_servers,
             add_mcp_server,
             delete_mcp_server,
+            import_mcp_servers,
+            export_mcp_servers,
+            probe_mcp_server,
             read_model_providers,
             read_profiles,
             get_provider_config,
@@ -91,12 +129,24 @@ pub fn run() {
             add_or_update_profile,
             delete_profile,
             add_or_update_model_provider,
+            list_provider_models,
+            test_model_provider,
+            get_usage_report,
             // Authentication commands
             get_auth_status,
             start_login_flow,
+            cancel_login_flow,
+            start_device_login,
             login_with_api_key_command,
             logout_command,
             get_auth_token,
+            list_accounts,
+            save_current_account,
+            switch_account,
+            remove_account,
+            encrypt_credentials,
+            unlock_credentials,
+            lock_credentials,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,401 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, Command};
+
+/// MCP protocol revision sent in the `initialize` request.
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// How much of a stdio server's stderr is kept for error reports.
+const STDERR_TAIL_BYTES: usize = 4096;
+
+#[derive(Error, Debug)]
+pub enum McpError {
+    #[error("Failed to start server: {0}")]
+    Spawn(std::io::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("HTTP {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+    #[error("Invalid JSON-RPC message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Server returned error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("Server closed the connection")]
+    Closed,
+    #[error("Timed out waiting for response to {0}")]
+    Timeout(String),
+}
+
+type StdioReader = Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>;
+type StdioWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+enum Transport {
+    Stdio {
+        reader: StdioReader,
+        writer: StdioWriter,
+        child: Option<Box<Child>>,
+    },
+    Http {
+        client: reqwest::Client,
+        url: String,
+        session_id: Option<String>,
+    },
+}
+
+/// Minimal JSON-RPC client for talking to an MCP server over stdio or
+/// streamable HTTP.
+pub struct McpClient {
+    transport: Transport,
+    next_id: u64,
+    timeout: Duration,
+    stderr_tail: Arc<Mutex<String>>,
+}
+
+impl McpClient {
+    /// Launches `command` and speaks newline-delimited JSON-RPC over its
+    /// stdin/stdout. The process is killed when the client is dropped.
+    pub fn spawn_stdio(
+        command: &str,
+        args: &[String],
+        env: Option<&HashMap<String, String>>,
+        cwd: Option<&str>,
+        timeout: Duration,
+    ) -> Result<Self, McpError> {
+        let mut cmd = Command::new(command);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(env) = env {
+            cmd.envs(env);
+        }
+
+        let mut child = cmd.spawn().map_err(McpError::Spawn)?;
+        let stdin = child.stdin.take().ok_or(McpError::Closed)?;
+        let stdout = child.stdout.take().ok_or(McpError::Closed)?;
+
+        let stderr_tail = Arc::new(Mutex::new(String::new()));
+        if let Some(mut stderr) = child.stderr.take() {
+            let tail = stderr_tail.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                while let Ok(n) = stderr.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    if let Ok(mut tail) = tail.lock() {
+                        tail.push_str(&String::from_utf8_lossy(&buf[..n]));
+                        if tail.len() > STDERR_TAIL_BYTES {
+                            let mut cut = tail.len() - STDERR_TAIL_BYTES;
+                            while !tail.is_char_boundary(cut) {
+                                cut += 1;
+                            }
+                            tail.drain(..cut);
+                        }
+                    }
+                }
+            });
+        }
+
+        let mut client = Self::from_stdio_parts(stdout, stdin, timeout);
+        client.stderr_tail = stderr_tail;
+        if let Transport::Stdio { child: slot, .. } = &mut client.transport {
+            *slot = Some(Box::new(child));
+        }
+        Ok(client)
+    }
+
+    /// Builds a stdio client over arbitrary streams (used by tests and
+    /// in-process servers).
+    pub fn from_stdio_parts<R, W>(reader: R, writer: W, timeout: Duration) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let reader: Box<dyn AsyncRead + Unpin + Send> = Box::new(reader);
+        Self {
+            transport: Transport::Stdio {
+                reader: BufReader::new(reader).lines(),
+                writer: Box::new(writer),
+                child: None,
+            },
+            next_id: 1,
+            timeout,
+            stderr_tail: Arc::new(Mutex::new(String::new())),
+        }
+    }
+
+    /// Connects to a streamable HTTP MCP endpoint, sending `headers` (e.g.
+    /// authorization) with every request.
+    pub fn connect_http(
+        url: &str,
+        headers: reqwest::header::HeaderMap,
+        timeout: Duration,
+    ) -> Result<Self, McpError> {
+        let client = reqwest::Client::builder().default_headers(headers).build()?;
+        Ok(Self {
+            transport: Transport::Http {
+                client,
+                url: url.to_string(),
+                session_id: None,
+            },
+            next_id: 1,
+            timeout,
+            stderr_tail: Arc::new(Mutex::new(String::new())),
+        })
+    }
+
+    /// Changes the per-request timeout, e.g. after the startup handshake.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Last few KB the server wrote to stderr (stdio servers only).
+    pub fn stderr_tail(&self) -> String {
+        self.stderr_tail
+            .lock()
+            .map(|tail| tail.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Performs the `initialize` handshake and returns the server's result.
+    pub async fn initialize(&mut self) -> Result<Value, McpError> {
+        let result = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "codexia",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            )
+            .await?;
+        self.notify("notifications/initialized", json!({})).await?;
+        Ok(result)
+    }
+
+    /// Calls a paginated `*/list` method and collects every page of `key`.
+    pub async fn list_all(&mut self, method: &str, key: &str) -> Result<Vec<Value>, McpError> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(c) => json!({ "cursor": c }),
+                None => json!({}),
+            };
+            let result = self.request(method, params).await?;
+            if let Some(page) = result.get(key).and_then(|v| v.as_array()) {
+                items.extend(page.iter().cloned());
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+
+        let response = tokio::time::timeout(self.timeout, self.exchange(message, Some(id)))
+            .await
+            .map_err(|_| McpError::Timeout(method.to_string()))??;
+        let response = response.ok_or(McpError::Closed)?;
+
+        if let Some(error) = response.get("error") {
+            return Err(McpError::Rpc {
+                code: error.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+            });
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    pub async fn notify(&mut self, method: &str, params: Value) -> Result<(), McpError> {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        });
+        tokio::time::timeout(self.timeout, self.exchange(message, None))
+            .await
+            .map_err(|_| McpError::Timeout(method.to_string()))??;
+        Ok(())
+    }
+
+    /// Sends `message` and, when `id` is set, waits for the matching response.
+    async fn exchange(&mut self, message: Value, id: Option<u64>) -> Result<Option<Value>, McpError> {
+        match &mut self.transport {
+            Transport::Stdio { reader, writer, .. } => {
+                let mut line = serde_json::to_string(&message)?;
+                line.push('\n');
+                writer.write_all(line.as_bytes()).await?;
+                writer.flush().await?;
+
+                let Some(id) = id else {
+                    return Ok(None);
+                };
+                loop {
+                    let line = reader.next_line().await?.ok_or(McpError::Closed)?;
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let msg: Value = match serde_json::from_str(line) {
+                        Ok(msg) => msg,
+                        Err(_) => {
+                            log::debug!("Ignoring non JSON-RPC output from MCP server: {}", line);
+                            continue;
+                        }
+                    };
+                    if is_response_to(&msg, id) {
+                        return Ok(Some(msg));
+                    }
+                    if let (Some(request_id), Some(method)) = (msg.get("id"), msg.get("method")) {
+                        // We don't expose any client capabilities, so reject
+                        // server-initiated requests instead of leaving them hanging.
+                        log::debug!("Rejecting server request {}", method);
+                        let reply = json!({
+                            "jsonrpc": "2.0",
+                            "id": request_id,
+                            "error": { "code": -32601, "message": "Method not found" },
+                        });
+                        let mut reply = serde_json::to_string(&reply)?;
+                        reply.push('\n');
+                        writer.write_all(reply.as_bytes()).await?;
+                        writer.flush().await?;
+                    }
+                }
+            }
+            Transport::Http {
+                client,
+                url,
+                session_id,
+            } => {
+                let mut req = client
+                    .post(url.as_str())
+                    .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+                    .json(&message);
+                if let Some(sid) = session_id.as_deref() {
+                    req = req.header("Mcp-Session-Id", sid);
+                }
+
+                let response = req.send().await?;
+                if let Some(sid) = response
+                    .headers()
+                    .get("mcp-session-id")
+                    .and_then(|v| v.to_str().ok())
+                {
+                    *session_id = Some(sid.to_string());
+                }
+                let status = response.status();
+                let is_sse = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|ct| ct.starts_with("text/event-stream"));
+                let body = response.text().await?;
+
+                if !status.is_success() {
+                    return Err(McpError::HttpStatus {
+                        status: status.as_u16(),
+                        body: body.chars().take(500).collect(),
+                    });
+                }
+                let Some(id) = id else {
+                    return Ok(None);
+                };
+
+                let messages = if is_sse {
+                    parse_sse_messages(&body)
+                } else {
+                    match serde_json::from_str::<Value>(&body)? {
+                        Value::Array(batch) => batch,
+                        msg => vec![msg],
+                    }
+                };
+                Ok(messages.into_iter().find(|msg| is_response_to(msg, id)))
+            }
+        }
+    }
+
+    /// Terminates a spawned stdio server; HTTP sessions are simply dropped.
+    pub async fn shutdown(mut self) {
+        if let Transport::Stdio {
+            child: Some(child), ..
+        } = &mut self.transport
+        {
+            if let Err(e) = child.kill().await {
+                log::debug!("Failed to kill MCP server process: {}", e);
+            }
+        }
+    }
+}
+
+fn is_response_to(msg: &Value, id: u64) -> bool {
+    msg.get("id").and_then(|v| v.as_u64()) == Some(id)
+        && (msg.get("result").is_some() || msg.get("error").is_some())
+}
+
+/// Extracts the JSON payloads from the `data:` lines of an SSE body.
+fn parse_sse_messages(body: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    let mut data = String::new();
+    for line in body.lines().chain(std::iter::once("")) {
+        if let Some(rest) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(rest.trim_start());
+        } else if line.trim().is_empty() && !data.is_empty() {
+            if let Ok(msg) = serde_json::from_str(&data) {
+                messages.push(msg);
+            }
+            data.clear();
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sse_messages() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}\n\n: keep-alive\n\ndata: {\"jsonrpc\":\"2.0\",\ndata: \"method\":\"ping\"}\n";
+        let messages = parse_sse_messages(body);
+        assert_eq!(messages.len(), 2);
+        assert!(is_response_to(&messages[0], 1));
+        assert_eq!(messages[1]["method"], "ping");
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::{McpServerConfig, McpServerOptions};
+
+/// One server entry in the `{"mcpServers": {...}}` JSON layout shared by
+/// most editors and agents.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct JsonMcpServer {
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    transport: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    headers: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    disabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedMcpServer {
+    pub name: String,
+    pub config: McpServerConfig,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedMcpServer {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Parses a JSON MCP config file. Entries that can't be represented in
+/// `config.toml` are returned as skipped rather than failing the import.
+pub fn parse_mcp_json(content: &str) -> Result<(Vec<ImportedMcpServer>, Vec<SkippedMcpServer>), String> {
+    let root: Value = serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?;
+    // Claude Desktop/Cursor use `mcpServers`, VS Code uses `servers`
+    let servers = root
+        .get("mcpServers")
+        .or_else(|| root.get("servers"))
+        .or_else(|| root.get("mcp").and_then(|m| m.get("servers")))
+        .and_then(|s| s.as_object())
+        .ok_or("No \"mcpServers\" or \"servers\" object found")?;
+
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    for (name, entry) in servers {
+        let parsed = serde_json::from_value::<JsonMcpServer>(entry.clone())
+            .map_err(|e| format!("Unrecognized entry: {}", e))
+            .and_then(convert_server);
+        match parsed {
+            Ok((config, warnings)) => match config.validate() {
+                Ok(()) => imported.push(ImportedMcpServer {
+                    name: name.clone(),
+                    config,
+                    warnings,
+                }),
+                Err(reason) => skipped.push(SkippedMcpServer {
+                    name: name.clone(),
+                    reason,
+                }),
+            },
+            Err(reason) => skipped.push(SkippedMcpServer {
+                name: name.clone(),
+                reason,
+            }),
+        }
+    }
+    imported.sort_by(|a, b| a.name.cmp(&b.name));
+    skipped.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((imported, skipped))
+}
+
+fn convert_server(server: JsonMcpServer) -> Result<(McpServerConfig, Vec<String>), String> {
+    let mut warnings = Vec::new();
+    let options = McpServerOptions {
+        enabled: !server.disabled,
+        ..Default::default()
+    };
+
+    if let Some(command) = server.command {
+        if server
+            .env
+            .iter()
+            .flatten()
+            .any(|(_, value)| env_placeholder(value).is_some())
+        {
+            warnings.push("Environment values with ${...} placeholders are passed through literally".to_string());
+        }
+        return Ok((
+            McpServerConfig::Stdio {
+                command,
+                args: server.args,
+                env: server.env,
+                cwd: server.cwd,
+                options,
+            },
+            warnings,
+        ));
+    }
+
+    let url = server.url.ok_or("Entry has neither \"command\" nor \"url\"")?;
+    if server.transport.as_deref() == Some("sse") {
+        warnings.push("SSE transport imported as streamable HTTP".to_string());
+    }
+
+    let mut bearer_token_env_var = None;
+    let mut http_headers = HashMap::new();
+    let mut env_http_headers = HashMap::new();
+    for (header, value) in server.headers.unwrap_or_default() {
+        if header.eq_ignore_ascii_case("authorization") {
+            match value.strip_prefix("Bearer ").and_then(env_placeholder) {
+                Some(var) => bearer_token_env_var = Some(var.to_string()),
+                None => match env_placeholder(&value) {
+                    Some(var) => {
+                        env_http_headers.insert(header, var.to_string());
+                    }
+                    None => warnings.push(
+                        "Dropped plaintext Authorization header; set bearer_token_env_var instead".to_string(),
+                    ),
+                },
+            }
+        } else if let Some(var) = env_placeholder(&value) {
+            env_http_headers.insert(header, var.to_string());
+        } else {
+            http_headers.insert(header, value);
+        }
+    }
+
+    Ok((
+        McpServerConfig::Http {
+            url,
+            bearer_token_env_var,
+            http_headers: Some(http_headers).filter(|h| !h.is_empty()),
+            env_http_headers: Some(env_http_headers).filter(|h| !h.is_empty()),
+            options,
+        },
+        warnings,
+    ))
+}
+
+/// Returns `VAR` for values of the form `${VAR}` or `${env:VAR}`.
+fn env_placeholder(value: &str) -> Option<&str> {
+    let inner = value.trim().strip_prefix("${")?.strip_suffix('}')?;
+    let var = inner.strip_prefix("env:").unwrap_or(inner);
+    let valid = !var.is_empty() && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(var)
+}
+
+/// Renders servers in the `{"mcpServers": {...}}` layout. Secrets are written
+/// as `${VAR}` placeholders, never resolved.
+pub fn to_mcp_json(servers: &HashMap<String, McpServerConfig>) -> Value {
+    let entries: BTreeMap<&String, JsonMcpServer> = servers
+        .iter()
+        .map(|(name, config)| {
+            let disabled = !config.options().enabled;
+            let entry = match config {
+                McpServerConfig::Stdio {
+                    command,
+                    args,
+                    env,
+                    cwd,
+                    ..
+                } => JsonMcpServer {
+                    command: Some(command.clone()),
+                    args: args.clone(),
+                    env: env.clone(),
+                    cwd: cwd.clone(),
+                    disabled,
+                    ..Default::default()
+                },
+                McpServerConfig::Http {
+                    url,
+                    bearer_token_env_var,
+                    http_headers,
+                    env_http_headers,
+                    ..
+                } => {
+                    let mut headers: BTreeMap<String, String> = http_headers
+                        .iter()
+                        .flatten()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect();
+                    for (header, var) in env_http_headers.iter().flatten() {
+                        headers.insert(header.clone(), format!("${{{}}}", var));
+                    }
+                    if let Some(var) = bearer_token_env_var {
+                        headers.insert("Authorization".to_string(), format!("Bearer ${{{}}}", var));
+                    }
+                    JsonMcpServer {
+                        transport: Some("http".to_string()),
+                        url: Some(url.clone()),
+                        headers: Some(headers).filter(|h| !h.is_empty()),
+                        disabled,
+                        ..Default::default()
+                    }
+                }
+            };
+            (name, entry)
+        })
+        .collect();
+    json!({ "mcpServers": entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mcp_json() {
+        let content = r#"{
+            "mcpServers": {
+                "files": { "command": "npx", "args": ["-y", "fs-server"], "env": { "ROOT": "/tmp" } },
+                "remote": {
+                    "type": "http",
+                    "url": "https://mcp.example.com/mcp",
+                    "headers": { "Authorization": "Bearer ${env:EXAMPLE_TOKEN}", "X-Org": "${EXAMPLE_ORG}", "X-Client": "codexia" }
+                },
+                "leaky": { "url": "https://mcp.example.com/mcp", "headers": { "Authorization": "Bearer sk-123" } },
+                "broken": { "args": ["nothing to run"] }
+            }
+        }"#;
+        let (imported, skipped) = parse_mcp_json(content).unwrap();
+        let names: Vec<&str> = imported.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["files", "leaky", "remote"]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].name, "broken");
+
+        match &imported[2].config {
+            McpServerConfig::Http {
+                bearer_token_env_var,
+                http_headers,
+                env_http_headers,
+                ..
+            } => {
+                assert_eq!(bearer_token_env_var.as_deref(), Some("EXAMPLE_TOKEN"));
+                assert_eq!(http_headers.as_ref().unwrap()["X-Client"], "codexia");
+                assert_eq!(env_http_headers.as_ref().unwrap()["X-Org"], "EXAMPLE_ORG");
+            }
+            other => panic!("expected http server, got {:?}", other),
+        }
+        assert_eq!(imported[1].warnings.len(), 1);
+        assert!(matches!(&imported[1].config, McpServerConfig::Http { http_headers: None, .. }));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let content = r#"{"servers": {
+            "remote": { "url": "https://mcp.example.com/mcp", "headers": { "Authorization": "Bearer ${TOKEN}" }, "disabled": true },
+            "local": { "command": "server", "args": ["--stdio"] }
+        }}"#;
+        let (imported, _) = parse_mcp_json(content).unwrap();
+        let servers: HashMap<String, McpServerConfig> =
+            imported.into_iter().map(|s| (s.name, s.config)).collect();
+
+        let exported = to_mcp_json(&servers);
+        assert_eq!(exported["mcpServers"]["remote"]["headers"]["Authorization"], "Bearer ${TOKEN}");
+        assert_eq!(exported["mcpServers"]["remote"]["disabled"], true);
+
+        let (reimported, skipped) = parse_mcp_json(&exported.to_string()).unwrap();
+        assert!(skipped.is_empty());
+        let reimported: HashMap<String, McpServerConfig> =
+            reimported.into_iter().map(|s| (s.name, s.config)).collect();
+        assert_eq!(reimported, servers);
+    }
+}
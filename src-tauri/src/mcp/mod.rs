@@ -0,0 +1,5 @@
+pub mod client;
+pub mod interop;
+pub mod probe;
+
+pub use probe::{probe_server, McpProbeReport};
@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+use crate::config::McpServerConfig;
+use crate::mcp::client::{McpClient, McpError};
+
+/// Startup timeout used when the server entry doesn't set one.
+const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Per-request timeout used when the server entry doesn't set one.
+const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpToolInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub input_schema: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpPromptInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResourceInfo {
+    pub uri: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+/// Result of starting an MCP server and enumerating what it offers.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct McpProbeReport {
+    pub name: String,
+    pub ok: bool,
+    pub server_name: Option<String>,
+    pub server_version: Option<String>,
+    pub protocol_version: Option<String>,
+    pub tools: Vec<McpToolInfo>,
+    pub prompts: Vec<McpPromptInfo>,
+    pub resources: Vec<McpResourceInfo>,
+    /// Time from launch/connect until `initialize` returned.
+    pub initialize_ms: Option<u64>,
+    pub total_ms: u64,
+    pub error: Option<String>,
+    /// Tail of the server's stderr, captured for stdio servers on failure.
+    pub stderr: Option<String>,
+}
+
+pub async fn probe_server(name: &str, config: &McpServerConfig) -> McpProbeReport {
+    let started = Instant::now();
+    let mut report = McpProbeReport {
+        name: name.to_string(),
+        ..Default::default()
+    };
+
+    let options = config.options();
+    let startup_timeout = options
+        .startup_timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_STARTUP_TIMEOUT);
+    let tool_timeout = options
+        .tool_timeout_sec
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .unwrap_or(DEFAULT_TOOL_TIMEOUT);
+
+    let client = match config {
+        McpServerConfig::Stdio {
+            command,
+            args,
+            env,
+            cwd,
+            ..
+        } => McpClient::spawn_stdio(command, args, env.as_ref(), cwd.as_deref(), startup_timeout)
+            .map_err(|e| e.to_string()),
+        McpServerConfig::Http { url, .. } => resolve_http_headers(config)
+            .and_then(|headers| McpClient::connect_http(url, headers, startup_timeout).map_err(|e| e.to_string())),
+    };
+
+    match client {
+        Ok(mut client) => {
+            if let Err(e) = run_probe(&mut client, &mut report, started, tool_timeout).await {
+                report.error = Some(e.to_string());
+                let stderr = client.stderr_tail();
+                if !stderr.is_empty() {
+                    report.stderr = Some(stderr);
+                }
+            } else {
+                report.ok = true;
+            }
+            client.shutdown().await;
+        }
+        Err(e) => report.error = Some(e),
+    }
+
+    report.total_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+pub(crate) async fn run_probe(
+    client: &mut McpClient,
+    report: &mut McpProbeReport,
+    started: Instant,
+    tool_timeout: Duration,
+) -> Result<(), McpError> {
+    let init = client.initialize().await?;
+    report.initialize_ms = Some(started.elapsed().as_millis() as u64);
+    client.set_timeout(tool_timeout);
+    report.protocol_version = str_field(&init, "protocolVersion");
+    if let Some(info) = init.get("serverInfo") {
+        report.server_name = str_field(info, "name");
+        report.server_version = str_field(info, "version");
+    }
+
+    // Only ask for what the server advertises; many servers reject unknown methods.
+    let capabilities = init.get("capabilities").cloned().unwrap_or(Value::Null);
+
+    if capabilities.get("tools").is_some() {
+        report.tools = client
+            .list_all("tools/list", "tools")
+            .await?
+            .iter()
+            .map(|tool| McpToolInfo {
+                name: str_field(tool, "name").unwrap_or_default(),
+                description: str_field(tool, "description"),
+                input_schema: tool.get("inputSchema").cloned().unwrap_or(Value::Null),
+            })
+            .collect();
+    }
+
+    if capabilities.get("prompts").is_some() {
+        report.prompts = client
+            .list_all("prompts/list", "prompts")
+            .await?
+            .iter()
+            .map(|prompt| McpPromptInfo {
+                name: str_field(prompt, "name").unwrap_or_default(),
+                description: str_field(prompt, "description"),
+                arguments: prompt
+                    .get("arguments")
+                    .and_then(|a| a.as_array())
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect();
+    }
+
+    if capabilities.get("resources").is_some() {
+        report.resources = client
+            .list_all("resources/list", "resources")
+            .await?
+            .iter()
+            .map(|resource| McpResourceInfo {
+                uri: str_field(resource, "uri").unwrap_or_default(),
+                name: str_field(resource, "name"),
+                description: str_field(resource, "description"),
+                mime_type: str_field(resource, "mimeType"),
+            })
+            .collect();
+    }
+
+    Ok(())
+}
+
+/// Builds request headers for an HTTP server, reading secrets from the
+/// environment variables the entry references.
+fn resolve_http_headers(config: &McpServerConfig) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    let McpServerConfig::Http {
+        bearer_token_env_var,
+        http_headers,
+        env_http_headers,
+        ..
+    } = config
+    else {
+        return Ok(headers);
+    };
+
+    let mut insert = |name: &str, value: &str| -> Result<(), String> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("Invalid header name '{}': {}", name, e))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| format!("Invalid value for header '{}': {}", name, e))?;
+        headers.insert(name, value);
+        Ok(())
+    };
+
+    for (name, value) in http_headers.iter().flatten() {
+        insert(name, value)?;
+    }
+    for (name, var) in env_http_headers.iter().flatten() {
+        match std::env::var(var) {
+            Ok(value) if !value.is_empty() => insert(name, &value)?,
+            _ => log::warn!("Environment variable {} for header {} is not set", var, name),
+        }
+    }
+    if let Some(var) = bearer_token_env_var {
+        let token = std::env::var(var)
+            .ok()
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| format!("Environment variable {} is not set", var))?;
+        insert("Authorization", &format!("Bearer {}", token))?;
+    }
+    Ok(headers)
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::McpServerOptions;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// Answers the subset of MCP requests a probe sends.
+    fn stub_response(request: &Value) -> Option<Value> {
+        let id = request.get("id")?.clone();
+        let result = match request["method"].as_str()? {
+            "initialize" => json!({
+                "protocolVersion": "2025-03-26",
+                "serverInfo": { "name": "stub", "version": "1.2.3" },
+                "capabilities": { "tools": {}, "resources": {} },
+            }),
+            "tools/list" if request["params"].get("cursor").is_none() => json!({
+                "tools": [{ "name": "echo", "inputSchema": { "type": "object" } }],
+                "nextCursor": "page2",
+            }),
+            "tools/list" => json!({
+                "tools": [{ "name": "add", "description": "Adds numbers", "inputSchema": {} }],
+            }),
+            "resources/list" => json!({
+                "resources": [{ "uri": "file:///readme.md", "name": "readme", "mimeType": "text/markdown" }],
+            }),
+            _ => {
+                return Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": "Method not found" },
+                }))
+            }
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    fn assert_stub_report(report: &McpProbeReport) {
+        assert_eq!(report.server_name.as_deref(), Some("stub"));
+        assert_eq!(report.server_version.as_deref(), Some("1.2.3"));
+        let tools: Vec<&str> = report.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tools, vec!["echo", "add"]);
+        assert!(report.prompts.is_empty());
+        assert_eq!(report.resources[0].mime_type.as_deref(), Some("text/markdown"));
+        assert!(report.initialize_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_probe_stdio_stub() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (server_read, mut server_write) = tokio::io::split(server_io);
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(server_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let request: Value = serde_json::from_str(&line).unwrap();
+                if let Some(response) = stub_response(&request) {
+                    let mut out = serde_json::to_string(&response).unwrap();
+                    out.push('\n');
+                    server_write.write_all(out.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        let (client_read, client_write) = tokio::io::split(client_io);
+        let mut client = McpClient::from_stdio_parts(client_read, client_write, DEFAULT_STARTUP_TIMEOUT);
+        let mut report = McpProbeReport::default();
+        run_probe(&mut client, &mut report, Instant::now(), DEFAULT_TOOL_TIMEOUT)
+            .await
+            .unwrap();
+        assert_stub_report(&report);
+    }
+
+    #[tokio::test]
+    async fn test_probe_http_stub() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                assert!(request.headers().iter().any(|h| h.field.equiv("X-Probe")));
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let message: Value = serde_json::from_str(&body).unwrap();
+                let response = match stub_response(&message) {
+                    // Answer over SSE to exercise the event-stream path
+                    Some(response) => tiny_http::Response::from_string(format!(
+                        "event: message\ndata: {}\n\n",
+                        response
+                    ))
+                    .with_header(
+                        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/event-stream"[..])
+                            .unwrap(),
+                    ),
+                    None => tiny_http::Response::from_string("").with_status_code(202),
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        let config = McpServerConfig::Http {
+            url: format!("http://{}/mcp", addr),
+            bearer_token_env_var: None,
+            http_headers: Some(HashMap::from([("X-Probe".to_string(), "1".to_string())])),
+            env_http_headers: None,
+            options: McpServerOptions::default(),
+        };
+        let report = probe_server("stub", &config).await;
+        assert!(report.ok, "probe failed: {:?}", report.error);
+        assert_stub_report(&report);
+    }
+
+    #[test]
+    fn test_missing_bearer_token_env_var() {
+        let config = McpServerConfig::Http {
+            url: "http://127.0.0.1:1/mcp".to_string(),
+            bearer_token_env_var: Some("CODEXIA_TEST_UNSET_MCP_TOKEN".to_string()),
+            http_headers: None,
+            env_http_headers: None,
+            options: McpServerOptions::default(),
+        };
+        let err = resolve_http_headers(&config).unwrap_err();
+        assert!(err.contains("CODEXIA_TEST_UNSET_MCP_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_spawn_failure() {
+        let config = McpServerConfig::Stdio {
+            command: "codexia-nonexistent-mcp-server".to_string(),
+            args: vec![],
+            env: None,
+            cwd: None,
+            options: McpServerOptions::default(),
+        };
+        let report = probe_server("missing", &config).await;
+        assert!(!report.ok);
+        assert!(report.error.unwrap().starts_with("Failed to start server"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::auth::credential_resolver::CredentialSource;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -27,6 +28,8 @@ pub enum Op {
         items: Vec<InputItem>,
     },
     Interrupt,
+    /// Summarizes the conversation so far to free context; newer CLIs only.
+    Compact,
     ExecApproval {
         id: String,
         decision: String,
@@ -207,4 +210,12 @@ pub struct CodexConfig {
     pub sandbox_mode: String,
     pub codex_path: Option<String>,
     pub api_key: Option<String>,
+    /// Named profile from `config.toml` to launch with (`--profile <name>`).
+    /// Non-empty `provider`, `model`, `approval_policy` and `sandbox_mode`
+    /// values are layered on top of it as per-session overrides.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Where to get this session's API key. Defaults to `api_key` when set.
+    #[serde(default)]
+    pub credential_source: Option<CredentialSource>,
 }
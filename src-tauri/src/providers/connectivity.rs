@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use super::endpoint::{EndpointKind, ProviderEndpoint};
+use super::models::ollama_root;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a provider test failed, coarse enough for the UI to suggest a fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderFailure {
+    Dns,
+    Tls,
+    ConnectionRefused,
+    Timeout,
+    MissingApiKey,
+    Unauthorized,
+    Forbidden,
+    /// The base URL doesn't serve the expected API.
+    EndpointNotFound,
+    ModelNotFound,
+    RateLimited,
+    ServerError,
+    InvalidResponse,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderTestReport {
+    pub provider: String,
+    pub base_url: String,
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub failure: Option<ProviderFailure>,
+    pub message: String,
+    /// Where the API key came from: "argument", an env var name, or none.
+    pub key_source: Option<String>,
+}
+
+/// Makes one cheap authenticated request against `endpoint` (listing its
+/// models, which must include `model` when given) and classifies the outcome.
+pub async fn test_endpoint(
+    endpoint: &ProviderEndpoint,
+    model: Option<&str>,
+    key_source: Option<String>,
+) -> ProviderTestReport {
+    let started = Instant::now();
+    let mut report = ProviderTestReport {
+        provider: endpoint.name.clone(),
+        base_url: endpoint.base_url.clone(),
+        ok: false,
+        status: None,
+        latency_ms: 0,
+        failure: None,
+        message: String::new(),
+        key_source,
+    };
+
+    let (failure, message) = match run_test(endpoint, model, &mut report).await {
+        Ok(()) => (None, "Connection successful".to_string()),
+        Err((failure, message)) => (Some(failure), message),
+    };
+    report.ok = failure.is_none();
+    report.failure = failure;
+    report.message = message;
+    report.latency_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+async fn run_test(
+    endpoint: &ProviderEndpoint,
+    model: Option<&str>,
+    report: &mut ProviderTestReport,
+) -> Result<(), (ProviderFailure, String)> {
+    let client = reqwest::Client::builder()
+        .timeout(TEST_TIMEOUT)
+        .build()
+        .map_err(|e| (ProviderFailure::Other, e.to_string()))?;
+
+    // Not `/models/{id}`: many compatible servers lack it and ids like
+    // `openai/gpt-4o` contain slashes
+    let url = match endpoint.kind {
+        EndpointKind::OpenAi => format!("{}/models", endpoint.base_url),
+        EndpointKind::Ollama => format!("{}/api/tags", ollama_root(&endpoint.base_url)),
+    };
+
+    let mut request = client.get(&url);
+    if let Some(key) = &endpoint.api_key {
+        request = request.bearer_auth(key);
+    }
+    let response = request.send().await.map_err(|e| classify_request_error(&e))?;
+
+    let status = response.status();
+    report.status = Some(status.as_u16());
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let detail: String = body.chars().take(200).collect();
+        let failure = match status.as_u16() {
+            401 if endpoint.api_key.is_none() => ProviderFailure::MissingApiKey,
+            401 => ProviderFailure::Unauthorized,
+            403 => ProviderFailure::Forbidden,
+            404 => ProviderFailure::EndpointNotFound,
+            429 => ProviderFailure::RateLimited,
+            500..=599 => ProviderFailure::ServerError,
+            _ => ProviderFailure::Other,
+        };
+        let message = match failure {
+            ProviderFailure::MissingApiKey => match &endpoint.env_key {
+                Some(var) => format!("No API key found; set {} or provide a key", var),
+                None => "No API key configured for this provider".to_string(),
+            },
+            ProviderFailure::EndpointNotFound => {
+                format!("{} does not look like an OpenAI-compatible API (404)", endpoint.base_url)
+            }
+            _ => format!("HTTP {}: {}", status, detail),
+        };
+        return Err((failure, message));
+    }
+
+    let Some(model) = model else {
+        return Ok(());
+    };
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| (ProviderFailure::InvalidResponse, e.to_string()))?;
+    let (list, field) = match endpoint.kind {
+        EndpointKind::OpenAi => ("data", "id"),
+        EndpointKind::Ollama => ("models", "name"),
+    };
+    let found = body
+        .get(list)
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get(field).and_then(|n| n.as_str()))
+        .any(|name| name == model || name.strip_suffix(":latest") == Some(model));
+    if !found {
+        let message = match endpoint.kind {
+            EndpointKind::OpenAi => format!("Model '{}' not found at {}", model, endpoint.base_url),
+            EndpointKind::Ollama => format!("Model '{}' is not pulled in Ollama", model),
+        };
+        return Err((ProviderFailure::ModelNotFound, message));
+    }
+
+    Ok(())
+}
+
+fn classify_request_error(error: &reqwest::Error) -> (ProviderFailure, String) {
+    // reqwest wraps the underlying hyper/io error; its text is the most
+    // reliable way to tell DNS, TLS and refused connections apart.
+    let mut chain = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(err) = source {
+        chain.push_str(": ");
+        chain.push_str(&err.to_string());
+        source = err.source();
+    }
+
+    let failure = if error.is_timeout() {
+        ProviderFailure::Timeout
+    } else {
+        classify_error_chain(&chain)
+    };
+    (failure, chain)
+}
+
+fn classify_error_chain(chain: &str) -> ProviderFailure {
+    let lower = chain.to_lowercase();
+    if lower.contains("dns error")
+        || lower.contains("failed to lookup address")
+        || lower.contains("name or service not known")
+        || lower.contains("no such host")
+    {
+        ProviderFailure::Dns
+    } else if lower.contains("certificate") || lower.contains("tls") || lower.contains("ssl") {
+        ProviderFailure::Tls
+    } else if lower.contains("connection refused") {
+        ProviderFailure::ConnectionRefused
+    } else if lower.contains("timed out") {
+        ProviderFailure::Timeout
+    } else {
+        ProviderFailure::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn mock_provider() -> SocketAddr {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let auth = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Authorization"))
+                    .map(|h| h.value.as_str().to_string());
+                let status = match (auth.as_deref(), request.url()) {
+                    (Some("Bearer busy"), _) => 429,
+                    (Some("Bearer good"), "/v1/models") => 200,
+                    (Some("Bearer good"), _) => 404,
+                    _ => 401,
+                };
+                let body = r#"{"data": [{"id": "gpt-4.1"}, {"id": "openai/gpt-4o"}]}"#;
+                let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(status));
+            }
+        });
+        addr
+    }
+
+    fn endpoint(base_url: String, api_key: Option<&str>) -> ProviderEndpoint {
+        ProviderEndpoint {
+            name: "mock".to_string(),
+            kind: EndpointKind::OpenAi,
+            base_url,
+            env_key: Some("MOCK_API_KEY".to_string()),
+            api_key: api_key.map(|k| k.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_classification() {
+        let base = format!("http://{}/v1", mock_provider());
+        let cases = [
+            (Some("good"), None, None),
+            (Some("good"), Some("gpt-4.1"), None),
+            (Some("good"), Some("openai/gpt-4o"), None),
+            (Some("good"), Some("missing"), Some(ProviderFailure::ModelNotFound)),
+            (Some("busy"), None, Some(ProviderFailure::RateLimited)),
+            (Some("bad"), None, Some(ProviderFailure::Unauthorized)),
+            (None, None, Some(ProviderFailure::MissingApiKey)),
+        ];
+        for (key, model, expected) in cases {
+            let report = test_endpoint(&endpoint(base.clone(), key), model, None).await;
+            assert_eq!(report.failure, expected, "key={:?} model={:?}: {}", key, model, report.message);
+            assert_eq!(report.ok, expected.is_none());
+        }
+
+        // A wrong base URL is the endpoint's fault, not the model's
+        for model in [None, Some("gpt-4.1")] {
+            let report = test_endpoint(&endpoint(format!("{}/wrong", base), Some("good")), model, None).await;
+            assert_eq!(report.failure, Some(ProviderFailure::EndpointNotFound));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_refused() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let report = test_endpoint(&endpoint(format!("http://{}/v1", addr), Some("good")), None, None).await;
+        assert_eq!(report.failure, Some(ProviderFailure::ConnectionRefused));
+        assert!(report.status.is_none());
+    }
+
+    #[test]
+    fn test_classify_error_chain() {
+        assert_eq!(
+            classify_error_chain("error sending request: client error (Connect): dns error: failed to lookup address information"),
+            ProviderFailure::Dns
+        );
+        assert_eq!(
+            classify_error_chain("error sending request: invalid peer certificate: UnknownIssuer"),
+            ProviderFailure::Tls
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{load_auth, AuthMode};
+use crate::config::read_model_providers;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OSS_BASE_URL: &str = "http://localhost:11434/v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointKind {
+    /// OpenAI-compatible API exposing `GET /models`.
+    OpenAi,
+    /// Local Ollama server exposing `GET /api/tags`.
+    Ollama,
+}
+
+/// Where and how to reach a model provider's HTTP API.
+#[derive(Debug, Clone)]
+pub struct ProviderEndpoint {
+    pub name: String,
+    pub kind: EndpointKind,
+    pub base_url: String,
+    /// Environment variable the key was read from, if any.
+    pub env_key: Option<String>,
+    pub api_key: Option<String>,
+}
+
+impl ProviderEndpoint {
+    /// Cache key identifying this endpoint.
+    pub fn cache_key(&self) -> String {
+        format!("{}|{}", self.name, self.base_url)
+    }
+}
+
+/// Resolves `provider` against `[model_providers]` in `config.toml`, falling
+/// back to the built-in OpenAI and OSS (Ollama) providers the CLI knows about.
+pub async fn resolve_endpoint(provider: &str, use_oss: bool) -> Result<ProviderEndpoint, String> {
+    if use_oss || provider == "oss" {
+        let base_url = std::env::var("CODEX_OSS_BASE_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_OSS_BASE_URL.to_string());
+        return Ok(ProviderEndpoint {
+            name: "oss".to_string(),
+            kind: EndpointKind::Ollama,
+            base_url,
+            env_key: None,
+            api_key: None,
+        });
+    }
+
+    let providers = read_model_providers().await?;
+    // Try exact match first, then lowercase match
+    if let Some(config) = providers
+        .get(provider)
+        .or_else(|| providers.get(&provider.to_lowercase()))
+    {
+        let env_key = Some(config.env_key.clone()).filter(|k| !k.is_empty());
+        let api_key = env_key
+            .as_deref()
+            .and_then(|k| std::env::var(k).ok())
+            .filter(|k| !k.is_empty());
+        return Ok(ProviderEndpoint {
+            name: provider.to_string(),
+            kind: EndpointKind::OpenAi,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            env_key,
+            api_key,
+        });
+    }
+
+    if provider.is_empty() || provider.eq_ignore_ascii_case("openai") {
+        // Same lookup as the CLI: env var first, then auth.json
+        let api_key = match dirs::home_dir() {
+            Some(home) => match load_auth(&home.join(".codex"), false).await {
+                Ok(Some(auth)) if auth.mode == AuthMode::ApiKey => auth.get_api_key(),
+                _ => None,
+            },
+            None => None,
+        };
+        return Ok(ProviderEndpoint {
+            name: "openai".to_string(),
+            kind: EndpointKind::OpenAi,
+            base_url: OPENAI_BASE_URL.to_string(),
+            env_key: Some(crate::auth::OPENAI_API_KEY_ENV_VAR.to_string()),
+            api_key,
+        });
+    }
+
+    Err(format!("Model provider '{}' not found", provider))
+}
@@ -0,0 +1,9 @@
+pub mod connectivity;
+pub mod endpoint;
+pub mod model_info;
+pub mod models;
+
+pub use connectivity::{test_endpoint, ProviderTestReport};
+pub use endpoint::resolve_endpoint;
+pub use model_info::{model_info, ModelInfo};
+pub use models::{ModelCatalog, ProviderModels};
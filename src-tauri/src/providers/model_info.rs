@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// Token limits of a model family.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub context_window: u64,
+    pub max_output_tokens: u64,
+}
+
+/// Known limits by model name prefix. The longest matching prefix wins, so
+/// `gpt-4.1-mini` and dated snapshots resolve to their family.
+const MODEL_INFO: &[(&str, ModelInfo)] = &[
+    ("gpt-5", ModelInfo { context_window: 272_000, max_output_tokens: 128_000 }),
+    ("codex-", ModelInfo { context_window: 272_000, max_output_tokens: 128_000 }),
+    ("codex-mini", ModelInfo { context_window: 200_000, max_output_tokens: 100_000 }),
+    ("gpt-4.1", ModelInfo { context_window: 1_047_576, max_output_tokens: 32_768 }),
+    ("gpt-4o", ModelInfo { context_window: 128_000, max_output_tokens: 16_384 }),
+    ("o1", ModelInfo { context_window: 200_000, max_output_tokens: 100_000 }),
+    ("o3", ModelInfo { context_window: 200_000, max_output_tokens: 100_000 }),
+    ("o4-mini", ModelInfo { context_window: 200_000, max_output_tokens: 100_000 }),
+    ("gpt-oss", ModelInfo { context_window: 131_072, max_output_tokens: 32_768 }),
+];
+
+/// Limits for `model` as passed in `CodexConfig.model`, if known. A
+/// `provider/` prefix (as used by OpenRouter) is ignored.
+pub fn model_info(model: &str) -> Option<ModelInfo> {
+    let model = model.to_lowercase();
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    MODEL_INFO
+        .iter()
+        .filter(|(prefix, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, info)| *info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_info_lookup() {
+        assert_eq!(model_info("gpt-5").unwrap().context_window, 272_000);
+        assert_eq!(model_info("GPT-4.1-mini-2025-04-14").unwrap().max_output_tokens, 32_768);
+        assert_eq!(model_info("codex-mini-latest").unwrap().context_window, 200_000);
+        assert_eq!(model_info("openai/gpt-4o").unwrap().context_window, 128_000);
+        assert!(model_info("llama3.1").is_none());
+    }
+}
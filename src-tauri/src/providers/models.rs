@@ -0,0 +1,262 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use super::endpoint::{EndpointKind, ProviderEndpoint};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a provider's model list is reused before asking again.
+pub const DEFAULT_CATALOG_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderModel {
+    pub id: String,
+    pub owned_by: Option<String>,
+    /// Unix timestamp reported by OpenAI-compatible APIs.
+    pub created: Option<i64>,
+    /// Size on disk reported by Ollama.
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderModels {
+    pub provider: String,
+    pub models: Vec<ProviderModel>,
+    pub fetched_at: DateTime<Utc>,
+    /// True when served from the cache instead of a fresh request.
+    pub cached: bool,
+}
+
+struct CachedModels {
+    stored_at: Instant,
+    models: ProviderModels,
+}
+
+/// Per-provider model lists with a time-to-live.
+pub struct ModelCatalog {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedModels>>,
+}
+
+impl ModelCatalog {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached list for `endpoint` if still fresh, otherwise
+    /// fetches it. `refresh` bypasses the cache.
+    pub async fn list(&self, endpoint: &ProviderEndpoint, refresh: bool) -> Result<ProviderModels, String> {
+        let key = endpoint.cache_key();
+        if !refresh {
+            let entries = self.entries.lock().await;
+            if let Some(entry) = entries.get(&key) {
+                if entry.stored_at.elapsed() < self.ttl {
+                    let mut models = entry.models.clone();
+                    models.cached = true;
+                    return Ok(models);
+                }
+            }
+        }
+
+        let models = ProviderModels {
+            provider: endpoint.name.clone(),
+            models: fetch_models(endpoint).await?,
+            fetched_at: Utc::now(),
+            cached: false,
+        };
+        self.entries.lock().await.insert(
+            key,
+            CachedModels {
+                stored_at: Instant::now(),
+                models: models.clone(),
+            },
+        );
+        Ok(models)
+    }
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CATALOG_TTL)
+    }
+}
+
+pub async fn fetch_models(endpoint: &ProviderEndpoint) -> Result<Vec<ProviderModel>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let url = match endpoint.kind {
+        EndpointKind::OpenAi => format!("{}/models", endpoint.base_url),
+        EndpointKind::Ollama => format!("{}/api/tags", ollama_root(&endpoint.base_url)),
+    };
+    let mut request = client.get(&url);
+    if let Some(key) = &endpoint.api_key {
+        request = request.bearer_auth(key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", url, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "{} returned {}: {}",
+            url,
+            status,
+            body.chars().take(200).collect::<String>()
+        ));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {}: {}", url, e))?;
+
+    let mut models = match endpoint.kind {
+        EndpointKind::OpenAi => parse_openai_models(&body),
+        EndpointKind::Ollama => parse_ollama_tags(&body),
+    };
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
+/// Ollama serves its native API at the root, not under the `/v1` prefix used
+/// for OpenAI compatibility.
+pub(super) fn ollama_root(base_url: &str) -> &str {
+    let trimmed = base_url.trim_end_matches('/');
+    trimmed.strip_suffix("/v1").unwrap_or(trimmed)
+}
+
+fn parse_openai_models(body: &Value) -> Vec<ProviderModel> {
+    body.get("data")
+        .and_then(|d| d.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            Some(ProviderModel {
+                id: model.get("id")?.as_str()?.to_string(),
+                owned_by: model
+                    .get("owned_by")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
+                created: model.get("created").and_then(|v| v.as_i64()),
+                size: None,
+            })
+        })
+        .collect()
+}
+
+fn parse_ollama_tags(body: &Value) -> Vec<ProviderModel> {
+    body.get("models")
+        .and_then(|d| d.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            Some(ProviderModel {
+                id: model.get("name")?.as_str()?.to_string(),
+                owned_by: None,
+                created: None,
+                size: model.get("size").and_then(|v| v.as_u64()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Serves canned `/v1/models` and `/api/tags` responses, counting hits.
+    fn mock_provider() -> (SocketAddr, Arc<AtomicUsize>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits_clone = hits.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                hits_clone.fetch_add(1, Ordering::SeqCst);
+                let authorized = request
+                    .headers()
+                    .iter()
+                    .any(|h| h.field.equiv("Authorization") && h.value.as_str() == "Bearer test-key");
+                let (status, body) = match request.url() {
+                    "/v1/models" if authorized => (
+                        200,
+                        r#"{"object":"list","data":[{"id":"gpt-b","owned_by":"org","created":1},{"id":"gpt-a"}]}"#,
+                    ),
+                    "/v1/models" => (401, r#"{"error":{"message":"bad key"}}"#),
+                    "/api/tags" => (200, r#"{"models":[{"name":"llama3.2:latest","size":2019393189}]}"#),
+                    _ => (404, "{}"),
+                };
+                let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(status));
+            }
+        });
+        (addr, hits)
+    }
+
+    fn endpoint(addr: SocketAddr, kind: EndpointKind, api_key: Option<&str>) -> ProviderEndpoint {
+        ProviderEndpoint {
+            name: "mock".to_string(),
+            kind,
+            base_url: format!("http://{}/v1", addr),
+            env_key: None,
+            api_key: api_key.map(|k| k.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_openai_models() {
+        let (addr, _) = mock_provider();
+        let models = fetch_models(&endpoint(addr, EndpointKind::OpenAi, Some("test-key")))
+            .await
+            .unwrap();
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["gpt-a", "gpt-b"]);
+        assert_eq!(models[1].owned_by.as_deref(), Some("org"));
+
+        let err = fetch_models(&endpoint(addr, EndpointKind::OpenAi, None))
+            .await
+            .unwrap_err();
+        assert!(err.contains("401"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_ollama_tags() {
+        let (addr, _) = mock_provider();
+        let models = fetch_models(&endpoint(addr, EndpointKind::Ollama, None))
+            .await
+            .unwrap();
+        assert_eq!(models[0].id, "llama3.2:latest");
+        assert_eq!(models[0].size, Some(2019393189));
+    }
+
+    #[tokio::test]
+    async fn test_catalog_ttl() {
+        let (addr, hits) = mock_provider();
+        let target = endpoint(addr, EndpointKind::Ollama, None);
+
+        let catalog = ModelCatalog::new(Duration::from_secs(60));
+        assert!(!catalog.list(&target, false).await.unwrap().cached);
+        assert!(catalog.list(&target, false).await.unwrap().cached);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert!(!catalog.list(&target, true).await.unwrap().cached);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let expired = ModelCatalog::new(Duration::ZERO);
+        expired.list(&target, false).await.unwrap();
+        expired.list(&target, false).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+}
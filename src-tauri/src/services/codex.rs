@@ -1,7 +1,11 @@
 use crate::codex_client::CodexClient;
 use crate::protocol::CodexConfig;
+use crate::services::context_window::supports_compaction;
+use crate::services::file_finder::{FileMatch, DEFAULT_FIND_LIMIT};
 use crate::state::CodexState;
 use crate::utils::codex_discovery::discover_codex_command;
+use serde::Serialize;
+use std::path::PathBuf;
 use std::process::Command;
 use tauri::{AppHandle, State};
 
@@ -24,9 +28,28 @@ pub async fn start_codex_session(
         }
     }
 
-    let codex_client = CodexClient::new(&app, session_id.clone(), config)
-        .await
-        .map_err(|e| format!("Failed to start Codex session: {}", e))?;
+    // Index the workspace in the background so the first search is fast
+    if !config.working_directory.is_empty() {
+        let finder = state.file_finder.clone();
+        match finder.hold(&session_id, &PathBuf::from(&config.working_directory)) {
+            Ok(root) => {
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = finder.index_for(&root) {
+                        log::warn!("Failed to index {}: {}", root.display(), e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Failed to index {}: {}", config.working_directory, e),
+        }
+    }
+
+    let codex_client = match CodexClient::new(&app, session_id.clone(), config).await {
+        Ok(client) => client,
+        Err(e) => {
+            state.file_finder.release(&session_id);
+            return Err(format!("Failed to start Codex session: {}", e));
+        }
+    };
 
     {
         let mut sessions = state.sessions.lock().await;
@@ -38,6 +61,31 @@ pub async fn start_codex_session(
     Ok(())
 }
 
+/// Fuzzy-matches `query` against the files in the session's working directory.
+pub async fn find_files(
+    state: State<'_, CodexState>,
+    session_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<FileMatch>, String> {
+    let root = {
+        let sessions = state.sessions.lock().await;
+        let client = sessions.get(&session_id).ok_or("Session not found")?;
+        PathBuf::from(client.working_directory())
+    };
+    if root.as_os_str().is_empty() {
+        return Err("Session has no working directory".to_string());
+    }
+
+    let finder = state.file_finder.clone();
+    tokio::task::spawn_blocking(move || {
+        let index = finder.index_for(&root)?;
+        Ok(index.find(&query, limit.unwrap_or(DEFAULT_FIND_LIMIT)))
+    })
+    .await
+    .map_err(|e| format!("File search failed: {}", e))?
+}
+
 pub async fn send_message(
     state: State<'_, CodexState>,
     session_id: String,
@@ -139,9 +187,29 @@ pub async fn pause_session(state: State<'_, CodexState>, session_id: String) ->
     }
 }
 
+/// Asks the CLI to summarize the conversation so far, freeing context.
+pub async fn compact_session(state: State<'_, CodexState>, session_id: String) -> Result<(), String> {
+    let version = check_codex_version().await?;
+    if !supports_compaction(&version) {
+        return Err(format!("{} does not support compaction; update the Codex CLI", version));
+    }
+
+    let sessions = state.sessions.lock().await;
+    if let Some(client) = sessions.get(&session_id) {
+        client
+            .compact()
+            .await
+            .map_err(|e| format!("Failed to compact session: {}", e))?;
+        Ok(())
+    } else {
+        Err("Session not found".to_string())
+    }
+}
+
 pub async fn close_session(state: State<'_, CodexState>, session_id: String) -> Result<(), String> {
     let mut sessions = state.sessions.lock().await;
     if let Some(mut client) = sessions.remove(&session_id) {
+        state.file_finder.release(&session_id);
         client
             .close_session()
             .await
@@ -152,14 +220,28 @@ pub async fn close_session(state: State<'_, CodexState>, session_id: String) ->
     }
 }
 
-pub async fn get_running_sessions(state: State<'_, CodexState>) -> Result<Vec<String>, String> {
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningSession {
+    pub session_id: String,
+    /// Where the session's API key came from (e.g. `env:WORK_KEY`), if it
+    /// was given one.
+    pub credential_source: Option<String>,
+}
+
+pub async fn get_running_sessions(state: State<'_, CodexState>) -> Result<Vec<RunningSession>, String> {
     let sessions = state.sessions.lock().await;
-    let session_keys: Vec<String> = sessions.keys().cloned().collect();
+    let session_keys: Vec<&String> = sessions.keys().collect();
 
     // Debug log to see what sessions are actually stored
     log::debug!("get_running_sessions called - stored sessions: {:?}", session_keys);
 
-    Ok(session_keys)
+    Ok(sessions
+        .iter()
+        .map(|(session_id, client)| RunningSession {
+            session_id: session_id.clone(),
+            credential_source: client.credential_source().map(str::to_string),
+        })
+        .collect())
 }
 
 pub async fn check_codex_version() -> Result<String, String> {
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+use crate::providers::{model_info, ModelInfo};
+use crate::services::usage::TokenUsage;
+
+/// Percentages of the context window at which the frontend is warned.
+pub const WARNING_THRESHOLDS: [u8; 2] = [75, 90];
+
+/// First CLI release that accepts the `compact` op.
+const COMPACT_MIN_VERSION: (u64, u64, u64) = (0, 24, 0);
+
+/// Payload of the `context-usage` and `context-window-warning` events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextUsage {
+    pub session_id: String,
+    pub model: String,
+    pub tokens_in_context: u64,
+    /// Unknown models have no limits and never trigger warnings.
+    pub context_window: Option<u64>,
+    pub max_output_tokens: Option<u64>,
+    pub percent_used: Option<f64>,
+    /// Highest entry of `WARNING_THRESHOLDS` reached.
+    pub threshold: Option<u8>,
+}
+
+/// Relates each response's token count to the session model's context
+/// window.
+pub struct ContextMonitor {
+    session_id: String,
+    model: String,
+    info: Option<ModelInfo>,
+    /// Last threshold reported, so each one fires once until the context
+    /// shrinks again (e.g. after compaction).
+    reported: Option<u8>,
+}
+
+impl ContextMonitor {
+    pub fn new(session_id: &str, model: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            model: model.to_string(),
+            info: model_info(model),
+            reported: None,
+        }
+    }
+
+    pub fn set_model(&mut self, model: &str) {
+        if self.model != model {
+            self.model = model.to_string();
+            self.info = model_info(model);
+            self.reported = None;
+        }
+    }
+
+    /// Takes the usage of the latest response. Returns the context usage and
+    /// whether a new threshold was crossed.
+    pub fn observe(&mut self, last_response: &TokenUsage) -> (ContextUsage, bool) {
+        // Reasoning tokens are dropped from the history sent on the next turn.
+        let total = match last_response.total_tokens {
+            0 => last_response.input_tokens + last_response.output_tokens,
+            total => total,
+        };
+        let tokens_in_context = total.saturating_sub(last_response.reasoning_output_tokens);
+        let percent_used = self
+            .info
+            .filter(|info| info.context_window > 0)
+            .map(|info| tokens_in_context as f64 * 100.0 / info.context_window as f64);
+        let threshold = percent_used.and_then(|percent| {
+            WARNING_THRESHOLDS
+                .iter()
+                .rev()
+                .find(|&&t| percent >= t as f64)
+                .copied()
+        });
+
+        let crossed = threshold.is_some() && threshold > self.reported;
+        if crossed || threshold < self.reported {
+            self.reported = threshold;
+        }
+
+        let usage = ContextUsage {
+            session_id: self.session_id.clone(),
+            model: self.model.clone(),
+            tokens_in_context,
+            context_window: self.info.map(|info| info.context_window),
+            max_output_tokens: self.info.map(|info| info.max_output_tokens),
+            percent_used,
+            threshold,
+        };
+        (usage, crossed)
+    }
+}
+
+/// Whether `codex -V` output such as `codex-cli 0.30.0` names a release
+/// that supports compaction.
+pub fn supports_compaction(version_output: &str) -> bool {
+    let Some(version) = version_output.split_whitespace().last() else {
+        return false;
+    };
+    let mut parts = version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map(|p| p.parse::<u64>().ok());
+    match (parts.next().flatten(), parts.next().flatten(), parts.next().flatten()) {
+        (Some(major), Some(minor), patch) => (major, minor, patch.unwrap_or(0)) >= COMPACT_MIN_VERSION,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(total: u64, reasoning: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: total - reasoning,
+            output_tokens: reasoning,
+            reasoning_output_tokens: reasoning,
+            total_tokens: total,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_thresholds_fire_once_and_rearm() {
+        let mut monitor = ContextMonitor::new("s1", "gpt-4o");
+
+        let (usage, crossed) = monitor.observe(&response(64_000, 0));
+        assert_eq!(usage.percent_used, Some(50.0));
+        assert!(!crossed);
+
+        let (usage, crossed) = monitor.observe(&response(100_000, 0));
+        assert_eq!(usage.threshold, Some(75));
+        assert!(crossed);
+        assert!(!monitor.observe(&response(101_000, 0)).1);
+
+        // Reasoning doesn't count towards the next turn's context.
+        let (usage, crossed) = monitor.observe(&response(130_000, 10_000));
+        assert_eq!(usage.tokens_in_context, 120_000);
+        assert_eq!(usage.threshold, Some(90));
+        assert!(crossed);
+
+        // After compaction the warnings fire again.
+        assert!(!monitor.observe(&response(20_000, 0)).1);
+        assert!(monitor.observe(&response(100_000, 0)).1);
+    }
+
+    #[test]
+    fn test_unknown_model_has_no_limits() {
+        let mut monitor = ContextMonitor::new("s1", "llama3.1");
+        let (usage, crossed) = monitor.observe(&response(1_000_000, 0));
+        assert_eq!(usage.context_window, None);
+        assert_eq!(usage.threshold, None);
+        assert!(!crossed);
+
+        monitor.set_model("gpt-5");
+        assert_eq!(monitor.observe(&response(1_000, 0)).0.context_window, Some(272_000));
+    }
+
+    #[test]
+    fn test_supports_compaction() {
+        assert!(supports_compaction("codex-cli 0.30.0"));
+        assert!(supports_compaction("codex-cli 0.24.0-alpha.1"));
+        assert!(!supports_compaction("codex-cli 0.10.0"));
+        assert!(!supports_compaction("codex-cli dev"));
+        assert!(!supports_compaction(""));
+    }
+}
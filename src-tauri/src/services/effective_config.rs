@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::config::{read_model_providers, read_profiles, ModelProvider, Profile};
+use crate::protocol::CodexConfig;
+
+/// Where an effective setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingSource {
+    /// Inherited from the selected profile in `config.toml`.
+    Profile,
+    /// Looked up from a `[model_providers]` entry.
+    Provider,
+    /// Explicitly set on the session config.
+    Session,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EffectiveSetting {
+    pub value: String,
+    pub source: SettingSource,
+}
+
+impl EffectiveSetting {
+    fn new(value: impl Into<String>, source: SettingSource) -> Self {
+        Self {
+            value: value.into(),
+            source,
+        }
+    }
+}
+
+/// Final settings a session will run with, and the `codex proto` arguments
+/// that produce them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveConfig {
+    pub profile: Option<String>,
+    /// Key of the provider in `config.toml` used to pick credentials.
+    pub provider_id: String,
+    pub model_provider: Option<EffectiveSetting>,
+    pub model: Option<EffectiveSetting>,
+    pub base_url: Option<EffectiveSetting>,
+    pub env_key: Option<String>,
+    pub approval_policy: Option<EffectiveSetting>,
+    pub sandbox_mode: Option<EffectiveSetting>,
+    /// Arguments appended after `codex proto`, in order.
+    pub args: Vec<String>,
+}
+
+pub async fn resolve_effective_config(config: &CodexConfig) -> Result<EffectiveConfig, String> {
+    let providers = read_model_providers().await.ok();
+    let profiles = if config.profile.is_some() {
+        read_profiles().await?
+    } else {
+        HashMap::new()
+    };
+    resolve(config, providers.as_ref(), &profiles)
+}
+
+fn lookup_provider<'a>(
+    providers: Option<&'a HashMap<String, ModelProvider>>,
+    name: &str,
+) -> Option<&'a ModelProvider> {
+    // Try exact match first, then lowercase match
+    providers.and_then(|p| p.get(name).or_else(|| p.get(&name.to_lowercase())))
+}
+
+fn normalize_sandbox_mode(mode: &str) -> &'static str {
+    match mode {
+        "read-only" => "read-only",
+        "danger-full-access" => "danger-full-access",
+        _ => "workspace-write",
+    }
+}
+
+/// Layers the session config on top of its profile (if any).
+///
+/// `providers` is `None` when `config.toml` could not be read, in which case
+/// provider names are passed through to the CLI unchanged.
+pub fn resolve(
+    config: &CodexConfig,
+    providers: Option<&HashMap<String, ModelProvider>>,
+    profiles: &HashMap<String, Profile>,
+) -> Result<EffectiveConfig, String> {
+    let profile_name = config.profile.as_deref().filter(|p| !p.is_empty());
+    let profile = match profile_name {
+        Some(name) => Some(
+            profiles
+                .get(name)
+                .ok_or_else(|| format!("Profile '{}' not found", name))?,
+        ),
+        None => None,
+    };
+
+    let mut args = Vec::new();
+    if let Some(name) = profile_name {
+        args.push("--profile".to_string());
+        args.push(name.to_string());
+    }
+    let mut push_override = |key: &str, value: &str| {
+        args.push("-c".to_string());
+        args.push(format!("{}={}", key, value));
+    };
+
+    // Provider: a session value only overrides the profile when it differs.
+    let session_provider = Some(config.provider.as_str()).filter(|p| !p.is_empty());
+    let provider_override = match (session_provider, profile) {
+        (Some(p), Some(profile)) => p != profile.model_provider,
+        (Some(p), None) => p != "openai" || config.use_oss,
+        (None, _) => config.use_oss,
+    };
+
+    let provider_id = session_provider
+        .map(str::to_string)
+        .or_else(|| profile.map(|p| p.model_provider.clone()))
+        .unwrap_or_default();
+    let provider_config = lookup_provider(providers, &provider_id);
+
+    let mut model_provider = profile.map(|p| EffectiveSetting::new(&p.model_provider, SettingSource::Profile));
+    let mut base_url = provider_config
+        .filter(|p| !p.base_url.is_empty())
+        .map(|p| EffectiveSetting::new(&p.base_url, SettingSource::Provider));
+
+    if provider_override {
+        let is_openai = session_provider.is_none_or(|p| p == "openai");
+        match provider_config.filter(|_| !is_openai) {
+            Some(provider_config) => {
+                push_override("model_provider", &provider_config.name);
+                if !provider_config.base_url.is_empty() {
+                    push_override("base_url", &provider_config.base_url);
+                }
+                model_provider = Some(EffectiveSetting::new(&provider_config.name, SettingSource::Session));
+            }
+            None => {
+                let name = if config.use_oss { "oss" } else { provider_id.as_str() };
+                push_override("model_provider", name);
+                model_provider = Some(EffectiveSetting::new(name, SettingSource::Session));
+                base_url = None;
+            }
+        }
+    } else if let Some(p) = session_provider.filter(|_| profile.is_none()) {
+        model_provider = Some(EffectiveSetting::new(p, SettingSource::Session));
+    }
+
+    let mut model = profile.map(|p| EffectiveSetting::new(&p.model, SettingSource::Profile));
+    if !config.model.is_empty() && profile.is_none_or(|p| p.model != config.model) {
+        push_override("model", &config.model);
+        model = Some(EffectiveSetting::new(&config.model, SettingSource::Session));
+    }
+
+    let mut approval_policy = profile
+        .and_then(|p| p.approval_policy.as_ref())
+        .map(|v| EffectiveSetting::new(v, SettingSource::Profile));
+    if !config.approval_policy.is_empty()
+        && approval_policy.as_ref().is_none_or(|a| a.value != config.approval_policy)
+    {
+        push_override("approval_policy", &config.approval_policy);
+        approval_policy = Some(EffectiveSetting::new(&config.approval_policy, SettingSource::Session));
+    }
+
+    let mut sandbox_mode = profile
+        .and_then(|p| p.sandbox_mode.as_ref())
+        .map(|v| EffectiveSetting::new(v, SettingSource::Profile));
+    if !config.sandbox_mode.is_empty() {
+        let mode = normalize_sandbox_mode(&config.sandbox_mode);
+        if sandbox_mode.as_ref().is_none_or(|s| s.value != mode) {
+            push_override("sandbox_mode", mode);
+            sandbox_mode = Some(EffectiveSetting::new(mode, SettingSource::Session));
+        }
+    }
+
+    // Enable streaming by setting show_raw_agent_reasoning=true
+    // This is required for agent_message_delta events to be generated
+    push_override("show_raw_agent_reasoning", "true");
+
+    if !config.working_directory.is_empty() {
+        push_override("cwd", &config.working_directory);
+    }
+
+    if let Some(custom_args) = &config.custom_args {
+        args.extend(custom_args.iter().cloned());
+    }
+
+    Ok(EffectiveConfig {
+        profile: profile_name.map(str::to_string),
+        env_key: provider_config
+            .map(|p| p.env_key.clone())
+            .filter(|k| !k.is_empty()),
+        provider_id,
+        model_provider,
+        model,
+        base_url,
+        approval_policy,
+        sandbox_mode,
+        args,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_config() -> CodexConfig {
+        CodexConfig {
+            working_directory: "/tmp/project".to_string(),
+            model: String::new(),
+            provider: String::new(),
+            use_oss: false,
+            custom_args: None,
+            approval_policy: String::new(),
+            sandbox_mode: String::new(),
+            codex_path: None,
+            api_key: None,
+            profile: None,
+            credential_source: None,
+        }
+    }
+
+    fn providers() -> HashMap<String, ModelProvider> {
+        HashMap::from([(
+            "openrouter".to_string(),
+            ModelProvider {
+                name: "OpenRouter".to_string(),
+                base_url: "https://openrouter.ai/api/v1".to_string(),
+                env_key: "OPENROUTER_API_KEY".to_string(),
+            },
+        )])
+    }
+
+    fn profiles() -> HashMap<String, Profile> {
+        HashMap::from([(
+            "work".to_string(),
+            Profile {
+                model_provider: "openrouter".to_string(),
+                model: "gpt-4.1".to_string(),
+                approval_policy: Some("on-request".to_string()),
+                sandbox_mode: None,
+            },
+        )])
+    }
+
+    #[test]
+    fn test_profile_without_overrides() {
+        let mut config = session_config();
+        config.profile = Some("work".to_string());
+        config.provider = "openrouter".to_string();
+        config.model = "gpt-4.1".to_string();
+        config.approval_policy = "on-request".to_string();
+
+        let effective = resolve(&config, Some(&providers()), &profiles()).unwrap();
+        assert_eq!(
+            effective.args,
+            vec![
+                "--profile",
+                "work",
+                "-c",
+                "show_raw_agent_reasoning=true",
+                "-c",
+                "cwd=/tmp/project",
+            ]
+        );
+        assert_eq!(effective.model.unwrap().source, SettingSource::Profile);
+        assert_eq!(effective.env_key.as_deref(), Some("OPENROUTER_API_KEY"));
+    }
+
+    #[test]
+    fn test_session_overrides_layered_on_profile() {
+        let mut config = session_config();
+        config.profile = Some("work".to_string());
+        config.model = "o3".to_string();
+        config.sandbox_mode = "read-only".to_string();
+
+        let effective = resolve(&config, Some(&providers()), &profiles()).unwrap();
+        assert!(effective.args.windows(2).any(|w| w == ["-c", "model=o3"]));
+        assert!(effective.args.windows(2).any(|w| w == ["-c", "sandbox_mode=read-only"]));
+        assert!(!effective.args.iter().any(|a| a.starts_with("model_provider=")));
+        assert_eq!(
+            effective.model,
+            Some(EffectiveSetting::new("o3", SettingSource::Session))
+        );
+        assert_eq!(
+            effective.approval_policy,
+            Some(EffectiveSetting::new("on-request", SettingSource::Profile))
+        );
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let mut config = session_config();
+        config.profile = Some("missing".to_string());
+        assert!(resolve(&config, Some(&providers()), &profiles()).is_err());
+    }
+
+    #[test]
+    fn test_without_profile_matches_legacy_flags() {
+        let mut config = session_config();
+        config.provider = "openrouter".to_string();
+        config.model = "gpt-4.1".to_string();
+        config.sandbox_mode = "bogus".to_string();
+
+        let effective = resolve(&config, Some(&providers()), &HashMap::new()).unwrap();
+        assert_eq!(
+            effective.args,
+            vec![
+                "-c",
+                "model_provider=OpenRouter",
+                "-c",
+                "base_url=https://openrouter.ai/api/v1",
+                "-c",
+                "model=gpt-4.1",
+                "-c",
+                "sandbox_mode=workspace-write",
+                "-c",
+                "show_raw_agent_reasoning=true",
+                "-c",
+                "cwd=/tmp/project",
+            ]
+        );
+    }
+}
@@ -0,0 +1,493 @@
+use ignore::{WalkBuilder, WalkState};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+
+use crate::filesystem::watcher::{WorkspaceSubscription, WorkspaceWatchers, DEFAULT_DEBOUNCE};
+
+/// Files per index, so a mistaken root like `/` can't exhaust memory.
+const MAX_INDEXED_FILES: usize = 500_000;
+pub const DEFAULT_FIND_LIMIT: usize = 50;
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_SEGMENT_START: i64 = 32;
+const BONUS_WORD_START: i64 = 24;
+const BONUS_CAMEL_CASE: i64 = 20;
+const BONUS_CONSECUTIVE: i64 = 16;
+const BONUS_FILE_NAME: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMatch {
+    pub path: String,
+    /// Relative to the indexed root, with `/` separators.
+    pub relative_path: String,
+    pub score: i64,
+    /// Character offsets into `relative_path` that matched the query.
+    pub positions: Vec<usize>,
+}
+
+/// Scores `candidate` against a lowercase `query` (whitespace removed).
+/// Matches at path segment starts, word starts and camelCase humps score
+/// higher, as do consecutive runs and matches in the file name.
+pub fn fuzzy_match(query: &[char], candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let (m, n) = (query.len(), chars.len());
+    if m == 0 {
+        return Some((0, Vec::new()));
+    }
+    if m > n {
+        return None;
+    }
+
+    // Cheap subsequence check before the full alignment
+    let mut qi = 0;
+    for c in &lower {
+        if qi < m && *c == query[qi] {
+            qi += 1;
+        }
+    }
+    if qi < m {
+        return None;
+    }
+
+    let bonus: Vec<i64> = (0..n)
+        .map(|j| {
+            let prev = if j == 0 { '/' } else { chars[j - 1] };
+            let c = chars[j];
+            if prev == '/' || prev == '\\' {
+                BONUS_SEGMENT_START
+            } else if matches!(prev, '_' | '-' | '.' | ' ') {
+                BONUS_WORD_START
+            } else if (prev.is_lowercase() && c.is_uppercase()) || (!prev.is_ascii_digit() && c.is_ascii_digit()) {
+                BONUS_CAMEL_CASE
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    // score[i][j]: best alignment of query[..=i] with query[i] at j;
+    // from[i][j]: where query[i - 1] sits in that alignment
+    const NONE: i64 = i64::MIN / 2;
+    let mut score = vec![vec![NONE; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+    for j in 0..n {
+        if lower[j] == query[0] {
+            score[0][j] = SCORE_MATCH + bonus[j];
+        }
+    }
+    for i in 1..m {
+        // max over k < j - 1 of score[i - 1][k] + PENALTY_GAP * k
+        let mut best_gapped = (NONE, 0usize);
+        for j in i..n {
+            if j >= 2 {
+                let k = j - 2;
+                let candidate = score[i - 1][k].saturating_add(PENALTY_GAP * k as i64);
+                if score[i - 1][k] > NONE && candidate > best_gapped.0 {
+                    best_gapped = (candidate, k);
+                }
+            }
+            if lower[j] != query[i] {
+                continue;
+            }
+            let consecutive = score[i - 1][j - 1];
+            let consecutive = if consecutive > NONE {
+                consecutive + BONUS_CONSECUTIVE.max(bonus[j])
+            } else {
+                NONE
+            };
+            let gapped = if best_gapped.0 > NONE {
+                best_gapped.0 - PENALTY_GAP * (j as i64 - 1) + bonus[j]
+            } else {
+                NONE
+            };
+            if consecutive >= gapped && consecutive > NONE {
+                score[i][j] = consecutive + SCORE_MATCH;
+                from[i][j] = j - 1;
+            } else if gapped > NONE {
+                score[i][j] = gapped + SCORE_MATCH;
+                from[i][j] = best_gapped.1;
+            }
+        }
+    }
+
+    let (mut j, mut best) = (0, NONE);
+    for (k, &s) in score[m - 1].iter().enumerate() {
+        // Later ends win ties: they sit closer to the file name
+        if s > NONE && s >= best {
+            best = s;
+            j = k;
+        }
+    }
+    if best == NONE {
+        return None;
+    }
+
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    let file_name_start = chars.iter().rposition(|&c| c == '/').map_or(0, |p| p + 1);
+    let in_file_name = positions.iter().filter(|&&p| p >= file_name_start).count() as i64;
+    Some((best + in_file_name * BONUS_FILE_NAME - n as i64 / 8, positions))
+}
+
+/// Relative paths of the files under `root` that aren't ignored.
+pub struct FileIndex {
+    root: PathBuf,
+    files: RwLock<BTreeSet<String>>,
+    /// Indexed directories, so events inside ignored ones are skipped fast.
+    dirs: RwLock<HashSet<String>>,
+    scanned: OnceLock<Result<(), String>>,
+    subscription: Mutex<Option<WorkspaceSubscription>>,
+}
+
+impl FileIndex {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            files: RwLock::new(BTreeSet::new()),
+            dirs: RwLock::new(HashSet::new()),
+            scanned: OnceLock::new(),
+            subscription: Mutex::new(None),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        Some(parts.join("/"))
+    }
+
+    fn walker(path: &Path) -> WalkBuilder {
+        let mut builder = WalkBuilder::new(path);
+        builder
+            .hidden(true)
+            .parents(true)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git");
+        builder
+    }
+
+    /// Adds every visible file under `dir` (which must be visible itself),
+    /// up to the index's overall cap.
+    fn add_tree(&self, dir: &Path) {
+        let budget = MAX_INDEXED_FILES.saturating_sub(self.len());
+        let found: Mutex<(Vec<String>, Vec<String>)> = Mutex::new((Vec::new(), Vec::new()));
+        Self::walker(dir).build_parallel().run(|| {
+            Box::new(|entry| {
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                let Some(relative) = self.relative(entry.path()) else {
+                    return WalkState::Continue;
+                };
+                let mut found = found.lock().unwrap_or_else(|e| e.into_inner());
+                if entry.file_type().is_some_and(|t| t.is_dir()) {
+                    found.1.push(relative);
+                } else if found.0.len() >= budget {
+                    return WalkState::Quit;
+                } else {
+                    found.0.push(relative);
+                }
+                WalkState::Continue
+            })
+        });
+        let (files, dirs) = found.into_inner().unwrap_or_else(|e| e.into_inner());
+        self.files.write().unwrap_or_else(|e| e.into_inner()).extend(files);
+        self.dirs.write().unwrap_or_else(|e| e.into_inner()).extend(dirs);
+    }
+
+    fn ensure_scanned(self: &Arc<Self>, watchers: &WorkspaceWatchers) -> Result<(), String> {
+        self.scanned
+            .get_or_init(|| {
+                if !self.root.is_dir() {
+                    return Err(format!("Not a directory: {}", self.root.display()));
+                }
+                let started = std::time::Instant::now();
+                self.add_tree(&self.root);
+                log::info!("Indexed {} files under {} in {:?}", self.len(), self.root.display(), started.elapsed());
+
+                let index = Arc::downgrade(self);
+                match watchers.subscribe(&self.root, DEFAULT_DEBOUNCE, move |changes| {
+                    if let Some(index) = Weak::upgrade(&index) {
+                        let paths: Vec<PathBuf> = changes.changes.iter().flat_map(|c| c.paths().cloned()).collect();
+                        index.apply_changes(&paths);
+                    }
+                }) {
+                    Ok(subscription) => *self.subscription.lock().unwrap_or_else(|e| e.into_inner()) = Some(subscription),
+                    // Still usable, just not kept up to date
+                    Err(e) => log::warn!("File index for {} won't update: {}", self.root.display(), e),
+                }
+                Ok(())
+            })
+            .clone()
+    }
+
+    /// Re-checks changed paths: gone ones are dropped with their children,
+    /// visible ones (re)added.
+    fn apply_changes(&self, paths: &[PathBuf]) {
+        for path in paths {
+            let Some(relative) = self.relative(path).filter(|r| !r.is_empty()) else {
+                continue;
+            };
+            let parent = relative.rsplit_once('/').map(|(parent, _)| parent);
+            if let Some(parent) = parent {
+                if !self.dirs.read().unwrap_or_else(|e| e.into_inner()).contains(parent) {
+                    // Inside an ignored directory, or one whose own event
+                    // hasn't been handled yet and will add this path
+                    continue;
+                }
+            }
+
+            self.remove(&relative);
+            if path.exists() && self.is_visible(path) {
+                if path.is_dir() {
+                    self.dirs.write().unwrap_or_else(|e| e.into_inner()).insert(relative);
+                    self.add_tree(path);
+                } else {
+                    let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+                    if files.len() < MAX_INDEXED_FILES {
+                        files.insert(relative);
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&self, relative: &str) {
+        let prefix = format!("{}/", relative);
+        let mut files = self.files.write().unwrap_or_else(|e| e.into_inner());
+        files.remove(relative);
+        let nested: Vec<String> = files.range(prefix.clone()..).take_while(|p| p.starts_with(&prefix)).cloned().collect();
+        for path in nested {
+            files.remove(&path);
+        }
+        drop(files);
+        let mut dirs = self.dirs.write().unwrap_or_else(|e| e.into_inner());
+        dirs.remove(relative);
+        dirs.retain(|d| !d.starts_with(&prefix));
+    }
+
+    /// Whether a walk of the parent directory includes `path`, i.e. no
+    /// ignore file or hidden-file rule excludes it.
+    fn is_visible(&self, path: &Path) -> bool {
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        Self::walker(parent)
+            .max_depth(Some(1))
+            .build()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.path() == path)
+    }
+
+    pub fn find(&self, query: &str, limit: usize) -> Vec<FileMatch> {
+        let query: Vec<char> = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+        let files = self.files.read().unwrap_or_else(|e| e.into_inner());
+        let files: Vec<&String> = files.iter().collect();
+
+        let mut matches: Vec<(i64, &String, Vec<usize>)> = files
+            .par_iter()
+            .filter_map(|path| fuzzy_match(&query, path).map(|(score, positions)| (score, *path, positions)))
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.len().cmp(&b.1.len())).then(a.1.cmp(b.1)));
+        matches.truncate(limit);
+
+        matches
+            .into_iter()
+            .map(|(score, relative, positions)| FileMatch {
+                path: self.root.join(relative).to_string_lossy().to_string(),
+                relative_path: relative.clone(),
+                score,
+                positions,
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Indexes {
+    by_root: HashMap<PathBuf, Arc<FileIndex>>,
+    /// Root each open session holds.
+    held: HashMap<String, PathBuf>,
+}
+
+/// One watched index per workspace root, shared by its sessions and
+/// dropped, watch subscription included, once the last of them releases it.
+#[derive(Default)]
+pub struct FileFinder {
+    indexes: Mutex<Indexes>,
+    watchers: Arc<WorkspaceWatchers>,
+}
+
+impl FileFinder {
+    /// Keeps indexes current through `watchers`, sharing its watch of
+    /// each root with the rest of the app.
+    pub fn new(watchers: Arc<WorkspaceWatchers>) -> Self {
+        Self {
+            indexes: Mutex::default(),
+            watchers,
+        }
+    }
+
+    /// Keeps the index for `root` alive until `release(session_id)`.
+    pub fn hold(&self, session_id: &str, root: &Path) -> Result<PathBuf, String> {
+        let root = root.canonicalize().map_err(|e| format!("Failed to open {}: {}", root.display(), e))?;
+        let mut indexes = self.indexes.lock().unwrap_or_else(|e| e.into_inner());
+        indexes.held.insert(session_id.to_string(), root.clone());
+        Ok(root)
+    }
+
+    pub fn release(&self, session_id: &str) {
+        let mut indexes = self.indexes.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(root) = indexes.held.remove(session_id) else {
+            return;
+        };
+        if !indexes.held.values().any(|held| *held == root) {
+            indexes.by_root.remove(&root);
+            log::debug!("Dropped file index for {}", root.display());
+        }
+    }
+
+    /// The index for `root`, scanning it on first use. Blocks while scanning.
+    /// Only roots some session holds are kept between calls.
+    pub fn index_for(&self, root: &Path) -> Result<Arc<FileIndex>, String> {
+        let root = root.canonicalize().map_err(|e| format!("Failed to open {}: {}", root.display(), e))?;
+        let index = {
+            let mut indexes = self.indexes.lock().unwrap_or_else(|e| e.into_inner());
+            if indexes.held.values().any(|held| *held == root) {
+                indexes
+                    .by_root
+                    .entry(root.clone())
+                    .or_insert_with(|| Arc::new(FileIndex::new(root)))
+                    .clone()
+            } else {
+                indexes.by_root.get(&root).cloned().unwrap_or_else(|| Arc::new(FileIndex::new(root)))
+            }
+        };
+        index.ensure_scanned(&self.watchers)?;
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    fn query(q: &str) -> Vec<char> {
+        q.chars().flat_map(|c| c.to_lowercase()).collect()
+    }
+
+    fn ranked(paths: &[&str], q: &str) -> Vec<String> {
+        let q = query(q);
+        let mut scored: Vec<(i64, &str)> = paths
+            .iter()
+            .filter_map(|p| fuzzy_match(&q, p).map(|(score, _)| (score, *p)))
+            .collect();
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        scored.into_iter().map(|(_, p)| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let paths = [
+            "src/components/filetree/FileTreeView.tsx",
+            "src/hooks/useFileTokens.ts",
+            "docs/fast-track/overview.md",
+        ];
+        assert_eq!(ranked(&paths, "ftv")[0], "src/components/filetree/FileTreeView.tsx");
+        assert_eq!(ranked(&paths, "hooks/uft")[0], "src/hooks/useFileTokens.ts");
+        assert!(ranked(&paths, "xyz").is_empty());
+
+        let (_, positions) = fuzzy_match(&query("FTV"), "FileTreeView.tsx").unwrap();
+        assert_eq!(positions, vec![0, 4, 8]);
+        // Segment starts beat matches buried inside words
+        let (segment, _) = fuzzy_match(&query("main"), "src/main.rs").unwrap();
+        let (buried, _) = fuzzy_match(&query("main"), "src/domain.rs").unwrap();
+        assert!(segment > buried);
+    }
+
+    fn wait_for(index: &FileIndex, check: impl Fn(&FileIndex) -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if check(index) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_index_respects_ignores_and_follows_changes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("target/lib.rs"), "").unwrap();
+
+        let finder = FileFinder::default();
+        finder.hold("session", root).unwrap();
+        let index = finder.index_for(root).unwrap();
+        let found = |index: &FileIndex, q: &str| -> Vec<String> {
+            index.find(q, 10).into_iter().map(|m| m.relative_path).collect()
+        };
+        assert_eq!(found(&index, "lib"), vec!["src/lib.rs"]);
+        assert!(Arc::ptr_eq(&index, &finder.index_for(root).unwrap()));
+
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("src/nested/mod.rs"), "").unwrap();
+        fs::write(root.join("target/new.rs"), "").unwrap();
+        assert!(wait_for(&index, |i| found(i, "nestedmod") == vec!["src/nested/mod.rs"]));
+
+        fs::remove_dir_all(root.join("src/nested")).unwrap();
+        assert!(wait_for(&index, |i| found(i, "nestedmod").is_empty()));
+        assert!(found(&index, "new").is_empty());
+    }
+
+    #[test]
+    fn test_index_dropped_with_last_session() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("lib.rs"), "").unwrap();
+
+        let watchers = Arc::new(WorkspaceWatchers::default());
+        let finder = FileFinder::new(watchers.clone());
+        finder.hold("a", root).unwrap();
+        finder.hold("b", root).unwrap();
+        let index = finder.index_for(root).unwrap();
+        finder.release("a");
+        assert!(Arc::ptr_eq(&index, &finder.index_for(root).unwrap()));
+        finder.release("b");
+        assert!(!Arc::ptr_eq(&index, &finder.index_for(root).unwrap()));
+        // Unheld roots are scanned for the caller but not kept
+        let weak = Arc::downgrade(&finder.index_for(root).unwrap());
+        assert!(weak.upgrade().is_none());
+        // The shared watch goes with the last index on the root
+        assert!(watchers.is_watching(&root.canonicalize().unwrap()));
+        drop(index);
+        assert!(!watchers.is_watching(&root.canonicalize().unwrap()));
+    }
+}
@@ -1,2 +1,6 @@
 pub mod codex;
+pub mod context_window;
+pub mod effective_config;
+pub mod file_finder;
 pub mod session;
+pub mod usage;
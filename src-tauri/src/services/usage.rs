@@ -0,0 +1,548 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::protocol::EventMsg;
+
+const LEDGER_FILE: &str = "usage.jsonl";
+const PRICES_FILE: &str = "prices.toml";
+
+/// Token counts as reported by the CLI. `cached_input_tokens` is the part of
+/// `input_tokens` served from the prompt cache and `reasoning_output_tokens`
+/// the part of `output_tokens` spent on reasoning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub cached_input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_output_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.cached_input_tokens += other.cached_input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.reasoning_output_tokens += other.reasoning_output_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == TokenUsage::default()
+    }
+
+    /// The usage of one response, from a final `token_count` event.
+    pub fn from_token_count(msg: &EventMsg) -> Option<Self> {
+        match msg {
+            EventMsg::TokenCount {
+                input_tokens,
+                cached_input_tokens,
+                output_tokens,
+                reasoning_output_tokens,
+                total_tokens,
+            } => Some(TokenUsage {
+                input_tokens: *input_tokens as u64,
+                cached_input_tokens: *cached_input_tokens as u64,
+                output_tokens: *output_tokens as u64,
+                reasoning_output_tokens: *reasoning_output_tokens as u64,
+                total_tokens: *total_tokens as u64,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// USD per million tokens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Provider id; applies to every provider when unset.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Exact model name, a prefix ending in `*`, or `*` for any model.
+    pub model: String,
+    pub input: f64,
+    /// Defaults to `input` when the provider has no cache discount.
+    #[serde(default)]
+    pub cached_input: Option<f64>,
+    pub output: f64,
+}
+
+impl ModelPrice {
+    fn cost(&self, usage: &TokenUsage) -> f64 {
+        let cached = usage.cached_input_tokens.min(usage.input_tokens);
+        let uncached = usage.input_tokens - cached;
+        (uncached as f64 * self.input
+            + cached as f64 * self.cached_input.unwrap_or(self.input)
+            + usage.output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+
+    /// Match strength against `provider`/`model`; higher wins, `None` if it
+    /// doesn't apply.
+    fn rank(&self, provider: &str, model: &str) -> Option<usize> {
+        let provider_bonus = match &self.provider {
+            Some(p) if p.eq_ignore_ascii_case(provider) => 1,
+            Some(_) => return None,
+            None => 0,
+        };
+        let pattern = self.model.to_lowercase();
+        let model = model.to_lowercase();
+        let score = if pattern == model {
+            1 << 16
+        } else if let Some(prefix) = pattern.strip_suffix('*') {
+            if !model.starts_with(prefix) {
+                return None;
+            }
+            prefix.len() + 1
+        } else {
+            return None;
+        };
+        Some(score * 2 + provider_bonus)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceTable {
+    #[serde(default, rename = "price")]
+    pub prices: Vec<ModelPrice>,
+}
+
+impl PriceTable {
+    /// Published list prices for common models. Entries in `prices.toml`
+    /// take precedence.
+    pub fn builtin() -> Self {
+        let price = |provider: Option<&str>, model: &str, input: f64, cached_input: f64, output: f64| ModelPrice {
+            provider: provider.map(|p| p.to_string()),
+            model: model.to_string(),
+            input,
+            cached_input: Some(cached_input),
+            output,
+        };
+        Self {
+            prices: vec![
+                price(None, "gpt-5*", 1.25, 0.125, 10.0),
+                price(None, "gpt-5-mini*", 0.25, 0.025, 2.0),
+                price(None, "gpt-5-nano*", 0.05, 0.005, 0.4),
+                price(None, "gpt-4.1*", 2.0, 0.5, 8.0),
+                price(None, "gpt-4.1-mini*", 0.4, 0.1, 1.6),
+                price(None, "gpt-4o*", 2.5, 1.25, 10.0),
+                price(None, "gpt-4o-mini*", 0.15, 0.075, 0.6),
+                price(None, "o3*", 2.0, 0.5, 8.0),
+                price(None, "o4-mini*", 1.1, 0.275, 4.4),
+                price(None, "codex-mini*", 1.5, 0.375, 6.0),
+                price(Some("ollama"), "*", 0.0, 0.0, 0.0),
+            ],
+        }
+    }
+
+    /// Built-in prices overlaid with `prices.toml` in `usage_dir`, if present.
+    pub fn load(usage_dir: &Path) -> Result<Self, String> {
+        let mut table = Self::builtin();
+        let path = usage_dir.join(PRICES_FILE);
+        if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let custom: PriceTable =
+                toml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+            // Later entries win ties in `lookup`, so custom prices go last.
+            table.prices.extend(custom.prices);
+        }
+        Ok(table)
+    }
+
+    pub fn lookup(&self, provider: &str, model: &str) -> Option<&ModelPrice> {
+        self.prices
+            .iter()
+            .filter_map(|p| p.rank(provider, model).map(|rank| (rank, p)))
+            .fold(None, |best: Option<(usize, &ModelPrice)>, (rank, p)| match best {
+                Some((best_rank, _)) if best_rank > rank => best,
+                _ => Some((rank, p)),
+            })
+            .map(|(_, p)| p)
+    }
+}
+
+/// One finished turn in the ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    pub turn: u32,
+    pub provider: String,
+    pub model: String,
+    pub project: String,
+    pub usage: TokenUsage,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageRange {
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl UsageRange {
+    fn contains(&self, at: &DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| *at >= since) && self.until.is_none_or(|until| *at < until)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGroupBy {
+    Turn,
+    Session,
+    Model,
+    Project,
+    Day,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub usage: TokenUsage,
+    pub turns: u32,
+    pub cost_usd: f64,
+    /// Turns whose model has no price; their tokens aren't in `cost_usd`.
+    pub unpriced_turns: u32,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord, prices: &PriceTable) {
+        self.usage.add(&record.usage);
+        self.turns += 1;
+        match prices.lookup(&record.provider, &record.model) {
+            Some(price) => self.cost_usd += price.cost(&record.usage),
+            None => self.unpriced_turns += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub range: UsageRange,
+    pub group_by: UsageGroupBy,
+    pub groups: Vec<UsageGroup>,
+    pub total: UsageTotals,
+}
+
+/// Running totals of a live session.
+#[derive(Debug, Clone)]
+struct SessionUsage {
+    provider: String,
+    model: String,
+    project: String,
+    turn: u32,
+    /// Counted usage of the turn in progress.
+    current: TokenUsage,
+    /// Latest `token_count_update` snapshot of the response in progress;
+    /// replaced by the final `token_count`.
+    pending: Option<TokenUsage>,
+    total: TokenUsage,
+}
+
+impl SessionUsage {
+    fn turn_usage(&self) -> TokenUsage {
+        let mut usage = self.current;
+        if let Some(pending) = &self.pending {
+            usage.add(pending);
+        }
+        usage
+    }
+
+    fn record(&self, session_id: &str) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc::now(),
+            session_id: session_id.to_string(),
+            turn: self.turn,
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            project: self.project.clone(),
+            usage: self.turn_usage(),
+        }
+    }
+}
+
+/// Accumulates TokenCount events per session and turn and appends each
+/// finished turn to `usage.jsonl`.
+pub struct UsageTracker {
+    dir: PathBuf,
+    sessions: Mutex<HashMap<String, SessionUsage>>,
+}
+
+impl UsageTracker {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `~/.codex/usage`
+    pub fn default_dir() -> PathBuf {
+        dirs::home_dir().unwrap_or_default().join(".codex").join("usage")
+    }
+
+    pub fn start_session(&self, session_id: &str, provider: &str, model: &str, project: &str) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.insert(
+            session_id.to_string(),
+            SessionUsage {
+                provider: provider.to_string(),
+                model: model.to_string(),
+                project: project.to_string(),
+                turn: 0,
+                current: TokenUsage::default(),
+                pending: None,
+                total: TokenUsage::default(),
+            },
+        );
+    }
+
+    /// Feeds one CLI event. Returns the session's total usage so far when the
+    /// event changed it.
+    pub fn record_event(&self, session_id: &str, msg: &EventMsg) -> Option<TokenUsage> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let session = sessions.get_mut(session_id)?;
+        match msg {
+            EventMsg::SessionConfigured { model, .. } => {
+                session.model = model.clone();
+                None
+            }
+            EventMsg::TaskStarted | EventMsg::TurnStarted => {
+                self.finish_turn(session_id, session);
+                None
+            }
+            EventMsg::TokenCount { .. } => {
+                let usage = TokenUsage::from_token_count(msg)?;
+                session.pending = None;
+                session.current.add(&usage);
+                session.total.add(&usage);
+                Some(session.total)
+            }
+            EventMsg::TokenCountUpdate {
+                input_tokens,
+                cached_input_tokens,
+                output_tokens,
+                reasoning_output_tokens,
+                total_tokens,
+            } => {
+                let previous = session.pending.unwrap_or_default();
+                let field = |value: &Option<u32>, previous: u64| value.map(|v| v as u64).unwrap_or(previous);
+                session.pending = Some(TokenUsage {
+                    input_tokens: field(input_tokens, previous.input_tokens),
+                    cached_input_tokens: field(cached_input_tokens, previous.cached_input_tokens),
+                    output_tokens: field(output_tokens, previous.output_tokens),
+                    reasoning_output_tokens: field(reasoning_output_tokens, previous.reasoning_output_tokens),
+                    total_tokens: field(total_tokens, previous.total_tokens),
+                });
+                let mut total = session.total;
+                total.add(session.pending.as_ref().unwrap_or(&previous));
+                Some(total)
+            }
+            EventMsg::TaskComplete { .. }
+            | EventMsg::TurnComplete { .. }
+            | EventMsg::TurnAborted
+            | EventMsg::ShutdownComplete => {
+                self.finish_turn(session_id, session);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Flushes the turn in progress and forgets the session.
+    pub fn end_session(&self, session_id: &str) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(mut session) = sessions.remove(session_id) {
+            self.finish_turn(session_id, &mut session);
+        }
+    }
+
+    fn finish_turn(&self, session_id: &str, session: &mut SessionUsage) {
+        let usage = session.turn_usage();
+        if usage.is_empty() {
+            return;
+        }
+        if let Err(e) = self.append(&session.record(session_id)) {
+            log::warn!("Failed to persist token usage for {}: {}", session_id, e);
+        }
+        if let Some(pending) = session.pending.take() {
+            session.total.add(&pending);
+        }
+        session.turn += 1;
+        session.current = TokenUsage::default();
+    }
+
+    fn append(&self, record: &UsageRecord) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.dir.join(LEDGER_FILE))?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    fn load_records(&self) -> Result<Vec<UsageRecord>, String> {
+        let path = self.dir.join(LEDGER_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read usage ledger: {}", e))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::warn!("Skipping malformed usage record: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Totals over finished turns in `range`, plus turns still in progress.
+    /// Costs use the current price table.
+    pub fn report(&self, range: UsageRange, group_by: UsageGroupBy) -> Result<UsageReport, String> {
+        let prices = PriceTable::load(&self.dir)?;
+        let mut records = self.load_records()?;
+        {
+            let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+            records.extend(
+                sessions
+                    .iter()
+                    .filter(|(_, session)| !session.turn_usage().is_empty())
+                    .map(|(id, session)| session.record(id)),
+            );
+        }
+
+        let mut groups: BTreeMap<String, UsageTotals> = BTreeMap::new();
+        let mut total = UsageTotals::default();
+        for record in records.iter().filter(|r| range.contains(&r.timestamp)) {
+            let key = match group_by {
+                UsageGroupBy::Turn => format!("{}#{}", record.session_id, record.turn),
+                UsageGroupBy::Session => record.session_id.clone(),
+                UsageGroupBy::Model => format!("{}/{}", record.provider, record.model),
+                UsageGroupBy::Project => record.project.clone(),
+                UsageGroupBy::Day => record.timestamp.format("%Y-%m-%d").to_string(),
+            };
+            groups.entry(key).or_default().add(record, &prices);
+            total.add(record, &prices);
+        }
+
+        Ok(UsageReport {
+            range,
+            group_by,
+            groups: groups.into_iter().map(|(key, totals)| UsageGroup { key, totals }).collect(),
+            total,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn token_count(input: u32, cached: u32, output: u32) -> EventMsg {
+        EventMsg::TokenCount {
+            input_tokens: input,
+            cached_input_tokens: cached,
+            output_tokens: output,
+            reasoning_output_tokens: 0,
+            total_tokens: input + output,
+        }
+    }
+
+    #[test]
+    fn test_turns_are_persisted_and_grouped() {
+        let dir = tempdir().unwrap();
+        let tracker = UsageTracker::new(dir.path().to_path_buf());
+        tracker.start_session("s1", "openai", "gpt-5", "/work/app");
+
+        tracker.record_event("s1", &EventMsg::TaskStarted);
+        tracker.record_event("s1", &token_count(1_000_000, 0, 0));
+        let total = tracker.record_event("s1", &token_count(0, 0, 100_000)).unwrap();
+        assert_eq!(total.total_tokens, 1_100_000);
+        tracker.record_event("s1", &EventMsg::TaskComplete { response_id: None, last_agent_message: None });
+
+        // A streaming update is replaced by the final count, not added to it.
+        tracker.record_event("s1", &EventMsg::TaskStarted);
+        tracker.record_event(
+            "s1",
+            &EventMsg::TokenCountUpdate {
+                input_tokens: Some(500),
+                cached_input_tokens: None,
+                output_tokens: None,
+                reasoning_output_tokens: None,
+                total_tokens: Some(500),
+            },
+        );
+        tracker.record_event("s1", &token_count(1_000_000, 1_000_000, 0));
+        tracker.end_session("s1");
+
+        let reopened = UsageTracker::new(dir.path().to_path_buf());
+        let report = reopened.report(UsageRange::default(), UsageGroupBy::Turn).unwrap();
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].key, "s1#0");
+        assert_eq!(report.groups[1].totals.usage.input_tokens, 1_000_000);
+        assert_eq!(report.total.turns, 2);
+        // 1M input at $1.25, 100k output at $10/M, 1M cached input at $0.125
+        assert!((report.total.cost_usd - 2.375).abs() < 1e-9);
+
+        let by_project = reopened.report(UsageRange::default(), UsageGroupBy::Project).unwrap();
+        assert_eq!(by_project.groups.len(), 1);
+        assert_eq!(by_project.groups[0].key, "/work/app");
+
+        let future = UsageRange {
+            since: Some(Utc::now() + chrono::Duration::days(1)),
+            until: None,
+        };
+        assert_eq!(reopened.report(future, UsageGroupBy::Model).unwrap().total.turns, 0);
+    }
+
+    #[test]
+    fn test_price_lookup_and_overrides() {
+        let dir = tempdir().unwrap();
+        let builtin = PriceTable::builtin();
+        assert_eq!(builtin.lookup("openai", "gpt-5-mini-2025-08-07").unwrap().model, "gpt-5-mini*");
+        assert_eq!(builtin.lookup("ollama", "llama3").unwrap().input, 0.0);
+        assert!(builtin.lookup("openrouter", "mistral-large").is_none());
+
+        std::fs::write(
+            dir.path().join(PRICES_FILE),
+            "[[price]]\nprovider = \"azure\"\nmodel = \"gpt-5\"\ninput = 2.0\noutput = 12.0\n",
+        )
+        .unwrap();
+        let table = PriceTable::load(dir.path()).unwrap();
+        let azure = table.lookup("azure", "gpt-5").unwrap();
+        assert_eq!(azure.input, 2.0);
+        assert_eq!(azure.cost(&TokenUsage { input_tokens: 1_000_000, cached_input_tokens: 1_000_000, ..Default::default() }), 2.0);
+        assert_eq!(table.lookup("openai", "gpt-5").unwrap().input, 1.25);
+    }
+
+    #[test]
+    fn test_unpriced_models_are_counted() {
+        let dir = tempdir().unwrap();
+        let tracker = UsageTracker::new(dir.path().to_path_buf());
+        tracker.start_session("s1", "openrouter", "mistral-large", "/p");
+        tracker.record_event("s1", &token_count(10, 0, 5));
+
+        // Still in progress, but already part of the report.
+        let report = tracker.report(UsageRange::default(), UsageGroupBy::Model).unwrap();
+        assert_eq!(report.groups[0].key, "openrouter/mistral-large");
+        assert_eq!(report.total.unpriced_turns, 1);
+        assert_eq!(report.total.cost_usd, 0.0);
+    }
+}
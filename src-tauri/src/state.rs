@@ -1,16 +1,63 @@
+use crate::auth::credential_store::SecretBackend;
+use crate::auth::jwt::JwksCache;
+use crate::auth::oauth_server::{configured_issuer, ShutdownHandle};
 use crate::codex_client::CodexClient;
+use crate::filesystem::file_analysis::TokenCountCache;
+use crate::filesystem::file_range::TailHandle;
+use crate::filesystem::watcher::WorkspaceWatchers;
+use crate::providers::ModelCatalog;
+use crate::services::file_finder::FileFinder;
+use crate::services::usage::UsageTracker;
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 pub struct CodexState {
     pub sessions: Arc<Mutex<HashMap<String, CodexClient>>>,
+    pub model_catalog: Arc<ModelCatalog>,
+    /// The browser login in progress, if any.
+    pub login_server: Arc<Mutex<Option<ShutdownHandle>>>,
+    /// Cancel flag of the device-code login being polled, if any.
+    pub device_login: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+    /// Key source for the encrypted credential store once unlocked.
+    pub credential_backend: Arc<Mutex<Option<Arc<dyn SecretBackend>>>>,
+    /// OAuth issuer for logins, refreshes and token checks.
+    pub issuer: String,
+    /// Issuer signing keys for optional ID token signature checks.
+    pub jwks: Arc<JwksCache>,
+    /// Token usage of running sessions and the persisted ledger.
+    pub usage: Arc<UsageTracker>,
+    /// Per-file token counts keyed by mtime and size.
+    pub token_cache: Arc<TokenCountCache>,
+    /// Watched file indexes of session working directories.
+    pub file_finder: Arc<FileFinder>,
+    /// Cancel flags of running workspace searches by id.
+    pub searches: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    /// Running file tails by id.
+    pub tails: Arc<Mutex<HashMap<String, TailHandle>>>,
+    /// Live change feeds of the workspaces open in the UI.
+    pub workspace_watchers: Arc<WorkspaceWatchers>,
 }
 
 impl CodexState {
     pub fn new() -> Self {
+        let issuer = configured_issuer();
+        let workspace_watchers = Arc::new(WorkspaceWatchers::default());
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            model_catalog: Arc::new(ModelCatalog::default()),
+            login_server: Arc::new(Mutex::new(None)),
+            device_login: Arc::new(Mutex::new(None)),
+            credential_backend: Arc::new(Mutex::new(None)),
+            jwks: Arc::new(JwksCache::new(&issuer)),
+            issuer,
+            usage: Arc::new(UsageTracker::new(UsageTracker::default_dir())),
+            token_cache: Arc::new(TokenCountCache::default()),
+            file_finder: Arc::new(FileFinder::new(workspace_watchers.clone())),
+            searches: Arc::new(Mutex::new(HashMap::new())),
+            tails: Arc::new(Mutex::new(HashMap::new())),
+            workspace_watchers,
         }
     }
 }
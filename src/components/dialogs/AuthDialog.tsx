@@ -13,6 +13,7 @@ import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { Input } from "@/components/ui/input";
 import { Badge } from "@/components/ui/badge";
 import { User, Key, LogIn, LogOut, Check, AlertCircle } from "lucide-react";
+import type { AuthStatus as BackendAuthStatus } from "@/types/auth";
 
 interface AuthStatus {
   type: 'none' | 'api_key' | 'chatgpt';
@@ -45,31 +46,23 @@ export function AuthDialog() {
         console.log('💻 Running in browser development mode');
       }
       
-      const status = await safeInvoke('get_auth_status') as string | null;
+      const status = await safeInvoke('get_auth_status') as BackendAuthStatus | null;
       console.log('✅ Auth status received:', status);
       
       if (!status) {
         setAuthStatus({ type: 'none' });
-      } else if (status === 'api_key') {
+      } else if (status.state === 'locked') {
+        setAuthStatus({ type: 'none' });
+        setError('Credentials are encrypted; unlock them to continue');
+      } else if (status.mode === 'api_key') {
         setAuthStatus({ type: 'api_key' });
-      } else if (status.startsWith('chatgpt:')) {
-        const parts = status.split(':');
-        if (parts.length === 3) {
-          const [, email, plan] = parts;
-          setAuthStatus({ 
-            type: 'chatgpt', 
-            email, 
-            plan, 
-            valid: true 
-          });
-        } else if (parts[1] === 'invalid') {
-          setAuthStatus({ 
-            type: 'chatgpt', 
-            valid: false 
-          });
-        } else {
-          setAuthStatus({ type: 'chatgpt', valid: true });
-        }
+      } else {
+        setAuthStatus({ 
+          type: 'chatgpt', 
+          email: status.email ?? undefined, 
+          plan: status.plan ?? undefined, 
+          valid: status.state === 'valid' 
+        });
       }
     } catch (error) {
       console.error('❌ Failed to check auth status:', error);
@@ -99,8 +92,8 @@ export function AuthDialog() {
       // Poll for completion (in a real app, you'd use events)
       const pollForCompletion = setInterval(async () => {
         try {
-          const newStatus = await safeInvoke('get_auth_status') as string | null;
-          if (newStatus && newStatus.startsWith('chatgpt:')) {
+          const newStatus = await safeInvoke('get_auth_status') as BackendAuthStatus | null;
+          if (newStatus?.mode === 'chatgpt' && newStatus.state === 'valid') {
             console.log('✅ OAuth completion detected:', newStatus);
             clearInterval(pollForCompletion);
             await checkAuthStatus();
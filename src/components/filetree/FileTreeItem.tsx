@@ -42,7 +42,7 @@ interface FileTreeItemProps {
   onAddToChat: (path: string) => void;
   onFileClick: (path: string, isDirectory: boolean) => void;
   onSetWorkingFolder: (path: string) => void;
-  onCalculateTokens: (path: string) => Promise<number | null>;
+  onCalculateTokens: (path: string, isDirectory?: boolean) => Promise<number | null>;
   isFiltered: (entry: FileEntry) => boolean;
   showAddButton?: boolean;
   onRemoveFromChat?: (path: string) => void;
@@ -126,9 +126,9 @@ export function FileTreeItem({
   };
 
   const handleMouseEnter = async () => {
-    if (!entry.is_directory && tokens === null && !loadingTokens) {
+    if (tokens === null && !loadingTokens) {
       setLoadingTokens(true);
-      const calculatedTokens = await onCalculateTokens(entry.path);
+      const calculatedTokens = await onCalculateTokens(entry.path, entry.is_directory);
       setTokens(calculatedTokens);
       setLoadingTokens(false);
     }
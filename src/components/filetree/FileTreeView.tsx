@@ -4,6 +4,7 @@ import { useSettingsStore } from "@/stores/SettingsStore";
 import { useFolderStore } from "@/stores/FolderStore";
 import { useContextFilesStore } from "@/stores/ContextFilesStore";
 import { useFileTokens } from "@/hooks/useFileTokens";
+import { useWorkspaceChanges } from "@/hooks/useWorkspaceChanges";
 import { FileTreeHeader } from "./FileTreeHeader";
 import { FileTreeItem } from "./FileTreeItem";
 
@@ -37,7 +38,7 @@ export function FileTree({
   const { excludeFolders } = useSettingsStore();
   const { setCurrentFolder } = useFolderStore();
   const { addFile, clearFiles } = useContextFilesStore();
-  const { calculateTokens } = useFileTokens();
+  const { calculateTokens, calculateBatchTokens } = useFileTokens();
 
   const loadDirectory = async (path?: string) => {
     setLoading(true);
@@ -55,6 +56,11 @@ export function FileTree({
         path: targetPath,
       });
       setEntries(result);
+      // Count the listed files in one go so hovering shows them at once
+      const files = result.filter((entry) => !entry.is_directory).map((entry) => entry.path);
+      if (files.length > 0) {
+        calculateBatchTokens(files).catch((err) => console.warn("Failed to count tokens:", err));
+      }
     } catch (err) {
       setError(err as string);
     } finally {
@@ -115,6 +121,13 @@ export function FileTree({
     loadDirectory();
   }, [currentFolder]);
 
+  useWorkspaceChanges(currentFolder, (changes) => {
+    // Only entry lists change the tree; content edits don't
+    if (changes.changes.some((change) => change.kind !== "modified")) {
+      loadDirectory();
+    }
+  });
+
   if (loading && entries.length === 0) {
     return (
       <div className="p-4 text-center text-gray-500">Loading files...</div>
@@ -1,11 +1,39 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { ask } from "@tauri-apps/plugin-dialog";
 import { Button } from "@/components/ui/button";
-import { X, Copy, Check, Sun, Moon, Send, FileText, GitBranch, Code } from "lucide-react";
+import { X, Copy, Check, Sun, Moon, Send, FileText, GitBranch, Code, ScrollText } from "lucide-react";
 import { CodeEditor } from "./CodeEditor";
 import { DiffViewer } from "./DiffViewer";
 import { useEditorStore } from "@/stores/EditorStore";
 import { useConversationStore } from "@/stores/ConversationStore";
+import { useFolderStore } from "@/stores/FolderStore";
+import { useWorkspaceChanges } from "@/hooks/useWorkspaceChanges";
+import type {
+  FileChunk,
+  FileContent,
+  FileTail,
+  FileTailEvent,
+  FileWriteError,
+  TextEncoding,
+  WrittenFile,
+} from "@/types/fileio";
+
+// Bytes fetched per "Load more"; the backend caps ranges at 1 MB
+const CHUNK_BYTES = 1024 * 1024;
+const TAIL_LINES = 200;
+const ENCODING_NAMES: Record<TextEncoding, string> = {
+  utf8: "UTF-8",
+  utf16_le: "UTF-16 LE",
+  utf16_be: "UTF-16 BE",
+  latin1: "Latin-1",
+};
+
+type FileMeta = Omit<FileContent, "content" | "version">;
+
+const formatBytes = (bytes: number) =>
+  bytes < 1024 * 1024 ? `${(bytes / 1024).toFixed(1)} KB` : `${(bytes / 1024 / 1024).toFixed(1)} MB`;
 
 interface FileViewerProps {
   filePath: string | null;
@@ -21,6 +49,17 @@ interface GitDiff {
 
 export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps) {
   const [content, setContent] = useState<string>("");
+  // Version of `content` on disk; null for converted formats
+  const [version, setVersion] = useState<string | null>(null);
+  // Size, encoding and type as read; null for converted formats
+  const [fileMeta, setFileMeta] = useState<FileMeta | null>(null);
+  // End of the bytes loaded so far, for files read in chunks
+  const [loadedBytes, setLoadedBytes] = useState(0);
+  const [loadingMore, setLoadingMore] = useState(false);
+  const [tailing, setTailing] = useState(false);
+  const [reloadKey, setReloadKey] = useState(0);
+  // filePath as workspace-changed events spell it
+  const [canonicalPath, setCanonicalPath] = useState<string | null>(null);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [copied, setCopied] = useState(false);
@@ -32,6 +71,7 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
   const [diffLoading, setDiffLoading] = useState(false);
   const { isDarkTheme, setIsDarkTheme } = useEditorStore();
   const { createConversation, addMessage, currentConversationId } = useConversationStore();
+  const { currentFolder } = useFolderStore();
 
   const getFileName = () => {
     if (!filePath) return "";
@@ -45,6 +85,22 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
     return lastDot > -1 ? fileName.substring(lastDot + 1).toLowerCase() : "";
   };
 
+  const applyFile = (file: FileContent) => {
+    setVersion(file.version);
+    setFileMeta({
+      modified: file.modified,
+      size: file.size,
+      length: file.length,
+      mime_type: file.mime_type,
+      encoding: file.encoding,
+    });
+    setLoadedBytes(file.length);
+  };
+
+  useEffect(() => {
+    setTailing(false);
+  }, [filePath]);
+
   useEffect(() => {
     if (!filePath) {
       setContent("");
@@ -59,6 +115,8 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
       setError(null);
       setGitDiff(null);
       setViewMode('code');
+      setVersion(null);
+      setFileMeta(null);
 
       try {
         const extension = getFileExtension();
@@ -80,9 +138,12 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
               filePath,
             });
             break;
-          default:
-            fileContent = await invoke<string>("read_file", { filePath });
+          default: {
+            const file = await invoke<FileContent>("read_file", { filePath });
+            fileContent = file.content;
+            applyFile(file);
             break;
+          }
         }
 
         setContent(fileContent);
@@ -95,12 +156,104 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
     };
 
     loadFile();
-  }, [filePath]);
+  }, [filePath, reloadKey]);
 
   useEffect(() => {
     setCurrentContent(content);
   }, [content]);
 
+  useEffect(() => {
+    setCanonicalPath(null);
+    if (!filePath) return;
+    let disposed = false;
+    invoke<string>("canonicalize_path", { path: filePath })
+      .then((path) => {
+        if (!disposed) setCanonicalPath(path);
+      })
+      .catch(() => {});
+    return () => {
+      disposed = true;
+    };
+  }, [filePath]);
+
+  // Pick up changes made on disk, unless there are unsaved edits
+  useWorkspaceChanges(currentFolder ?? undefined, async (changes) => {
+    if (!filePath || !canonicalPath || tailing || currentContent !== content) return;
+    const changed = changes.changes.some(
+      (change) => change.path === canonicalPath && change.kind !== "deleted",
+    );
+    if (!changed || ["pdf", "csv", "xlsx"].includes(getFileExtension())) return;
+    try {
+      const file = await invoke<FileContent>("read_file", { filePath });
+      if (file.version === version) return;
+      setContent(file.content);
+      applyFile(file);
+    } catch (err) {
+      console.error("Failed to reload file:", err);
+    }
+  });
+
+  // Follow appended text; stopped when toggled off, on another file or unmount
+  useEffect(() => {
+    if (!tailing || !filePath) return;
+    let tailId: string | null = null;
+    let disposed = false;
+
+    const unlistenPromise = listen<FileTailEvent>("file-tail", (event) => {
+      if (event.payload.tail_id !== tailId) return;
+      const { text, reset } = event.payload;
+      setContent((prev) => (reset ? text : prev + text));
+    });
+    invoke<FileTail>("tail_file", { filePath, lines: TAIL_LINES })
+      .then((tail) => {
+        tailId = tail.tail_id;
+        if (disposed) {
+          invoke("stop_tail", { tailId }).catch(() => {});
+        } else if (tail.chunk.kind === "text") {
+          setContent(tail.chunk.text);
+        }
+      })
+      .catch((err) => {
+        console.error("Failed to tail file:", err);
+        setTailing(false);
+      });
+
+    return () => {
+      disposed = true;
+      unlistenPromise.then((unlisten) => unlisten());
+      if (tailId) {
+        invoke("stop_tail", { tailId }).catch(() => {});
+      }
+    };
+  }, [tailing, filePath]);
+
+  const handleToggleTail = () => {
+    if (tailing) {
+      // Back to the start of the file
+      setReloadKey((key) => key + 1);
+    }
+    setTailing(!tailing);
+  };
+
+  const handleLoadMore = async () => {
+    if (!filePath) return;
+    setLoadingMore(true);
+    try {
+      const chunk = await invoke<FileChunk>("read_file_range", {
+        filePath,
+        offset: loadedBytes,
+        length: CHUNK_BYTES,
+      });
+      if (chunk.kind !== "text") return;
+      setContent((prev) => prev + chunk.text);
+      setLoadedBytes(chunk.offset + chunk.length);
+    } catch (err) {
+      console.error("Failed to load more of the file:", err);
+    } finally {
+      setLoadingMore(false);
+    }
+  };
+
   const loadGitDiff = async () => {
     if (!filePath) return;
     
@@ -140,17 +293,46 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
 
   const handleSave = async (newContent: string) => {
     if (!filePath) return;
+    if (version === null) {
+      throw new Error("Only UTF-8 text files that are fully loaded can be saved.");
+    }
     
-    try {
-      await invoke("write_file", { 
-        filePath, 
-        content: newContent 
+    const save = (confirmOutsideWorkspace: boolean) =>
+      invoke<WrittenFile>("write_file", {
+        filePath,
+        content: newContent,
+        expectedVersion: version,
+        confirmOutsideWorkspace,
       });
+
+    try {
+      let written: WrittenFile;
+      try {
+        written = await save(false);
+      } catch (err) {
+        const error = err as FileWriteError;
+        if (error.kind !== "outside_workspace") throw err;
+        const confirmed = await ask(
+          `${error.path} is outside your trusted projects. Save it anyway?`,
+          { title: "Save outside project", kind: "warning" },
+        );
+        if (!confirmed) return;
+        written = await save(true);
+      }
+      setVersion(written.version);
       setContent(newContent);
       setCurrentContent(newContent);
     } catch (err) {
       console.error("Failed to save file:", err);
-      throw new Error(`Failed to save file: ${err}`);
+      const error = err as FileWriteError;
+      if (error.kind === "conflict") {
+        throw new Error("The file changed on disk since it was opened. Reload it before saving.");
+      }
+      if (error.kind === "denied") {
+        throw new Error(`Saving ${error.path} is blocked by the write deny list (${error.pattern}).`);
+      }
+      const message = error.kind === "not_allowed" || error.kind === "io" ? error.message : String(err);
+      throw new Error(`Failed to save file: ${message}`);
     }
   };
 
@@ -206,6 +388,9 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
     return lines.slice(0, MAX_LINES).join("\n");
   })();
 
+  const isBinary = fileMeta !== null && fileMeta.encoding === null;
+  const isPartial = fileMeta !== null && !isBinary && loadedBytes < fileMeta.size;
+
   if (!filePath) return null;
 
   return (
@@ -233,6 +418,17 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
               {showFullContent ? "500" : "All"}
             </Button>
           )}
+          {fileMeta && !isBinary && (
+            <Button
+              variant={tailing ? "secondary" : "ghost"}
+              size="sm"
+              onClick={handleToggleTail}
+              className="p-1 h-auto"
+              title={tailing ? "Stop following the file" : "Follow the end of the file"}
+            >
+              <ScrollText className="w-4 h-4" />
+            </Button>
+          )}
           <Button
             variant="ghost"
             size="sm"
@@ -343,31 +539,61 @@ export function FileViewer({ filePath, onClose, addToNotepad }: FileViewerProps)
                 </div>
               )
             ) : (
-              <>
-                <CodeEditor
-                  content={displayContent}
-                  filePath={filePath}
-                  onContentChange={handleContentChange}
-                  onSave={handleSave}
-                  onSelectionChange={handleSelectionChange}
-                  className="flex-1"
-                />
-                {isLargeFile && !showFullContent && (
-                  <div className="p-4 text-center border-t border-gray-200 bg-gray-50">
-                    <p className="text-sm text-gray-600 mb-2">
-                      Showing first {MAX_LINES} lines of{" "}
-                      {content.split("\n").length} total lines
-                    </p>
-                    <Button
-                      variant="outline"
-                      size="sm"
-                      onClick={handleToggleContent}
-                    >
-                      Show All Lines
-                    </Button>
+              isBinary && fileMeta ? (
+                <>
+                  <div className="px-3 py-2 text-xs text-gray-600 border-b border-gray-200 bg-gray-50">
+                    Binary file ({fileMeta.mime_type}, {formatBytes(fileMeta.size)})
                   </div>
-                )}
-              </>
+                  <pre className="flex-1 overflow-auto p-3 font-mono text-xs">{content}</pre>
+                </>
+              ) : (
+                <>
+                  {fileMeta?.encoding && fileMeta.encoding !== "utf8" && (
+                    <div className="px-3 py-2 text-xs text-gray-600 border-b border-gray-200 bg-gray-50">
+                      Decoded as {ENCODING_NAMES[fileMeta.encoding]}; read-only
+                    </div>
+                  )}
+                  <CodeEditor
+                    content={displayContent}
+                    filePath={filePath}
+                    isReadOnly={version === null || tailing}
+                    onContentChange={handleContentChange}
+                    onSave={handleSave}
+                    onSelectionChange={handleSelectionChange}
+                    className="flex-1"
+                  />
+                  {isLargeFile && !showFullContent && (
+                    <div className="p-4 text-center border-t border-gray-200 bg-gray-50">
+                      <p className="text-sm text-gray-600 mb-2">
+                        Showing first {MAX_LINES} lines of{" "}
+                        {content.split("\n").length} total lines
+                      </p>
+                      <Button
+                        variant="outline"
+                        size="sm"
+                        onClick={handleToggleContent}
+                      >
+                        Show All Lines
+                      </Button>
+                    </div>
+                  )}
+                  {isPartial && fileMeta && !tailing && (
+                    <div className="p-4 text-center border-t border-gray-200 bg-gray-50">
+                      <p className="text-sm text-gray-600 mb-2">
+                        Showing the first {formatBytes(loadedBytes)} of {formatBytes(fileMeta.size)}
+                      </p>
+                      <Button
+                        variant="outline"
+                        size="sm"
+                        onClick={handleLoadMore}
+                        disabled={loadingMore}
+                      >
+                        {loadingMore ? "Loading..." : "Load More"}
+                      </Button>
+                    </div>
+                  )}
+                </>
+              )
             )}
           </div>
         )}
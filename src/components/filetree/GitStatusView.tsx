@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useFolderStore } from "@/stores/FolderStore";
+import { useWorkspaceChanges } from "@/hooks/useWorkspaceChanges";
 import { RefreshCw, GitBranch, FileText } from "lucide-react";
 import { Button } from "@/components/ui/button";
 
@@ -30,8 +31,9 @@ export function GitStatusView({ currentFolder, onDiffClick }: GitStatusViewProps
 	const [error, setError] = useState<string | null>(null);
 	const { currentFolder: storeFolder } = useFolderStore();
 
-	const loadGitStatus = async (path?: string) => {
-		setLoading(true);
+	// `silent` refreshes in place without the loading state
+	const loadGitStatus = async (path?: string, silent = false) => {
+		setLoading(!silent);
 		setError(null);
 
 		try {
@@ -56,6 +58,12 @@ export function GitStatusView({ currentFolder, onDiffClick }: GitStatusViewProps
 		loadGitStatus();
 	}, [currentFolder, storeFolder]);
 
+	useWorkspaceChanges(currentFolder || storeFolder || undefined, (changes) => {
+		if (changes.git_changed) {
+			loadGitStatus(undefined, true);
+		}
+	});
+
 	if (loading) {
 		return (
 			<div className="p-4 text-center text-gray-500 flex items-center justify-center gap-2">
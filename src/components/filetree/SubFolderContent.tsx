@@ -18,7 +18,7 @@ interface SubFolderContentProps {
   onAddToChat: (path: string) => void;
   onFileClick: (path: string, isDirectory: boolean) => void;
   onSetWorkingFolder: (path: string) => void;
-  onCalculateTokens: (path: string) => Promise<number | null>;
+  onCalculateTokens: (path: string, isDirectory?: boolean) => Promise<number | null>;
   isFiltered: (entry: FileEntry) => boolean;
   showAddButton?: boolean;
   onRemoveFromChat?: (path: string) => void;
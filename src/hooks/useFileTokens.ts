@@ -1,26 +1,73 @@
 import { useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { useModelStore } from "@/stores/ModelStore";
+import { useSettingsStore } from "@/stores/SettingsStore";
+
+// Mirrors `TokenCountSummary` in src-tauri/src/filesystem/file_analysis.rs
+export interface FileTokenCount {
+  path: string;
+  tokens: number | null;
+  cached: boolean;
+  error: string | null;
+}
+
+export interface TokenCountSummary {
+  files: FileTokenCount[];
+  total: number;
+  truncated: boolean;
+}
 
 export function useFileTokens() {
   const [tokenCache, setTokenCache] = useState<Map<string, number>>(new Map());
+  const { currentModel } = useModelStore();
+  const { excludeFolders } = useSettingsStore();
 
-  const calculateTokens = useCallback(async (filePath: string): Promise<number | null> => {
-    if (tokenCache.has(filePath)) {
-      return tokenCache.get(filePath) || null;
-    }
+  const calculateBatchTokens = useCallback(
+    async (paths: string[]) => {
+      const summary = await invoke<TokenCountSummary>("calculate_tokens_batch", {
+        paths,
+        model: currentModel,
+      });
+      setTokenCache((prev) => {
+        const next = new Map(prev);
+        for (const file of summary.files) {
+          if (file.tokens !== null) next.set(`${currentModel}:${file.path}`, file.tokens);
+        }
+        return next;
+      });
+      return summary;
+    },
+    [currentModel],
+  );
 
+  const calculateDirectoryTokens = useCallback(
+    (root: string) =>
+      invoke<TokenCountSummary>("calculate_directory_tokens", {
+        root,
+        model: currentModel,
+        options: { exclude: excludeFolders },
+      }),
+    [currentModel, excludeFolders],
+  );
+
+  const calculateTokens = useCallback(async (path: string, isDirectory = false): Promise<number | null> => {
     try {
-      const tokens = await invoke<number | null>("calculate_file_tokens", {
-        filePath,
-      });
-      if (tokens !== null) {
-        setTokenCache((prev) => new Map(prev).set(filePath, tokens));
+      // Folder totals follow their files, so only the backend caches them
+      if (isDirectory) {
+        return (await calculateDirectoryTokens(path)).total;
+      }
+
+      // Counts depend on the model's tokenizer
+      const cacheKey = `${currentModel}:${path}`;
+      if (tokenCache.has(cacheKey)) {
+        return tokenCache.get(cacheKey) ?? null;
       }
-      return tokens;
+      const summary = await calculateBatchTokens([path]);
+      return summary.files[0]?.tokens ?? null;
     } catch {
       return null;
     }
-  }, [tokenCache]);
+  }, [tokenCache, currentModel, calculateBatchTokens, calculateDirectoryTokens]);
 
-  return { calculateTokens };
-}
\ No newline at end of file
+  return { calculateTokens, calculateBatchTokens, calculateDirectoryTokens };
+}
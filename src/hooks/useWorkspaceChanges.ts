@@ -0,0 +1,42 @@
+import { useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { WorkspaceChanges } from "@/types/filetree";
+
+// Calls `onChange` with each debounced batch of changes under `root`.
+export function useWorkspaceChanges(
+  root: string | undefined,
+  onChange: (changes: WorkspaceChanges) => void,
+) {
+  // Kept in a ref so re-renders don't restart the watcher
+  const onChangeRef = useRef(onChange);
+  onChangeRef.current = onChange;
+
+  useEffect(() => {
+    if (!root) return;
+    let watchedRoot: string | null = null;
+    let disposed = false;
+
+    const unlistenPromise = listen<WorkspaceChanges>("workspace-changed", (event) => {
+      if (event.payload.root === watchedRoot) {
+        onChangeRef.current(event.payload);
+      }
+    });
+    invoke<string>("watch_workspace", { root })
+      .then((canonical) => {
+        watchedRoot = canonical;
+        if (disposed) {
+          invoke("unwatch_workspace", { root }).catch(() => {});
+        }
+      })
+      .catch((err) => console.warn("Failed to watch workspace:", err));
+
+    return () => {
+      disposed = true;
+      unlistenPromise.then((unlisten) => unlisten());
+      if (watchedRoot) {
+        invoke("unwatch_workspace", { root }).catch(() => {});
+      }
+    };
+  }, [root]);
+}
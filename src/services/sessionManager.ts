@@ -51,6 +51,8 @@ class SessionManager {
           approval_policy: config.approvalPolicy,
           sandbox_mode: config.sandboxMode,
           api_key: authToken, // This will be either OAuth token or API key
+          profile: config.profile || null,
+          credential_source: config.credentialSource || null,
         },
       });
 
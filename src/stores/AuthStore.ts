@@ -1,6 +1,7 @@
 import { create } from "zustand";
 import { persist } from "zustand/middleware";
 import { safeInvoke } from "@/utils/tauriMock";
+import type { AuthStatus } from "@/types/auth";
 
 export interface AuthState {
   type: 'none' | 'api_key' | 'chatgpt';
@@ -37,43 +38,32 @@ export const useAuthStore = create<AuthStore>()(
             auth: { ...state.auth, isLoading: true, error: undefined } 
           }));
 
-          const status = await safeInvoke('get_auth_status') as string | null;
+          const status = await safeInvoke('get_auth_status') as AuthStatus | null;
           console.log('✅ [AuthStore] Auth status received:', status);
           
           if (!status) {
             set({ auth: { type: 'none', isLoading: false } });
-          } else if (status === 'api_key') {
+          } else if (status.state === 'locked') {
+            set({
+              auth: {
+                type: 'none',
+                isLoading: false,
+                error: 'Credentials are encrypted; unlock them to continue',
+              },
+            });
+          } else if (status.mode === 'api_key') {
             set({ auth: { type: 'api_key', isLoading: false } });
-          } else if (status.startsWith('chatgpt:')) {
-            const parts = status.split(':');
-            if (parts.length === 3) {
-              const [, email, plan] = parts;
-              set({ 
-                auth: { 
-                  type: 'chatgpt', 
-                  email, 
-                  plan, 
-                  valid: true, 
-                  isLoading: false 
-                } 
-              });
-            } else if (parts[1] === 'invalid') {
-              set({ 
-                auth: { 
-                  type: 'chatgpt', 
-                  valid: false, 
-                  isLoading: false 
-                } 
-              });
-            } else {
-              set({ 
-                auth: { 
-                  type: 'chatgpt', 
-                  valid: true, 
-                  isLoading: false 
-                } 
-              });
-            }
+          } else {
+            set({ 
+              auth: { 
+                type: 'chatgpt', 
+                email: status.email ?? undefined, 
+                plan: status.plan ?? undefined, 
+                valid: status.state === 'valid', 
+                error: status.state === 'expired' ? 'ChatGPT session expired, please log in again' : undefined,
+                isLoading: false 
+              } 
+            });
           }
         } catch (error: any) {
           console.error('❌ [AuthStore] Failed to check auth status:', error);
@@ -119,8 +109,8 @@ export const useAuthStore = create<AuthStore>()(
           const pollForCompletion = () => {
             const interval = setInterval(async () => {
               try {
-                const newStatus = await safeInvoke('get_auth_status') as string | null;
-                if (newStatus && newStatus.startsWith('chatgpt:')) {
+                const newStatus = await safeInvoke('get_auth_status') as AuthStatus | null;
+                if (newStatus?.mode === 'chatgpt' && newStatus.state === 'valid') {
                   clearInterval(interval);
                   await get().checkAuthStatus();
                 }
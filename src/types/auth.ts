@@ -0,0 +1,16 @@
+// Mirrors `AuthStatus` in src-tauri/src/auth/auth_storage.rs
+export type AuthMode = 'api_key' | 'chatgpt';
+export type AuthSource = 'env_var' | 'file' | 'encrypted';
+export type CredentialState = 'valid' | 'expired' | 'invalid' | 'locked';
+
+export interface AuthStatus {
+  // Null while the encrypted credential store is locked
+  mode: AuthMode | null;
+  source: AuthSource;
+  state: CredentialState;
+  email: string | null;
+  plan: string | null;
+  account_id: string | null;
+  token_expires_at: string | null;
+  last_refresh: string | null;
+}
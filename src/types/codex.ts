@@ -70,8 +70,18 @@ export interface CodexConfig {
   approvalPolicy: 'untrusted' | 'on-failure' | 'on-request' | 'never';
   sandboxMode: 'read-only' | 'workspace-write' | 'danger-full-access';
   codexPath?: string;
+  profile?: string; // Named profile from config.toml, session fields override it
+  credentialSource?: CredentialSource;
 }
 
+// Where a session's API key comes from (see credential_resolver.rs)
+export type CredentialSource =
+  | { type: 'inline' }
+  | { type: 'account'; name: string }
+  | { type: 'env'; var: string }
+  | { type: 'command'; command: string; args?: string[] }
+  | { type: 'file'; path: string };
+
 export const DEFAULT_CONFIG: CodexConfig = {
   workingDirectory: '',
   model: 'llama3.2',
@@ -81,14 +91,31 @@ export const DEFAULT_CONFIG: CodexConfig = {
   sandboxMode: 'workspace-write',
 };
 
+export interface McpServerOptions {
+  startup_timeout_ms?: number;
+  tool_timeout_sec?: number;
+  enabled?: boolean;
+}
+
 export type McpServerConfig = 
-  | {
+  | ({
       type: 'stdio';
       command: string;
       args: string[];
       env?: Record<string, string>;
-    }
-  | {
+      cwd?: string;
+    } & McpServerOptions)
+  | ({
       type: 'http';
       url: string;
-    };
+      bearer_token_env_var?: string; // Name of the env var holding the token
+      http_headers?: Record<string, string>;
+      env_http_headers?: Record<string, string>; // Header name -> env var name
+    } & McpServerOptions);
+
+// Mirrors `RunningSession` in src-tauri/src/services/codex.rs
+export interface RunningSession {
+  session_id: string;
+  // Where the session's API key came from (e.g. `env:WORK_KEY`), never the key
+  credential_source: string | null;
+}
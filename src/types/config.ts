@@ -7,6 +7,8 @@ export interface ModelProvider {
 export interface Profile {
   model_provider: string;
   model: string;
+  approval_policy?: string;
+  sandbox_mode?: string;
 }
 
 export interface ProviderConfig {
@@ -0,0 +1,75 @@
+// Mirrors the types in src-tauri/src/filesystem/file_io.rs
+export interface FileContent {
+  content: string;
+  // Null when the file can't be edited: not UTF-8 text, or only partly read
+  version: string | null;
+  // Milliseconds since the epoch
+  modified: number | null;
+  size: number;
+  // Bytes `content` covers; continue with `read_file_range` from here
+  length: number;
+  mime_type: string;
+  // Null for binary files, whose `content` is a hex preview
+  encoding: TextEncoding | null;
+}
+
+export interface WrittenFile {
+  version: string;
+  modified: number | null;
+}
+
+export type FileWriteError =
+  | {
+      kind: 'conflict';
+      path: string;
+      expected_version: string;
+      current_version: string | null;
+    }
+  | { kind: 'not_allowed'; message: string }
+  | { kind: 'denied'; path: string; pattern: string }
+  // Retry with `confirmOutsideWorkspace: true` once the user agrees
+  | { kind: 'outside_workspace'; path: string; trusted_roots: string[] }
+  | { kind: 'io'; message: string };
+
+// Mirrors the chunk types in src-tauri/src/filesystem/file_range.rs
+export type TextEncoding = 'utf8' | 'utf16_le' | 'utf16_be' | 'latin1';
+
+interface FileChunkBase {
+  path: string;
+  size: number;
+  // Bytes actually covered, nudged to character boundaries
+  offset: number;
+  length: number;
+  eof: boolean;
+  mime_type: string;
+  // Line reads only (1-based)
+  first_line: number | null;
+  line_count: number | null;
+}
+
+export type FileChunk = FileChunkBase &
+  (
+    | { kind: 'text'; encoding: TextEncoding; text: string }
+    | { kind: 'binary'; hex_preview: string }
+  );
+
+export interface FileTail {
+  tail_id: string;
+  chunk: FileChunk;
+}
+
+// Payload of `file-tail`
+export interface FileTailEvent {
+  tail_id: string;
+  text: string;
+  offset: number;
+  // The file was truncated or rotated and `text` starts it over
+  reset: boolean;
+}
+
+// Mirrors `WritePolicy` in src-tauri/src/filesystem/write_policy.rs
+export interface WritePolicy {
+  deny: string[];
+  // Exceptions to `deny`, e.g. `.env.example`
+  allow: string[];
+}
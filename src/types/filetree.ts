@@ -0,0 +1,67 @@
+// Mirrors `FileEntry` and the tree types in src-tauri/src/filesystem
+export type GitFileStatus =
+  | 'modified'
+  | 'staged'
+  | 'added'
+  | 'untracked'
+  | 'deleted'
+  | 'renamed'
+  | 'conflicted';
+
+export interface FileEntry {
+  name: string;
+  path: string;
+  is_directory: boolean;
+  size?: number | null;
+  extension?: string | null;
+  git_status?: GitFileStatus;
+  // Unset for directories below the requested depth
+  children?: FileEntry[];
+}
+
+export interface TreeOptions {
+  hide_dotfiles?: boolean;
+  respect_gitignore?: boolean;
+  exclude?: string[];
+  git_status?: boolean;
+  chunk_size?: number;
+}
+
+export interface DirectoryTree {
+  root: string;
+  entries: FileEntry[];
+  // The caller's stream id when entries arrive as `directory-tree-chunk` events
+  stream_id: string | null;
+}
+
+export interface DirectoryTreeChunk {
+  stream_id: string;
+  entries: FileEntry[];
+  done: boolean;
+  error: string | null;
+}
+
+// Mirrors FileMatch in src-tauri/src/services/file_finder.rs
+export interface FileMatch {
+  path: string;
+  relative_path: string;
+  score: number;
+  positions: number[];
+}
+
+// Mirrors the watcher types in src-tauri/src/filesystem/watcher.rs
+export type FsChangeKind = 'created' | 'modified' | 'deleted' | 'renamed';
+
+export interface FsChange {
+  kind: FsChangeKind;
+  path: string;
+  // Set for renames
+  old_path?: string;
+}
+
+// Payload of `workspace-changed`
+export interface WorkspaceChanges {
+  root: string;
+  changes: FsChange[];
+  git_changed: boolean;
+}
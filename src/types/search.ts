@@ -0,0 +1,44 @@
+// Mirrors the search types in src-tauri/src/filesystem/search.rs
+export interface SearchOptions {
+  case_sensitive?: boolean;
+  whole_word?: boolean;
+  regex?: boolean;
+  include?: string[];
+  exclude?: string[];
+  context_lines?: number;
+  include_hidden?: boolean;
+  respect_gitignore?: boolean;
+  max_results?: number;
+}
+
+export interface SearchLine {
+  line_number: number;
+  text: string;
+  kind: 'match' | 'context';
+  // [start, end) character offsets into `text`
+  ranges: [number, number][];
+}
+
+export interface FileSearchResult {
+  path: string;
+  relative_path: string;
+  lines: SearchLine[];
+  match_count: number;
+}
+
+// Payload of `search-results`
+export interface SearchResultsEvent {
+  search_id: string;
+  files: FileSearchResult[];
+}
+
+// Payload of `search-complete`
+export interface SearchCompleteEvent {
+  search_id: string;
+  files_searched: number;
+  files_matched: number;
+  match_count: number;
+  truncated: boolean;
+  cancelled: boolean;
+  error: string | null;
+}
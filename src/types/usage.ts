@@ -0,0 +1,44 @@
+// Mirrors the report types in src-tauri/src/services/usage.rs
+export type UsageGroupBy = 'turn' | 'session' | 'model' | 'project' | 'day';
+
+export interface TokenUsage {
+  input_tokens: number;
+  cached_input_tokens: number;
+  output_tokens: number;
+  reasoning_output_tokens: number;
+  total_tokens: number;
+}
+
+export interface UsageRange {
+  since?: string | null;
+  until?: string | null;
+}
+
+export interface UsageTotals {
+  usage: TokenUsage;
+  turns: number;
+  cost_usd: number;
+  unpriced_turns: number;
+}
+
+export interface UsageGroup extends UsageTotals {
+  key: string;
+}
+
+export interface UsageReport {
+  range: UsageRange;
+  group_by: UsageGroupBy;
+  groups: UsageGroup[];
+  total: UsageTotals;
+}
+
+// Payload of the `context-usage` and `context-window-warning` events
+export interface ContextUsage {
+  session_id: string;
+  model: string;
+  tokens_in_context: number;
+  context_window: number | null;
+  max_output_tokens: number | null;
+  percent_used: number | null;
+  threshold: number | null;
+}